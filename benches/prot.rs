@@ -0,0 +1,55 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ziel::{logic, prot};
+
+fn messages() -> [prot::RawMessage; 3] {
+    [
+        prot::RawMessage::from(prot::ClientMessage::ShipPositions(logic::randomplacement())),
+        prot::RawMessage::from(prot::ClientMessage::Target(
+            logic::Position::fromcoords(4, 7).unwrap(),
+        )),
+        prot::RawMessage::from(prot::ServerMessage::InformTargetHitOpp(
+            logic::Position::fromcoords(9, 9).unwrap(),
+            Some(5),
+        )),
+    ]
+}
+
+fn encode(c: &mut Criterion) {
+    let messages = messages();
+    c.bench_function("encode frame", |b| {
+        b.iter(|| {
+            for message in &messages {
+                black_box(prot::encodeframe(black_box(message)));
+            }
+        })
+    });
+}
+
+fn decode(c: &mut Criterion) {
+    let frames = messages().map(|message| prot::encodeframe(&message));
+    c.bench_function("decode frame", |b| {
+        b.iter(|| {
+            for frame in &frames {
+                black_box(prot::decodeframe(black_box(frame)).unwrap());
+            }
+        })
+    });
+}
+
+fn shippositions(c: &mut Criterion) {
+    let ships = logic::randomplacement();
+    c.bench_function("ship positions round trip", |b| {
+        b.iter(|| {
+            let frame = prot::encodeframe(&prot::RawMessage::from(
+                prot::ClientMessage::ShipPositions(black_box(ships)),
+            ));
+            let raw = prot::decodeframe(&frame).unwrap();
+            black_box(prot::ClientMessage::try_from(raw).unwrap())
+        })
+    });
+}
+
+criterion_group!(benches, encode, decode, shippositions);
+criterion_main!(benches);
@@ -1,8 +1,21 @@
-use std::error;
+use rand::{seq::IndexedRandom, Rng};
+use std::{collections::VecDeque, error, fmt, str};
 
-use tokio::{io, net};
+use tokio::{io, time};
 
-use crate::{logic, prot};
+use crate::{
+    conn::{Connection, Endpoint},
+    logic, prot,
+};
+
+pub use crate::prot::{Capabilities, LeaveReason, ServerInfo};
+
+/// how long a dropped connection is retried before the game is given up
+const RECONNECTWINDOW: time::Duration = time::Duration::from_secs(30);
+/// pause between two reconnection attempts
+const RECONNECTINTERVAL: time::Duration = time::Duration::from_secs(1);
+/// how often the interface gets a turn while the server is quiet
+const IDLEINTERVAL: time::Duration = time::Duration::from_millis(50);
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error<I: UI> {
@@ -12,42 +25,332 @@ pub enum Error<I: UI> {
     Interface(#[from] UIError<I::Error>),
     #[error("networking error: {0}")]
     Networking(#[from] io::Error),
+    #[error("logic error: {0}")]
+    Logic(#[from] logic::Error),
+    #[error("fleet mismatch: placed ships of lengths {ours:?}, server plays {theirs:?}")]
+    FleetMismatch { ours: Vec<u8>, theirs: Vec<u8> },
 }
 
+#[derive(Clone, Copy)]
 pub struct ClientInfo<'i> {
-    pub ships: &'i [logic::Ship; 5],
-    pub selfhits: &'i [[Option<logic::AttackInfo>; 10]; 10],
-    pub opphits: &'i [[Option<logic::AttackInfo>; 10]; 10],
+    pub ships: &'i [logic::Ship],
+    pub selfhits: &'i logic::HitGrid,
+    pub opphits: &'i logic::HitGrid,
 
+    pub server: &'i ServerInfo,
+    /// the name this player goes by, if they gave one
+    pub name: Option<&'i str>,
+    /// the name the opponent goes by, if they gave one
+    pub oppname: Option<&'i str>,
     pub message: &'i [Message],
+    pub shots: &'i [Shot],
+    pub summary: Option<&'i logic::GameSummary>,
+    /// opponent ships not sunken yet
+    pub oppremaining: u8,
+    /// own ships not sunken yet
+    pub selfremaining: u8,
+    /// who selects the next target, `None` while nobody is asked
+    pub turn: Option<Origin>,
+    /// time left to select a target, if the server runs a turn clock
+    pub remaining: Option<time::Duration>,
+    /// why the opponent left, if the game was not decided on the board
+    pub oppleft: Option<LeaveReason>,
+    /// time the dropped opponent has left to come back
+    pub oppaway: Option<time::Duration>,
+    /// a pause waiting for an answer or holding the game
+    pub pause: Option<PauseState>,
+    /// chat in both directions, oldest first
+    pub chat: &'i [(Origin, String)],
+    /// watching from the first player's seat, `ships` are not theirs
+    pub spectating: bool,
+    /// both fleets, the first player's first, shown only to a spectator who
+    /// gave the admin password
+    pub fleets: Option<&'i [logic::Ships; 2]>,
+    /// games decided on this connection, counting every rematch
+    pub score: MatchScore,
+}
+
+/// where a pause the players have to agree on stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseState {
+    /// asked for by one side, the other has yet to answer
+    Offered(Origin),
+    /// both agreed, the game holds for at most this much longer
+    Held(time::Duration),
+}
+
+/// games won and lost against the same opponent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u8,
+    pub losses: u8,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Message {
     SuccessfullyConnected,
+    /// the connection dropped and was taken up again
+    Reconnected,
     SelectTarget,
     WaitForOpp,
     ShipHit,
-    ShipSunken,
+    /// with the length of the sunken ship
+    ShipSunken(u8),
     ShipMissed,
     OppShipHit,
-    OppShipSunken,
+    OppShipSunken(u8),
     OppShipMissed,
 }
 
+/// who acted in a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Own,
+    Opponent,
+}
+
+impl Message {
+    /// the log entry for a shot, `sunken` with the length of the ship it sank
+    fn shot(incoming: bool, info: logic::AttackInfo, sunken: Option<u8>) -> Message {
+        match (incoming, info, sunken) {
+            (true, logic::AttackInfo::Miss, _) => Message::ShipMissed,
+            (true, _, Some(len)) => Message::ShipSunken(len),
+            (true, _, None) => Message::ShipHit,
+            (false, logic::AttackInfo::Miss, _) => Message::OppShipMissed,
+            (false, _, Some(len)) => Message::OppShipSunken(len),
+            (false, _, None) => Message::OppShipHit,
+        }
+    }
+
+    /// `None` for status messages nobody acted in
+    pub fn origin(self) -> Option<Origin> {
+        match self {
+            Message::SuccessfullyConnected
+            | Message::Reconnected
+            | Message::SelectTarget
+            | Message::WaitForOpp => None,
+            Message::ShipHit | Message::ShipSunken(_) | Message::ShipMissed => {
+                Some(Origin::Opponent)
+            }
+            Message::OppShipHit | Message::OppShipSunken(_) | Message::OppShipMissed => {
+                Some(Origin::Own)
+            }
+        }
+    }
+}
+
+/// a resolved shot, from the perspective of this client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shot {
+    /// fired by the opponent at the own board
+    pub incoming: bool,
+    pub pos: logic::Position,
+    pub info: logic::AttackInfo,
+}
+
+/// rebuilds `(selfhits, opphits)` after every shot, starting with the empty boards
+pub fn replaystates(shots: &[Shot]) -> Result<Vec<(logic::HitGrid, logic::HitGrid)>, logic::Error> {
+    let mut states = Vec::with_capacity(shots.len() + 1);
+    let (mut selfhits, mut opphits) = (logic::HitGrid::new(), logic::HitGrid::new());
+    states.push((selfhits, opphits));
+    for shot in shots {
+        if shot.incoming {
+            selfhits.apply(shot.pos, shot.info)?;
+        } else {
+            opphits.apply(shot.pos, shot.info)?;
+        }
+        states.push((selfhits, opphits));
+    }
+    Ok(states)
+}
+
+/// a recorded game from one player's seat, stepped through without a server
+pub struct Replay {
+    ships: logic::Ships,
+    shots: Vec<Shot>,
+    /// grids and opponent and own ships afloat after every shot, starting
+    /// empty
+    states: Vec<(logic::HitGrid, logic::HitGrid, u8, u8)>,
+    message: Vec<Message>,
+    server: ServerInfo,
+    summary: Option<logic::GameSummary>,
+    victory: Option<bool>,
+}
+
+impl Replay {
+    /// replays `record` on fresh boards, `player` is 0 for the first one
+    pub fn new(record: &prot::GameRecord, player: u8) -> Result<Replay, logic::Error> {
+        let mut boards = record.fleets.map(logic::Board::new);
+        let mut shots = Vec::with_capacity(record.shots.len());
+        let mut message = Vec::with_capacity(record.shots.len());
+        let fleetlen = |seat: bool| record.fleets[usize::from(seat)].asslice().len() as u8;
+        let mut states = vec![(
+            logic::HitGrid::new(),
+            logic::HitGrid::new(),
+            fleetlen(player == 0),
+            fleetlen(player != 0),
+        )];
+        for &(shooter, pos, info) in &record.shots {
+            let board = &mut boards[usize::from(shooter == 0)];
+            if board.target(pos) != Some(info) {
+                return Err(logic::Error::ConflictingAttackInfo);
+            }
+            let sunken = board.sunkenship(pos).map(|(_, len)| len);
+            let incoming = shooter != player;
+            message.push(Message::shot(incoming, info, sunken));
+            shots.push(Shot {
+                incoming,
+                pos,
+                info,
+            });
+            let (mut selfhits, mut opphits, mut oppremaining, mut selfremaining) =
+                states[states.len() - 1];
+            if incoming {
+                selfhits.apply(pos, info)?;
+                if sunken.is_some() {
+                    selfremaining = selfremaining.saturating_sub(1);
+                }
+            } else {
+                opphits.apply(pos, info)?;
+                if sunken.is_some() {
+                    oppremaining = oppremaining.saturating_sub(1);
+                }
+            }
+            states.push((selfhits, opphits, oppremaining, selfremaining));
+        }
+        let [board1, board2] = &boards;
+        let summary = record.winner.map(|winner| logic::GameSummary {
+            winner,
+            turns: record.shots.len() as u16,
+            stats: [board2.stats(), board1.stats()],
+        });
+        Ok(Replay {
+            ships: record.fleets[usize::from(player != 0)],
+            shots,
+            states,
+            message,
+            server: ServerInfo::default(),
+            summary,
+            victory: record.winner.map(|winner| winner == player),
+        })
+    }
+
+    /// the last step, after every shot
+    pub fn laststep(&self) -> usize {
+        self.states.len() - 1
+    }
+
+    /// whether the seated player won, `None` if the game was not decided
+    pub fn victory(&self) -> Option<bool> {
+        self.victory
+    }
+
+    /// the game after `step` shots, with the summary on the last step
+    pub fn info(&self, step: usize) -> ClientInfo<'_> {
+        let step = step.min(self.laststep());
+        let (selfhits, opphits, oppremaining, selfremaining) = &self.states[step];
+        ClientInfo {
+            ships: self.ships.asslice(),
+            selfhits,
+            opphits,
+            server: &self.server,
+            name: None,
+            oppname: None,
+            message: &self.message[..step],
+            shots: &self.shots[..step],
+            summary: self.summary.as_ref().filter(|_| step == self.laststep()),
+            oppremaining: *oppremaining,
+            selfremaining: *selfremaining,
+            // whoever fires the next shot of the record
+            turn: self.shots.get(step).map(|shot| {
+                if shot.incoming {
+                    Origin::Opponent
+                } else {
+                    Origin::Own
+                }
+            }),
+            remaining: None,
+            oppleft: None,
+            oppaway: None,
+            pause: None,
+            chat: &[],
+            spectating: false,
+            fleets: None,
+            score: MatchScore::default(),
+        }
+    }
+}
+
+/// running hit rate in percent after each own shot
+pub fn accuracytrend(shots: &[Shot]) -> Vec<u8> {
+    shots
+        .iter()
+        .filter(|shot| !shot.incoming)
+        .scan((0u32, 0u32), |(hits, fired), shot| {
+            *fired += 1;
+            if let logic::AttackInfo::Hit(_) = shot.info {
+                *hits += 1;
+            }
+            Some((*hits * 100 / *fired) as u8)
+        })
+        .collect()
+}
+
 pub struct Client {
     ships: logic::Ships,
-    selfhits: [[Option<logic::AttackInfo>; 10]; 10],
-    opphits: [[Option<logic::AttackInfo>; 10]; 10],
+    selfhits: logic::HitGrid,
+    opphits: logic::HitGrid,
 
-    stream: net::TcpStream,
+    stream: Connection,
+    /// where the seat is taken back after a dropped connection, `None` if
+    /// the connection cannot be opened again
+    endpoint: Option<Endpoint>,
+    /// presented to take the seat back after a dropped connection, 0 if the
+    /// server does not allow it
+    token: u64,
+    server: ServerInfo,
+    /// sent with the handshake
+    name: Option<prot::PlayerName>,
+    /// received with the handshake, if the opponent gave one
+    oppname: Option<prot::PlayerName>,
+    capabilities: Capabilities,
     message: Vec<Message>,
+    shots: Vec<Shot>,
+    summary: Option<logic::GameSummary>,
+    oppremaining: u8,
+    selfremaining: u8,
+    turn: Option<Origin>,
+    /// a shot outcome contradicted the grids, ask for a snapshot next turn
+    desynced: bool,
+    deadline: Option<time::Instant>,
+    oppleft: Option<LeaveReason>,
+    /// the game waits for the dropped opponent until then
+    oppaway: Option<time::Instant>,
+    /// who asked for a pause not answered yet
+    pauseoffer: Option<Origin>,
+    /// the game holds until then, the turn clock stands still
+    pausedtill: Option<time::Instant>,
+    chat: Vec<(Origin, String)>,
+    spectating: bool,
+    /// revealed to an admin spectator for the game being watched
+    fleets: Option<[logic::Ships; 2]>,
+    /// read while looking for relayed chat, handled before anything else
+    early: Option<prot::ServerMessage>,
+    score: MatchScore,
+    /// losses after which every rematch is declined without asking
+    resignafter: Option<u8>,
 }
 
 #[derive(thiserror::Error, Debug)]
 #[error(transparent)]
 pub struct UIError<E: error::Error + 'static>(#[from] E);
 
+impl<E: error::Error + 'static> UIError<E> {
+    pub fn inner(&self) -> &E {
+        &self.0
+    }
+}
+
 pub trait UI {
     type Error: error::Error + 'static;
 
@@ -56,103 +359,1200 @@ pub trait UI {
     fn selecttarget(&mut self, info: ClientInfo) -> Result<logic::Position, UIError<Self::Error>>;
     fn displayvictory(&mut self, info: ClientInfo) -> Result<(), UIError<Self::Error>>;
     fn displayloss(&mut self, info: ClientInfo) -> Result<(), UIError<Self::Error>>;
+
+    /// called once an opponent shot on the own board has been acknowledged,
+    /// so implementations may take their time without stalling the server
+    fn animateincoming(
+        &mut self,
+        _info: ClientInfo,
+        _pos: logic::Position,
+    ) -> Result<(), UIError<Self::Error>> {
+        Ok(())
+    }
+
+    /// called once the server refused the ship positions; `buildboard` is
+    /// called again right before new positions are sent
+    fn invalidships(
+        &mut self,
+        _info: ClientInfo,
+        _reason: logic::Error,
+    ) -> Result<(), UIError<Self::Error>> {
+        Ok(())
+    }
+
+    /// `count` distinct targets fired at once in salvo games, by default
+    /// selected one after another
+    fn selecttargets(
+        &mut self,
+        info: ClientInfo,
+        count: u8,
+    ) -> Result<Vec<logic::Position>, UIError<Self::Error>> {
+        let mut targets = Vec::with_capacity(count.into());
+        while targets.len() < count.into() {
+            let target = self.selecttarget(info)?;
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        Ok(targets)
+    }
+
+    /// asked right after every target selection and while the opponent
+    /// moves; `true` concedes the game, instead of firing at what was
+    /// selected if asked after a selection
+    fn surrendering(&mut self) -> bool {
+        false
+    }
+
+    /// chat typed since the last call, sent to the opponent right away
+    fn takechat(&mut self) -> Option<String> {
+        None
+    }
+
+    /// asked along with `takechat` while a game runs; `Some(true)` asks for
+    /// a pause or accepts the opponent's, `Some(false)` declines it or ends
+    /// the pause holding
+    fn pausing(&mut self) -> Option<bool> {
+        None
+    }
+
+    /// asked right after every target selection; `true` if the selection was
+    /// cut short to hand over chat or to show what came in meanwhile, and is
+    /// to be asked again; such interfaces keep a salvo across calls
+    fn yielded(&mut self) -> bool {
+        false
+    }
+
+    /// called while the server is quiet during a game, to keep the screen
+    /// live and take chat; should not block for long
+    fn idle(&mut self, _info: ClientInfo) -> Result<(), UIError<Self::Error>> {
+        Ok(())
+    }
+
+    /// called once the server rejected the target just selected; a new
+    /// target is requested right after
+    fn invalidtarget(
+        &mut self,
+        _info: ClientInfo,
+        _pos: logic::Position,
+    ) -> Result<(), UIError<Self::Error>> {
+        Ok(())
+    }
+
+    /// asked on the end screen of a decided game; once both players accept,
+    /// ships are placed for another game on the same connection
+    fn rematch(&mut self, _info: ClientInfo) -> Result<bool, UIError<Self::Error>> {
+        Ok(false)
+    }
+
+    /// called after the game ended and the connection was closed; may block
+    /// for as long as the end screen should stay up
+    fn finish(&mut self, _info: ClientInfo, _victory: bool) -> Result<(), UIError<Self::Error>> {
+        Ok(())
+    }
+}
+
+/// how hard the fleet a bot places is to find
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    /// ships anywhere, now and then clustered and quicker to sink
+    #[default]
+    Easy,
+    /// ships spread out, never touching, not even diagonally
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 2] = [Difficulty::Easy, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown difficulty `{0}`; expected easy or hard")]
+pub struct UnknownDifficulty(pub String);
+
+impl str::FromStr for Difficulty {
+    type Err = UnknownDifficulty;
+
+    fn from_str(name: &str) -> Result<Difficulty, UnknownDifficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| UnknownDifficulty(name.to_owned()))
+    }
+}
+
+/// UI playing on its own; places a random fleet, fires at random until a
+/// ship is hit and then probes around the hits until it sinks
+#[derive(Debug, Default)]
+pub struct BotUI {
+    rematches: bool,
+    fleet: logic::FleetSpec,
+    difficulty: Difficulty,
+}
+
+impl BotUI {
+    pub fn new() -> BotUI {
+        BotUI::default()
+    }
+
+    /// accept every rematch instead of declining
+    pub fn rematches(mut self, accept: bool) -> BotUI {
+        self.rematches = accept;
+        self
+    }
+
+    /// places `fleet` instead of the classic one
+    pub fn fleet(mut self, fleet: logic::FleetSpec) -> BotUI {
+        self.fleet = fleet;
+        self
+    }
+
+    /// spreads the fleet out on `Difficulty::Hard`
+    pub fn difficulty(mut self, difficulty: Difficulty) -> BotUI {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// a fleet placed as hard to find as the difficulty asks, legal under
+    /// `fleet` either way; `None` unless the fleet has five ships
+    fn place(&self, rng: &mut impl Rng) -> Option<logic::Ships> {
+        match self.difficulty {
+            Difficulty::Easy => self.fleet.random(rng),
+            Difficulty::Hard => self.fleet.clone().notouch(true).random(rng),
+        }
+    }
+
+    /// next target besides those already `taken` this turn
+    fn pick(hits: &logic::HitGrid, taken: &[logic::Position]) -> Option<logic::Position> {
+        let open = |x: u8, y: u8| {
+            logic::Position::fromcoords(x, y)
+                .filter(|&pos| hits.get(pos).is_none() && !taken.contains(&pos))
+        };
+        // unshot neighbours of hits on ships still afloat
+        let probes: Vec<_> = (0..logic::BOARDSIZE)
+            .flat_map(|y| (0..logic::BOARDSIZE).map(move |x| (x, y)))
+            .filter(|&(x, y)| hits[y as usize][x as usize] == Some(logic::AttackInfo::Hit(false)))
+            .flat_map(|(x, y)| {
+                [
+                    x.checked_sub(1).and_then(|x| open(x, y)),
+                    open(x + 1, y),
+                    y.checked_sub(1).and_then(|y| open(x, y)),
+                    open(x, y + 1),
+                ]
+            })
+            .flatten()
+            .collect();
+        let candidates = if probes.is_empty() {
+            logic::legaltargets(hits)
+                .into_iter()
+                .filter(|pos| !taken.contains(pos))
+                .collect()
+        } else {
+            probes
+        };
+        candidates.choose(&mut rand::rng()).copied()
+    }
+}
+
+impl UI for BotUI {
+    type Error = io::Error;
+
+    fn buildboard(&mut self) -> Result<logic::Ships, UIError<io::Error>> {
+        Ok(self
+            .place(&mut rand::rng())
+            .ok_or(io::Error::other("fleet does not have five ships"))?)
+    }
+
+    fn displayboard(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        Ok(())
+    }
+
+    fn selecttarget(&mut self, info: ClientInfo) -> Result<logic::Position, UIError<io::Error>> {
+        Ok(BotUI::pick(info.opphits, &[]).ok_or(io::Error::other("no target left"))?)
+    }
+
+    fn selecttargets(
+        &mut self,
+        info: ClientInfo,
+        count: u8,
+    ) -> Result<Vec<logic::Position>, UIError<io::Error>> {
+        let mut targets = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            targets.push(
+                BotUI::pick(info.opphits, &targets).ok_or(io::Error::other("no target left"))?,
+            );
+        }
+        Ok(targets)
+    }
+
+    fn displayvictory(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        Ok(())
+    }
+
+    fn displayloss(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        Ok(())
+    }
+
+    fn rematch(&mut self, _info: ClientInfo) -> Result<bool, UIError<io::Error>> {
+        Ok(self.rematches)
+    }
+}
+
+/// what a `ScriptedUI` saw on its end screen
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptedEnd {
+    pub victory: bool,
+    pub selfhits: logic::HitGrid,
+    pub opphits: logic::HitGrid,
+    pub summary: Option<logic::GameSummary>,
+}
+
+/// headless UI placing a fixed fleet and firing at queued targets, for
+/// driving whole games without a terminal
+pub struct ScriptedUI {
+    ships: logic::Ships,
+    targets: VecDeque<logic::Position>,
+    rejected: Vec<logic::Position>,
+    incoming: Vec<logic::Position>,
+    end: Option<ScriptedEnd>,
+}
+
+impl ScriptedUI {
+    pub fn new(
+        ships: logic::Ships,
+        targets: impl IntoIterator<Item = logic::Position>,
+    ) -> ScriptedUI {
+        ScriptedUI {
+            ships,
+            targets: targets.into_iter().collect(),
+            rejected: Vec::new(),
+            incoming: Vec::new(),
+            end: None,
+        }
+    }
+
+    /// the end screen, once the game is over
+    pub fn end(&self) -> Option<&ScriptedEnd> {
+        self.end.as_ref()
+    }
+
+    /// targets the server refused, in order
+    pub fn rejected(&self) -> &[logic::Position] {
+        &self.rejected
+    }
+
+    /// opponent shots at the own board, in order
+    pub fn incoming(&self) -> &[logic::Position] {
+        &self.incoming
+    }
+
+    fn record(&mut self, victory: bool, info: ClientInfo) {
+        self.end = Some(ScriptedEnd {
+            victory,
+            selfhits: *info.selfhits,
+            opphits: *info.opphits,
+            summary: info.summary.copied(),
+        });
+    }
+}
+
+impl UI for ScriptedUI {
+    type Error = io::Error;
+
+    fn buildboard(&mut self) -> Result<logic::Ships, UIError<io::Error>> {
+        Ok(self.ships)
+    }
+
+    fn displayboard(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        Ok(())
+    }
+
+    fn selecttarget(&mut self, _info: ClientInfo) -> Result<logic::Position, UIError<io::Error>> {
+        Ok(self
+            .targets
+            .pop_front()
+            .ok_or(io::Error::other("script exhausted"))?)
+    }
+
+    fn invalidtarget(
+        &mut self,
+        _info: ClientInfo,
+        pos: logic::Position,
+    ) -> Result<(), UIError<io::Error>> {
+        self.rejected.push(pos);
+        Ok(())
+    }
+
+    fn animateincoming(
+        &mut self,
+        _info: ClientInfo,
+        pos: logic::Position,
+    ) -> Result<(), UIError<io::Error>> {
+        self.incoming.push(pos);
+        Ok(())
+    }
+
+    fn displayvictory(&mut self, info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        self.record(true, info);
+        Ok(())
+    }
+
+    fn displayloss(&mut self, info: ClientInfo) -> Result<(), UIError<io::Error>> {
+        self.record(false, info);
+        Ok(())
+    }
 }
 
 impl Client {
-    fn info(&self) -> ClientInfo {
+    fn info(&self) -> ClientInfo<'_> {
         ClientInfo {
-            ships: self.ships.asarray(),
+            ships: self.ships.asslice(),
             selfhits: &self.selfhits,
             opphits: &self.opphits,
+            server: &self.server,
+            name: self.name.as_ref().map(prot::PlayerName::name),
+            oppname: self.oppname.as_ref().map(prot::PlayerName::name),
             message: &self.message,
+            shots: &self.shots,
+            summary: self.summary.as_ref(),
+            oppremaining: self.oppremaining,
+            selfremaining: self.selfremaining,
+            turn: self.turn,
+            remaining: self.remaining(time::Instant::now()),
+            oppleft: self.oppleft,
+            oppaway: self
+                .oppaway
+                .map(|deadline| deadline.saturating_duration_since(time::Instant::now())),
+            pause: self.pause(time::Instant::now()),
+            chat: &self.chat,
+            spectating: self.spectating,
+            fleets: self.fleets.as_ref(),
+            score: self.score,
+        }
+    }
+
+    /// name and version announced by the connected server
+    pub fn server(&self) -> &ServerInfo {
+        &self.server
+    }
+
+    /// optional features offered by the connected server
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// games won and lost on this connection so far
+    pub fn score(&self) -> MatchScore {
+        self.score
+    }
+
+    /// concedes the match once `losses` games are lost by declining every
+    /// further rematch, whatever the interface would answer; a best of n
+    /// match is decided after `(n + 1) / 2` losses
+    pub fn resignafter(mut self, losses: u8) -> Client {
+        self.resignafter = Some(losses);
+        self
+    }
+
+    /// concedes the game in progress, best effort, for players quitting
+    /// midway; the opponent is told at once instead of waiting out the grace
+    /// for a reconnect. Spectators, and players before the first turn or after
+    /// the game, have nothing to concede
+    pub async fn resign(&mut self) {
+        if !self.spectating && self.turn.is_some() {
+            let _ = prot::sendmessage(&mut self.stream, prot::ClientMessage::Surrender).await;
+        }
+    }
+
+    /// logs and records a shot the server reported, `incoming` for shots at
+    /// the own board, `sunken` with the length of the ship it sank
+    fn landed(
+        &mut self,
+        incoming: bool,
+        pos: logic::Position,
+        info: logic::AttackInfo,
+        sunken: Option<u8>,
+    ) -> Result<(), logic::Error> {
+        // repeated after a reconnect, the acknowledgment got lost
+        if self
+            .shots
+            .iter()
+            .any(|shot| shot.incoming == incoming && shot.pos == pos)
+        {
+            return Ok(());
+        }
+        self.message.push(Message::shot(incoming, info, sunken));
+        match (incoming, sunken) {
+            (true, Some(_)) => self.selfremaining = self.selfremaining.saturating_sub(1),
+            (false, Some(_)) => self.oppremaining = self.oppremaining.saturating_sub(1),
+            (_, None) => {}
+        }
+        self.record(incoming, pos, info)
+    }
+
+    fn record(
+        &mut self,
+        incoming: bool,
+        pos: logic::Position,
+        info: logic::AttackInfo,
+    ) -> Result<(), logic::Error> {
+        let res = if incoming {
+            self.selfhits.apply(pos, info)
+        } else {
+            self.opphits.apply(pos, info)
+        };
+        if let Err(logic::Error::ConflictingAttackInfo) = res {
+            self.desynced = true;
+            return Ok(());
+        }
+        res?;
+        self.shots.push(Shot {
+            incoming,
+            pos,
+            info,
+        });
+        Ok(())
+    }
+
+    /// takes over the grids of a snapshot and rebuilds the shots, the log and
+    /// the ships afloat from them; shots the grids do not know are dropped,
+    /// shots missing from `shots` are appended in board order
+    fn adopt(&mut self, snapshot: logic::BoardSnapshot) {
+        self.selfhits = snapshot.selfhits;
+        self.opphits = snapshot.opphits;
+        // the fleet of the watched seat is unknown to spectators, only its
+        // lengths were announced
+        let lengths = self.server.fleet();
+        let selfsunken = if self.spectating {
+            logic::sunkenships(&self.selfhits, lengths).unwrap_or_default()
+        } else {
+            self.ships
+                .into_iter()
+                .filter(|ship| ship.into_iter().all(|pos| self.selfhits.get(pos).is_some()))
+                .collect()
+        };
+        let oppsunken = logic::sunkenships(&self.opphits, lengths).unwrap_or_default();
+        self.selfremaining = (lengths.len() - selfsunken.len()) as u8;
+        self.oppremaining = (lengths.len() - oppsunken.len()) as u8;
+
+        let ishit = |info| matches!(info, logic::AttackInfo::Hit(_));
+        let (selfhits, opphits) = (self.selfhits, self.opphits);
+        let grid = |incoming: bool| if incoming { selfhits } else { opphits };
+        self.shots.retain(|shot| {
+            grid(shot.incoming)
+                .get(shot.pos)
+                .is_some_and(|info| ishit(info) == ishit(shot.info))
+        });
+        let cells = (0..logic::BOARDSIZE)
+            .flat_map(|y| (0..logic::BOARDSIZE).map(move |x| (x, y)))
+            .filter_map(|(x, y)| logic::Position::fromcoords(x, y));
+        for incoming in [true, false] {
+            for pos in cells.clone() {
+                let known = self
+                    .shots
+                    .iter()
+                    .any(|shot| shot.incoming == incoming && shot.pos == pos);
+                let info = match grid(incoming).get(pos) {
+                    Some(logic::AttackInfo::Hit(_)) => logic::AttackInfo::Hit(false),
+                    Some(logic::AttackInfo::Miss) => logic::AttackInfo::Miss,
+                    None => continue,
+                };
+                if !known {
+                    self.shots.push(Shot {
+                        incoming,
+                        pos,
+                        info,
+                    });
+                }
+            }
+        }
+
+        // status messages since the last shot, like a reconnect, stay last
+        let since = self
+            .message
+            .iter()
+            .rposition(|message| message.origin().is_some())
+            .map_or(0, |idx| idx + 1);
+        let status = self.message.split_off(since);
+        // the last shot at a sunken ship is the one that sank it
+        self.message.clear();
+        for idx in 0..self.shots.len() {
+            let shot = self.shots[idx];
+            let sunken = if shot.incoming {
+                &selfsunken
+            } else {
+                &oppsunken
+            };
+            let ship = sunken.iter().find(|ship| ship.contains(shot.pos));
+            let sank = ship.is_some_and(|ship| {
+                !self.shots[idx + 1..]
+                    .iter()
+                    .any(|later| later.incoming == shot.incoming && ship.contains(later.pos))
+            });
+            if let logic::AttackInfo::Hit(_) = shot.info {
+                self.shots[idx].info = logic::AttackInfo::Hit(sank);
+            }
+            let length = ship.filter(|_| sank).map(|ship| ship.length());
+            self.message
+                .push(Message::shot(shot.incoming, self.shots[idx].info, length));
+        }
+        self.message.extend(status);
+    }
+
+    /// clears everything but the connection and the chat for a rematch
+    fn newgame(&mut self) {
+        self.selfhits = logic::HitGrid::new();
+        self.opphits = logic::HitGrid::new();
+        self.message.clear();
+        self.shots.clear();
+        self.summary = None;
+        self.oppremaining = self.server.fleet().len() as u8;
+        self.selfremaining = self.server.fleet().len() as u8;
+        self.turn = None;
+        self.desynced = false;
+        self.deadline = None;
+        self.oppleft = None;
+        self.oppaway = None;
+        self.pauseoffer = None;
+        self.pausedtill = None;
+        self.fleets = None;
+    }
+
+    /// the clock stands still while a pause holds
+    fn remaining(&self, now: time::Instant) -> Option<time::Duration> {
+        let now = self.pausedtill.map_or(now, |until| until.max(now));
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+
+    /// when the turn clock starts ticking again, right away unless a pause
+    /// holds
+    fn clockstart(&self, now: time::Instant) -> time::Instant {
+        self.pausedtill.map_or(now, |until| until.max(now))
+    }
+
+    fn pause(&self, now: time::Instant) -> Option<PauseState> {
+        match (self.pausedtill, self.pauseoffer) {
+            (Some(until), _) if until > now => Some(PauseState::Held(until - now)),
+            (_, offer) => offer.map(PauseState::Offered),
+        }
+    }
+
+    /// follows the pause as the server reports it; a holding pause pushes
+    /// the own deadline back, ending it early takes the unused rest off again
+    fn paused(&mut self, news: prot::PauseNews) {
+        let now = time::Instant::now();
+        match news {
+            prot::PauseNews::Offered => self.pauseoffer = Some(Origin::Opponent),
+            prot::PauseNews::Declined => self.pauseoffer = None,
+            prot::PauseNews::Held(secs) => {
+                let hold = time::Duration::from_secs(secs.into());
+                self.pauseoffer = None;
+                self.pausedtill = Some(now + hold);
+                self.deadline = self.deadline.map(|deadline| deadline + hold);
+            }
+            prot::PauseNews::Resumed => {
+                if let Some(until) = self.pausedtill.take() {
+                    let unused = until.saturating_duration_since(now);
+                    self.deadline = self.deadline.map(|deadline| deadline - unused);
+                }
+            }
+        }
+    }
+
+    /// notes the opponent dropped with `secs` to come back, or is back at 0
+    fn away(&mut self, secs: u16) {
+        self.oppaway =
+            (secs > 0).then(|| time::Instant::now() + time::Duration::from_secs(secs.into()));
+    }
+
+    /// sends chat the interface has waiting, best effort; a connection gone
+    /// bad shows with the next game message
+    async fn sendchat<I: UI>(&mut self, interface: &mut I) {
+        let Some(text) = interface.takechat() else {
+            return;
+        };
+        let text = prot::clampchat(&text).to_owned();
+        if text.is_empty() {
+            return;
+        }
+        let chat = prot::ClientMessage::Chat(text.clone());
+        if prot::sendmessage(&mut self.stream, chat).await.is_ok() {
+            self.chat.push((Origin::Own, text));
         }
     }
 
+    /// asks for, answers or ends a pause as the interface wants, best effort;
+    /// only if the server lets players pause
+    async fn sendpause<I: UI>(&mut self, interface: &mut I) {
+        let Some(ask) = interface.pausing() else {
+            return;
+        };
+        if !self.capabilities.contains(Capabilities::PAUSE) || self.turn.is_none() {
+            return;
+        }
+        let message = prot::ClientMessage::Pause(ask);
+        if prot::sendmessage(&mut self.stream, message).await.is_err() {
+            return;
+        }
+        let held = self.pause(time::Instant::now());
+        match (ask, held) {
+            (true, None) => self.pauseoffer = Some(Origin::Own),
+            (false, Some(PauseState::Offered(Origin::Opponent))) => self.pauseoffer = None,
+            _ => {}
+        }
+    }
+
+    /// takes chat and news the server relayed while the player picks a
+    /// target, without waiting for more; anything else is kept for the game
+    /// loop
+    async fn takerelayed(&mut self) -> Result<(), prot::Error> {
+        while self.early.is_none() && self.pending().await {
+            match prot::readmessage(&mut self.stream).await? {
+                prot::ServerMessage::Chat(text) => self.chat.push((Origin::Opponent, text)),
+                prot::ServerMessage::OpponentAway(secs) => self.away(secs),
+                prot::ServerMessage::Pause(news) => self.paused(news),
+                message => self.early = Some(message),
+            }
+        }
+        Ok(())
+    }
+
+    /// whether the server sent more that can be read without waiting
+    async fn pending(&mut self) -> bool {
+        // peeking sees through readiness that turns out to be spurious
+        matches!(
+            time::timeout(time::Duration::ZERO, self.stream.peek(&mut [0])).await,
+            Ok(Ok(1..))
+        )
+    }
+
+    /// gives the interface its turns until the server has something to say
+    async fn idle<I: UI>(&mut self, interface: &mut I) -> Result<(), Error<I>> {
+        let mut byte = [0];
+        while time::timeout(IDLEINTERVAL, self.stream.peek(&mut byte))
+            .await
+            .is_err()
+        {
+            interface.idle(self.info())?;
+            self.sendchat(interface).await;
+            self.sendpause(interface).await;
+            // conceding while the opponent moves, the server answers with
+            // the end of the game; a dropped connection shows on the next read
+            if self.turn == Some(Origin::Opponent) && interface.surrendering() {
+                let _ = prot::sendmessage(&mut self.stream, prot::ClientMessage::Surrender).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// `handshaketimeout` bounds everything from opening the connection up to
+    /// the capabilities reply; servers only answer once an opponent joined
     pub async fn connect<I: UI>(
-        addr: impl net::ToSocketAddrs,
+        endpoint: impl Into<Endpoint>,
+        interface: &mut I,
+        handshaketimeout: Option<time::Duration>,
+    ) -> Result<Client, Error<I>> {
+        Client::join(
+            endpoint,
+            interface,
+            handshaketimeout,
+            prot::QueueKey::default(),
+            None,
+        )
+        .await
+    }
+
+    /// `connect`, waiting for an opponent from `queue` instead of the
+    /// default queue and going by `name` if given
+    pub async fn join<I: UI>(
+        endpoint: impl Into<Endpoint>,
         interface: &mut I,
+        handshaketimeout: Option<time::Duration>,
+        queue: prot::QueueKey,
+        name: Option<prot::PlayerName>,
     ) -> Result<Client, Error<I>> {
         let ships = interface.buildboard()?;
-        let mut stream = net::TcpStream::connect(addr).await?;
+        let endpoint = endpoint.into();
+        let hello = prot::ClientMessage::Handshake(prot::PROTOCOLVERSION, queue, name);
+        let open = Client::open(endpoint, ships, hello);
+        match handshaketimeout {
+            Some(duration) => time::timeout(duration, open)
+                .await
+                .map_err(|_| prot::Error::Timeout)?,
+            None => open.await,
+        }
+    }
 
-        prot::sendmessage(&mut stream, prot::ClientMessage::Handshake).await?;
-        if let prot::ServerMessage::Handshake = prot::readmessage(&mut stream).await? {
-        } else {
-            return Err(prot::Error::UnsuccessfulHandshake.into());
+    /// plays over a connection opened elsewhere, which is not taken up again
+    /// once it drops
+    pub async fn attach<I: UI>(stream: Connection, interface: &mut I) -> Result<Client, Error<I>> {
+        let ships = interface.buildboard()?;
+        let hello = prot::ClientMessage::Handshake(prot::PROTOCOLVERSION, Default::default(), None);
+        Client::handshake(stream, None, ships, hello).await
+    }
+
+    /// connects to watch the game the server started last, see `watch`
+    pub async fn spectate<I: UI>(
+        endpoint: impl Into<Endpoint>,
+        handshaketimeout: Option<time::Duration>,
+    ) -> Result<Client, Error<I>> {
+        Client::watching(endpoint.into(), handshaketimeout, None).await
+    }
+
+    /// `spectate`, shown both fleets as well; the server refuses anyone
+    /// without its admin password
+    pub async fn oversee<I: UI>(
+        endpoint: impl Into<Endpoint>,
+        handshaketimeout: Option<time::Duration>,
+        password: String,
+    ) -> Result<Client, Error<I>> {
+        Client::watching(endpoint.into(), handshaketimeout, Some(password)).await
+    }
+
+    async fn watching<I: UI>(
+        endpoint: Endpoint,
+        handshaketimeout: Option<time::Duration>,
+        password: Option<String>,
+    ) -> Result<Client, Error<I>> {
+        // spectators own no fleet, this one is neither sent nor drawn
+        let ships = logic::randomplacement();
+        let hello = prot::ClientMessage::Spectate(prot::PROTOCOLVERSION, password);
+        let open = Client::open(endpoint, ships, hello);
+        match handshaketimeout {
+            Some(duration) => time::timeout(duration, open)
+                .await
+                .map_err(|_| prot::Error::Timeout)?,
+            None => open.await,
+        }
+    }
+
+    async fn open<I: UI>(
+        endpoint: Endpoint,
+        ships: logic::Ships,
+        hello: prot::ClientMessage,
+    ) -> Result<Client, Error<I>> {
+        let stream = Connection::connect(&endpoint).await?;
+        Client::handshake(stream, Some(endpoint), ships, hello).await
+    }
+
+    /// `hello` is the handshake of a player or a spectator
+    async fn handshake<I: UI>(
+        mut stream: Connection,
+        endpoint: Option<Endpoint>,
+        ships: logic::Ships,
+        hello: prot::ClientMessage,
+    ) -> Result<Client, Error<I>> {
+        let spectating = matches!(hello, prot::ClientMessage::Spectate(..));
+        let name = match &hello {
+            prot::ClientMessage::Handshake(_, _, name) => name.clone(),
+            _ => None,
+        };
+        prot::sendmessage(&mut stream, hello).await?;
+        let (server, token, oppname) = match prot::readmessage(&mut stream).await? {
+            prot::ServerMessage::Handshake(server, token, oppname) => (server, token, oppname),
+            prot::ServerMessage::Refused => return Err(prot::Error::Refused.into()),
+            prot::ServerMessage::VersionMismatch(theirs) => {
+                return Err(prot::Error::VersionMismatch {
+                    ours: prot::PROTOCOLVERSION,
+                    theirs,
+                }
+                .into())
+            }
+            _ => return Err(prot::Error::UnsuccessfulHandshake.into()),
+        };
+        // players have to have placed the fleet the server plays
+        let theirs = logic::FleetSpec::new(server.fleet().iter().copied())?;
+        if !spectating && !theirs.matches(ships.asslice()) {
+            let mut ours: Vec<_> = ships.into_iter().map(|ship| ship.length()).collect();
+            ours.sort_unstable();
+            return Err(Error::FleetMismatch {
+                ours,
+                theirs: server.fleet().to_vec(),
+            });
         }
+
+        // spectators never talk to the server again
+        let capabilities = if spectating {
+            Capabilities::empty()
+        } else {
+            prot::sendmessage(&mut stream, prot::ClientMessage::QueryCapabilities).await?;
+            let capabilities = match prot::readmessage(&mut stream).await? {
+                prot::ServerMessage::Capabilities(capabilities) => capabilities,
+                _ => return Err(prot::Error::UnsuccessfulHandshake.into()),
+            };
+            // a game under rules this build cannot play is refused up front
+            Capabilities::supported().require(capabilities & Capabilities::MODES)?;
+            capabilities
+        };
+
+        let fleetlen = server.fleet().len() as u8;
         Ok(Client {
             ships,
-            selfhits: [[None; 10]; 10],
-            opphits: [[None; 10]; 10],
+            selfhits: logic::HitGrid::new(),
+            opphits: logic::HitGrid::new(),
             stream,
+            endpoint,
+            token,
+            server,
+            name,
+            oppname,
+            capabilities,
             message: vec![Message::SuccessfullyConnected],
+            shots: Vec::new(),
+            summary: None,
+            oppremaining: fleetlen,
+            selfremaining: fleetlen,
+            turn: None,
+            desynced: false,
+            deadline: None,
+            oppleft: None,
+            oppaway: None,
+            pauseoffer: None,
+            pausedtill: None,
+            chat: Vec::new(),
+            spectating,
+            fleets: None,
+            early: None,
+            score: MatchScore::default(),
+            resignafter: None,
         })
     }
 
+    /// whether `err` is a dropped connection this client may take up again
+    fn resumable(&self, err: &prot::Error) -> bool {
+        self.token != 0 && self.endpoint.is_some() && matches!(err, prot::Error::Networking(_))
+    }
+
+    /// takes the seat back on a new connection, retrying for
+    /// `RECONNECTWINDOW`; the server follows up with a snapshot and repeats
+    /// its last request
+    async fn reconnect<I: UI>(&mut self) -> Result<(), Error<I>> {
+        let deadline = time::Instant::now() + RECONNECTWINDOW;
+        loop {
+            match time::timeout_at(deadline, self.rejoin()).await {
+                Ok(Ok(stream)) => {
+                    self.stream = stream;
+                    self.early = None;
+                    self.message.push(Message::Reconnected);
+                    return Ok(());
+                }
+                Ok(Err(prot::Error::Networking(_))) if time::Instant::now() < deadline => {
+                    time::sleep(RECONNECTINTERVAL).await
+                }
+                Ok(Err(err)) => return Err(err.into()),
+                Err(_) => return Err(prot::Error::Timeout.into()),
+            }
+        }
+    }
+
+    async fn rejoin(&self) -> Result<Connection, prot::Error> {
+        let endpoint = self.endpoint.as_ref().ok_or(prot::Error::Refused)?;
+        let mut stream = Connection::connect(endpoint).await?;
+        prot::sendmessage(
+            &mut stream,
+            prot::ClientMessage::Reconnect(prot::PROTOCOLVERSION, self.token),
+        )
+        .await?;
+        match prot::readmessage(&mut stream).await? {
+            prot::ServerMessage::Handshake(..) => Ok(stream),
+            prot::ServerMessage::Refused => Err(prot::Error::Refused),
+            prot::ServerMessage::VersionMismatch(theirs) => Err(prot::Error::VersionMismatch {
+                ours: prot::PROTOCOLVERSION,
+                theirs,
+            }),
+            _ => Err(prot::Error::UnsuccessfulHandshake),
+        }
+    }
+
+    /// follows the game from the first player's seat without ever answering,
+    /// until the server ends the feed
+    pub async fn watch<I: UI>(&mut self, interface: &mut I) -> Result<(), Error<I>> {
+        interface.displayboard(self.info())?;
+
+        let mut victory = None;
+        let mut stale = false;
+        loop {
+            match prot::readmessage(&mut self.stream).await? {
+                prot::ServerMessage::Snapshot(snapshot) => {
+                    // a rematch starts over from empty grids
+                    if victory.take().is_some() {
+                        self.newgame();
+                    }
+                    self.adopt(snapshot);
+                }
+                prot::ServerMessage::InformTargetHitYou(pos, sunken) => {
+                    self.landed(true, pos, logic::AttackInfo::Hit(sunken.is_some()), sunken)?
+                }
+                prot::ServerMessage::InformTargetHitOpp(pos, sunken) => {
+                    self.landed(false, pos, logic::AttackInfo::Hit(sunken.is_some()), sunken)?
+                }
+                prot::ServerMessage::InformTargetMissYou(pos) => {
+                    self.landed(true, pos, logic::AttackInfo::Miss, None)?
+                }
+                prot::ServerMessage::InformTargetMissOpp(pos) => {
+                    self.landed(false, pos, logic::AttackInfo::Miss, None)?
+                }
+                prot::ServerMessage::InformVictory => victory = Some(true),
+                prot::ServerMessage::InformLoss => victory = Some(false),
+                prot::ServerMessage::GameSummary(summary) => self.summary = Some(summary),
+                prot::ServerMessage::Fleets(fleets) => self.fleets = Some(fleets),
+                prot::ServerMessage::TerminateConnection => {
+                    if stale {
+                        self.redraw(interface, victory)?;
+                    }
+                    if let Some(victory) = victory {
+                        interface.finish(self.info(), victory)?;
+                    }
+                    return Ok(());
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid spectator message",
+                    )
+                    .into());
+                }
+            }
+            // a fast sweep is drawn once it has been taken in whole
+            stale = self.pending().await;
+            if !stale {
+                self.redraw(interface, victory)?;
+            }
+        }
+    }
+
+    fn redraw<I: UI>(
+        &self,
+        interface: &mut I,
+        victory: Option<bool>,
+    ) -> Result<(), UIError<I::Error>> {
+        match victory {
+            Some(true) => interface.displayvictory(self.info()),
+            Some(false) => interface.displayloss(self.info()),
+            None => interface.displayboard(self.info()),
+        }
+    }
+
     pub async fn play<I: UI>(&mut self, interface: &mut I) -> Result<bool, Error<I>> {
         interface.displayboard(self.info())?;
 
         let mut victory = None;
+        let mut lasttarget = None;
+        let mut rebuild = false;
         loop {
-            let request = prot::readmessage(&mut self.stream).await?;
+            let mut incoming = None;
+            let mut rejected = None;
+            if self.early.is_none() && victory.is_none() {
+                self.idle(interface).await?;
+            }
+            let next = match self.early.take() {
+                Some(message) => Ok(message),
+                None => prot::readmessage(&mut self.stream).await,
+            };
+            let request = match next {
+                Err(err) if self.resumable(&err) => {
+                    self.reconnect().await?;
+                    if victory.is_none() {
+                        interface.displayboard(self.info())?;
+                    }
+                    continue;
+                }
+                res => res?,
+            };
             let response = match request {
                 prot::ServerMessage::RequestShipPositions => {
-                    prot::ClientMessage::ShipPositions(self.ships)
+                    // a rematch both players accepted
+                    if victory.take().is_some() {
+                        self.newgame();
+                        lasttarget = None;
+                        rebuild = true;
+                    }
+                    if std::mem::take(&mut rebuild) {
+                        self.ships = interface.buildboard()?;
+                    }
+                    if self.capabilities.contains(Capabilities::COMPACTFLEET) {
+                        prot::ClientMessage::CompactShipPositions(self.ships)
+                    } else {
+                        prot::ClientMessage::ShipPositions(self.ships)
+                    }
+                }
+                prot::ServerMessage::RequestTarget(_) | prot::ServerMessage::RequestSalvo(..)
+                    if self.desynced =>
+                {
+                    prot::ClientMessage::RequestResync
+                }
+                prot::ServerMessage::Snapshot(snapshot) => {
+                    self.adopt(snapshot);
+                    self.desynced = false;
+                    prot::ClientMessage::Acknowledge
                 }
-                prot::ServerMessage::RequestTarget => {
+                prot::ServerMessage::RequestTarget(limit) => {
+                    let start = self.clockstart(time::Instant::now());
+                    self.deadline =
+                        limit.map(|secs| start + time::Duration::from_secs(secs.into()));
+                    self.turn = Some(Origin::Own);
                     self.message.push(Message::SelectTarget);
-                    prot::ClientMessage::Target(interface.selecttarget(self.info())?)
+                    let target = loop {
+                        let target = interface.selecttarget(self.info())?;
+                        self.sendchat(interface).await;
+                        self.sendpause(interface).await;
+                        if !interface.yielded() {
+                            break Some(target);
+                        }
+                        self.takerelayed().await?;
+                        // the server moved on, the turn ran out or the
+                        // opponent resigned meanwhile
+                        if self.early.is_some() {
+                            break None;
+                        }
+                    };
+                    self.deadline = None;
+                    let Some(target) = target else {
+                        continue;
+                    };
+                    if interface.surrendering() {
+                        prot::ClientMessage::Surrender
+                    } else {
+                        lasttarget = Some(target);
+                        prot::ClientMessage::Target(target)
+                    }
+                }
+                prot::ServerMessage::RequestSalvo(shots, secs) => {
+                    let start = self.clockstart(time::Instant::now());
+                    self.deadline = Some(start + time::Duration::from_secs(secs.into()));
+                    self.turn = Some(Origin::Own);
+                    self.message.push(Message::SelectTarget);
+                    let targets = loop {
+                        let targets = interface.selecttargets(self.info(), shots)?;
+                        self.sendchat(interface).await;
+                        self.sendpause(interface).await;
+                        if !interface.yielded() {
+                            break Some(targets);
+                        }
+                        self.takerelayed().await?;
+                        if self.early.is_some() {
+                            break None;
+                        }
+                    };
+                    self.deadline = None;
+                    let Some(targets) = targets else {
+                        continue;
+                    };
+                    if interface.surrendering() {
+                        prot::ClientMessage::Surrender
+                    } else {
+                        prot::ClientMessage::Salvo(targets)
+                    }
+                }
+                prot::ServerMessage::InvalidShips(reason) => {
+                    interface.invalidships(self.info(), reason)?;
+                    rebuild = true;
+                    prot::ClientMessage::Acknowledge
+                }
+                prot::ServerMessage::Invalid => {
+                    rejected = lasttarget.take();
+                    prot::ClientMessage::Acknowledge
                 }
-                prot::ServerMessage::Invalid => prot::ClientMessage::Acknowledge,
                 prot::ServerMessage::InformTargetSelection => {
+                    self.turn = Some(Origin::Opponent);
                     self.message.push(Message::WaitForOpp);
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformTargetHitYou(pos, sunken) => {
-                    self.message.push(if sunken {
-                        Message::ShipSunken
-                    } else {
-                        Message::ShipHit
-                    });
-                    let (x, y) = pos.coords();
-                    self.selfhits[y as usize][x as usize] = Some(logic::AttackInfo::Hit(sunken));
+                    self.landed(true, pos, logic::AttackInfo::Hit(sunken.is_some()), sunken)?;
+                    incoming = Some(pos);
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformTargetHitOpp(pos, sunken) => {
-                    self.message.push(if sunken {
-                        Message::OppShipSunken
-                    } else {
-                        Message::OppShipHit
-                    });
-                    let (x, y) = pos.coords();
-                    self.opphits[y as usize][x as usize] = Some(logic::AttackInfo::Hit(sunken));
+                    self.landed(false, pos, logic::AttackInfo::Hit(sunken.is_some()), sunken)?;
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformTargetMissYou(pos) => {
-                    self.message.push(Message::ShipMissed);
-                    let (x, y) = pos.coords();
-                    self.selfhits[y as usize][x as usize] = Some(logic::AttackInfo::Miss);
+                    self.landed(true, pos, logic::AttackInfo::Miss, None)?;
+                    incoming = Some(pos);
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformTargetMissOpp(pos) => {
-                    self.message.push(Message::OppShipMissed);
-                    let (x, y) = pos.coords();
-                    self.opphits[y as usize][x as usize] = Some(logic::AttackInfo::Miss);
+                    self.landed(false, pos, logic::AttackInfo::Miss, None)?;
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformVictory => {
+                    self.turn = None;
+                    // repeated after a reconnect, counted once
+                    if victory.is_none() {
+                        self.score.wins = self.score.wins.saturating_add(1);
+                    }
                     interface.displayvictory(self.info())?;
                     victory = Some(true);
                     prot::ClientMessage::Acknowledge
                 }
                 prot::ServerMessage::InformLoss => {
+                    self.turn = None;
+                    if victory.is_none() {
+                        self.score.losses = self.score.losses.saturating_add(1);
+                    }
                     interface.displayloss(self.info())?;
                     victory = Some(false);
                     prot::ClientMessage::Acknowledge
                 }
+                prot::ServerMessage::GameSummary(summary) => {
+                    self.summary = Some(summary);
+                    prot::ClientMessage::Acknowledge
+                }
+                prot::ServerMessage::OpponentLeft(reason) => {
+                    self.oppleft = Some(reason);
+                    self.oppaway = None;
+                    prot::ClientMessage::Acknowledge
+                }
+                prot::ServerMessage::OfferRematch => {
+                    let conceded = self
+                        .resignafter
+                        .is_some_and(|losses| self.score.losses >= losses);
+                    prot::ClientMessage::Rematch(!conceded && interface.rematch(self.info())?)
+                }
+                // not answered, the server is still waiting for the answer
+                // to its last request
+                prot::ServerMessage::Chat(text) => {
+                    self.chat.push((Origin::Opponent, text));
+                    if victory.is_none() {
+                        interface.displayboard(self.info())?;
+                    }
+                    continue;
+                }
+                prot::ServerMessage::OpponentAway(secs) => {
+                    self.away(secs);
+                    if victory.is_none() {
+                        interface.displayboard(self.info())?;
+                    }
+                    continue;
+                }
+                prot::ServerMessage::Pause(news) => {
+                    self.paused(news);
+                    if victory.is_none() {
+                        interface.displayboard(self.info())?;
+                    }
+                    continue;
+                }
                 prot::ServerMessage::TerminateConnection => {
                     prot::sendmessage(&mut self.stream, prot::ClientMessage::Acknowledge).await?;
-                    return victory.ok_or(io::Error::from(io::ErrorKind::ConnectionAborted).into());
+                    let victory =
+                        victory.ok_or(io::Error::from(io::ErrorKind::ConnectionAborted))?;
+                    interface.finish(self.info(), victory)?;
+                    return Ok(victory);
                 }
                 _ => {
                     return Err(io::Error::new(
@@ -162,7 +1562,20 @@ impl Client {
                     .into());
                 }
             };
-            prot::sendmessage(&mut self.stream, response).await?;
+            match prot::sendmessage(&mut self.stream, response).await {
+                // the server repeats the request once the seat is taken back
+                Err(err) if self.resumable(&err) => {
+                    self.reconnect().await?;
+                    continue;
+                }
+                res => res?,
+            }
+            if let Some(pos) = incoming {
+                interface.animateincoming(self.info(), pos)?;
+            }
+            if let Some(pos) = rejected {
+                interface.invalidtarget(self.info(), pos)?;
+            }
             match victory {
                 Some(true) => interface.displayvictory(self.info()),
                 Some(false) => interface.displayloss(self.info()),
@@ -171,3 +1584,604 @@ impl Client {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net;
+
+    /// a server that answers the first handshake on a free loopback port
+    /// with `capabilities`
+    async fn fakeserver(capabilities: Capabilities) -> std::net::SocketAddr {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _: prot::ClientMessage = prot::readmessage(&mut stream).await.unwrap();
+            let handshake = prot::ServerMessage::Handshake(ServerInfo::default(), 0, None);
+            prot::sendmessage(&mut stream, handshake).await.unwrap();
+            let _: prot::ClientMessage = prot::readmessage(&mut stream).await.unwrap();
+            let reply = prot::ServerMessage::Capabilities(capabilities);
+            prot::sendmessage(&mut stream, reply).await.unwrap();
+            // held open until the client is done with it
+            let _ = prot::readmessage::<prot::ClientMessage, _>(&mut stream).await;
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn refusesunsupportedmodes() {
+        let addr = fakeserver(Capabilities::CHAT | Capabilities::FASTMODE).await;
+        let res = Client::connect(addr, &mut BotUI::new(), None).await;
+        assert!(matches!(
+            res,
+            Err(Error::Protocol(prot::Error::Unsupported(missing))) if missing == Capabilities::FASTMODE
+        ));
+
+        let addr = fakeserver(Capabilities::CHAT | Capabilities::SALVO).await;
+        let client = Client::connect(addr, &mut BotUI::new(), None)
+            .await
+            .unwrap();
+        assert!(client.capabilities().contains(Capabilities::SALVO));
+    }
+
+    #[tokio::test]
+    async fn refusesotherfleet() {
+        for (preset, lengths) in [
+            (logic::FleetPreset::Small, &[2, 2, 3][..]),
+            (logic::FleetPreset::Mega, &[4, 4, 5, 5, 6]),
+        ] {
+            let (ours, mut theirs) = Connection::pair(4096);
+            let server = tokio::spawn(async move {
+                let _: prot::ClientMessage = prot::readmessage(&mut theirs).await.unwrap();
+                let info = ServerInfo::default().withfleet(&logic::FleetSpec::from(preset));
+                let handshake = prot::ServerMessage::Handshake(info, 0, None);
+                prot::sendmessage(&mut theirs, handshake).await.unwrap();
+                // hung up on instead of asked for capabilities
+                prot::readmessage::<prot::ClientMessage, _>(&mut theirs)
+                    .await
+                    .is_err()
+            });
+            let res = Client::attach(ours, &mut ScriptedUI::new(fleet(), [])).await;
+            let Err(Error::FleetMismatch { ours, theirs }) = res else {
+                panic!("{preset:?} fleet accepted");
+            };
+            assert_eq!(ours, [2, 3, 3, 4, 5]);
+            assert_eq!(theirs, lengths);
+            assert!(server.await.unwrap());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn remainingcountsdown() {
+        let addr = fakeserver(Capabilities::CHAT).await;
+        let mut interface = ScriptedUI::new(fleet(), []);
+        let mut client = Client::connect(addr, &mut interface, None)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+        let secs = |secs| Some(time::Duration::from_secs(secs));
+        // no clock without a deadline
+        assert_eq!(client.info().remaining, None);
+
+        client.deadline = Some(time::Instant::now() + time::Duration::from_secs(30));
+        assert_eq!(client.info().remaining, secs(30));
+        time::advance(time::Duration::from_secs(10)).await;
+        assert_eq!(client.info().remaining, secs(20));
+
+        // stands still while a pause holds, then ticks on
+        client.paused(prot::PauseNews::Held(5));
+        time::advance(time::Duration::from_secs(3)).await;
+        assert_eq!(client.info().remaining, secs(20));
+        time::advance(time::Duration::from_secs(5)).await;
+        assert_eq!(client.info().remaining, secs(17));
+
+        // never below zero once the deadline passed
+        time::advance(time::Duration::from_secs(60)).await;
+        assert_eq!(client.info().remaining, secs(0));
+        client.newgame();
+        assert_eq!(client.info().remaining, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silentserverhandshaketimesout() {
+        // takes the connection and the handshake, then never answers
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _: prot::ClientMessage = prot::readmessage(&mut stream).await.unwrap();
+            time::sleep(time::Duration::from_secs(3600)).await;
+        });
+
+        let start = time::Instant::now();
+        let timeout = Some(time::Duration::from_secs(5));
+        let res = Client::connect(addr, &mut BotUI::new(), timeout).await;
+        assert!(matches!(res, Err(Error::Protocol(prot::Error::Timeout))));
+        let res = Client::spectate::<BotUI>(addr, timeout).await;
+        assert!(matches!(res, Err(Error::Protocol(prot::Error::Timeout))));
+        assert_eq!(start.elapsed().as_secs(), 10);
+    }
+
+    #[test]
+    fn hardbotspreadsout() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for fleet in [logic::FleetSpec::default(), logic::FleetPreset::Mega.into()] {
+            let spread = fleet.clone().notouch(true);
+            let mut touched = false;
+            for _ in 0..100 {
+                let easy = BotUI::new().fleet(fleet.clone()).place(&mut rng).unwrap();
+                assert!(logic::Ships::validate(easy.asslice(), &fleet).is_ok());
+                touched |= logic::Ships::validate(easy.asslice(), &spread).is_err();
+
+                let hard = BotUI::new()
+                    .fleet(fleet.clone())
+                    .difficulty(Difficulty::Hard)
+                    .place(&mut rng)
+                    .unwrap();
+                assert!(logic::Ships::validate(hard.asslice(), &spread).is_ok());
+            }
+            // easy placements are not held apart, a hundred of them touch
+            assert!(touched);
+        }
+        // a server that forbids touching gets legal fleets from either
+        let easy = BotUI::new().fleet(logic::FleetSpec::default().notouch(true));
+        let ships = easy.place(&mut rng).unwrap();
+        assert!(logic::Ships::validate(ships.asslice(), &easy.fleet).is_ok());
+
+        assert_eq!(" Hard".parse::<Difficulty>().unwrap(), Difficulty::Hard);
+        assert!("nightmare".parse::<Difficulty>().is_err());
+    }
+
+    /// fires at random on its turn and concedes while the opponent moves,
+    /// or, `thinking`, never settles on a target and notes why the game ended
+    struct Resigner {
+        thinking: bool,
+        resigning: bool,
+        oppleft: Option<LeaveReason>,
+    }
+
+    impl UI for Resigner {
+        type Error = io::Error;
+
+        fn buildboard(&mut self) -> Result<logic::Ships, UIError<io::Error>> {
+            Ok(logic::randomplacement())
+        }
+
+        fn displayboard(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+
+        fn selecttarget(
+            &mut self,
+            info: ClientInfo,
+        ) -> Result<logic::Position, UIError<io::Error>> {
+            BotUI::new().selecttarget(info)
+        }
+
+        fn yielded(&mut self) -> bool {
+            self.thinking
+        }
+
+        fn surrendering(&mut self) -> bool {
+            std::mem::take(&mut self.resigning)
+        }
+
+        fn idle(&mut self, info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            self.resigning = !self.thinking && info.turn == Some(Origin::Opponent);
+            Ok(())
+        }
+
+        fn displayvictory(&mut self, info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            self.oppleft = info.oppleft;
+            Ok(())
+        }
+
+        fn displayloss(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn resignswhileopponentthinks() {
+        for seed in 0..2 {
+            let resigner = Resigner {
+                thinking: false,
+                resigning: false,
+                oppleft: None,
+            };
+            let thinker = Resigner {
+                thinking: true,
+                ..resigner
+            };
+            let (resigner, thinker) = crate::testing::play_local(resigner, thinker, seed).await;
+            assert!(matches!(resigner.outcome, Ok(false)));
+            assert!(matches!(thinker.outcome, Ok(true)));
+            assert_eq!(thinker.interface.oppleft, Some(LeaveReason::Resigned));
+        }
+    }
+
+    /// places a random fleet and quits at the first chance, like Ctrl-C
+    struct Quitter;
+
+    impl UI for Quitter {
+        type Error = io::Error;
+
+        fn buildboard(&mut self) -> Result<logic::Ships, UIError<io::Error>> {
+            Ok(logic::randomplacement())
+        }
+
+        fn displayboard(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+
+        fn selecttarget(
+            &mut self,
+            _info: ClientInfo,
+        ) -> Result<logic::Position, UIError<io::Error>> {
+            Err(io::Error::from(io::ErrorKind::Interrupted).into())
+        }
+
+        fn idle(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Err(io::Error::from(io::ErrorKind::Interrupted).into())
+        }
+
+        fn displayvictory(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+
+        fn displayloss(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn quittingresigns() {
+        for seed in 0..2 {
+            let (quitterend, server1) = Connection::pair(4096);
+            let (thinkerend, server2) = Connection::pair(4096);
+            let rng = rand::rngs::StdRng::seed_from_u64(seed);
+            tokio::spawn(crate::server::hostpair(
+                [server1, server2],
+                Default::default(),
+                rng,
+            ));
+            let quit = async {
+                let mut quitter = Quitter;
+                let mut client = Client::attach(quitterend, &mut quitter)
+                    .await
+                    .unwrap_or_else(|err| panic!("{err}"));
+                let res = client.play(&mut quitter).await;
+                assert!(matches!(res, Err(Error::Interface(_))));
+                client.resign().await;
+            };
+            let think = async {
+                let mut thinker = Resigner {
+                    thinking: true,
+                    resigning: false,
+                    oppleft: None,
+                };
+                let mut client = Client::attach(thinkerend, &mut thinker)
+                    .await
+                    .unwrap_or_else(|err| panic!("{err}"));
+                let victory = client
+                    .play(&mut thinker)
+                    .await
+                    .unwrap_or_else(|err| panic!("{err}"));
+                (victory, thinker.oppleft)
+            };
+            let ((), thought) = tokio::join!(quit, think);
+            // told right away, not once the quitter's reconnect grace ran out
+            assert_eq!(thought, (true, Some(LeaveReason::Resigned)));
+        }
+    }
+
+    #[tokio::test]
+    async fn resignswhenmatchdecided() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(crate::server::serve(listener, Default::default()));
+
+        // best of three, both accept rematches until two games are lost
+        let player = || async move {
+            let mut bot = BotUI::new().rematches(true);
+            let client = Client::connect(addr, &mut bot, None).await;
+            let mut client = client.unwrap_or_else(|err| panic!("{err}")).resignafter(2);
+            client.play(&mut bot).await.unwrap();
+            client.score()
+        };
+        let (first, second) = tokio::join!(player(), player());
+        assert_eq!((first.wins, first.losses), (second.losses, second.wins));
+        let (loser, winner) = if first.losses == 2 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        assert_eq!(loser.losses, 2);
+        assert_eq!(winner.wins, 2);
+        assert!(winner.losses < 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn playsoverdomainsocket() {
+        let path = std::env::temp_dir().join(format!("ziel-{}.sock", std::process::id()));
+        let endpoint = Endpoint::Unix(path.clone());
+        let listener = crate::conn::Listener::bind(&endpoint).await.unwrap();
+        tokio::spawn(crate::server::serve(listener, Default::default()));
+
+        let player = || async {
+            let mut bot = BotUI::new();
+            let client = Client::connect(endpoint.clone(), &mut bot, None).await;
+            let mut client = client.unwrap_or_else(|err| panic!("{err}"));
+            client
+                .play(&mut bot)
+                .await
+                .unwrap_or_else(|err| panic!("{err}"))
+        };
+        let (first, second) = tokio::join!(player(), player());
+        let _ = std::fs::remove_file(&path);
+        assert_ne!(first, second);
+    }
+
+    /// the standard fleet stacked vertically in the five leftmost columns
+    fn fleet() -> logic::Ships {
+        const SHIPLEN: [u8; 5] = [5, 4, 3, 3, 2];
+        let ships: [logic::Ship; 5] = std::array::from_fn(|i| {
+            logic::Ship::try_from(logic::ShipPlan::Vertical {
+                pos: logic::Position::fromcoords(i as u8, 0).unwrap(),
+                len: SHIPLEN[i],
+            })
+            .unwrap()
+        });
+        logic::Ships::try_from(ships).unwrap()
+    }
+
+    fn cell(x: u8, y: u8) -> logic::Position {
+        logic::Position::fromcoords(x, y).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scriptedseesincoming() {
+        let water: Vec<_> = (5..10)
+            .flat_map(|x| (0..10).map(move |y| cell(x, y)))
+            .collect();
+        for seed in 0..2 {
+            let hunter = ScriptedUI::new(fleet(), fleet().into_iter().flatten());
+            let idler = ScriptedUI::new(fleet(), water.iter().copied());
+            let (hunter, idler) = crate::testing::play_local(hunter, idler, seed).await;
+            assert!(matches!(hunter.outcome, Ok(true)));
+            let fired: Vec<_> = fleet().into_iter().flatten().collect();
+            assert_eq!(idler.interface.incoming(), fired);
+            // however few shots the idler got in, they arrived in order
+            let incoming = hunter.interface.incoming();
+            assert_eq!(incoming, &water[..incoming.len()]);
+        }
+    }
+
+    fn shot(incoming: bool, x: u8, y: u8, info: logic::AttackInfo) -> Shot {
+        Shot {
+            incoming,
+            pos: cell(x, y),
+            info,
+        }
+    }
+
+    #[test]
+    fn replaystatespershot() {
+        let shots = [
+            shot(false, 0, 0, logic::AttackInfo::Hit(false)),
+            shot(true, 9, 9, logic::AttackInfo::Miss),
+            shot(false, 1, 0, logic::AttackInfo::Miss),
+            shot(true, 4, 4, logic::AttackInfo::Hit(false)),
+        ];
+        let states = replaystates(&shots).unwrap();
+        assert_eq!(states.len(), shots.len() + 1);
+        assert_eq!(states[0], (logic::HitGrid::new(), logic::HitGrid::new()));
+
+        // each step adds its shot to one grid and leaves the other alone
+        for (step, shot) in shots.iter().enumerate() {
+            let (before, after) = (states[step], states[step + 1]);
+            let (changed, kept) = if shot.incoming {
+                ((before.0, after.0), (before.1, after.1))
+            } else {
+                ((before.1, after.1), (before.0, after.0))
+            };
+            assert_eq!(kept.0, kept.1);
+            let mut expected = changed.0;
+            expected.apply(shot.pos, shot.info).unwrap();
+            assert_eq!(changed.1, expected);
+        }
+
+        // a shot contradicting an earlier one spoils the replay
+        let conflicting = [shots[0], shot(false, 0, 0, logic::AttackInfo::Miss)];
+        assert!(replaystates(&conflicting).is_err());
+    }
+
+    #[test]
+    fn accuracytrendownshots() {
+        assert!(accuracytrend(&[]).is_empty());
+        let shots = [
+            shot(false, 0, 0, logic::AttackInfo::Miss),
+            // incoming shots leave the series alone
+            shot(true, 0, 0, logic::AttackInfo::Hit(false)),
+            shot(false, 0, 1, logic::AttackInfo::Hit(false)),
+            shot(false, 0, 2, logic::AttackInfo::Hit(true)),
+            shot(true, 5, 5, logic::AttackInfo::Miss),
+            shot(false, 9, 9, logic::AttackInfo::Miss),
+        ];
+        // rounded down, one value per own shot
+        assert_eq!(accuracytrend(&shots), [0, 50, 66, 50]);
+        assert_eq!(accuracytrend(&shots[..1]), [0]);
+        assert!(accuracytrend(&shots[1..2]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn oppremainingpersink() {
+        let addr = fakeserver(Capabilities::CHAT).await;
+        let mut interface = ScriptedUI::new(fleet(), []);
+        let mut client = Client::connect(addr, &mut interface, None)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+        assert_eq!(client.info().oppremaining, 5);
+
+        client
+            .landed(false, cell(0, 0), logic::AttackInfo::Hit(false), None)
+            .unwrap();
+        client
+            .landed(false, cell(0, 1), logic::AttackInfo::Hit(true), Some(2))
+            .unwrap();
+        assert_eq!(client.info().oppremaining, 4);
+        // repeated after a reconnect, counted once
+        client
+            .landed(false, cell(0, 1), logic::AttackInfo::Hit(true), Some(2))
+            .unwrap();
+        assert_eq!(client.info().oppremaining, 4);
+        // own losses and misses leave it alone
+        client
+            .landed(true, cell(5, 5), logic::AttackInfo::Hit(true), Some(2))
+            .unwrap();
+        client
+            .landed(false, cell(9, 9), logic::AttackInfo::Miss, None)
+            .unwrap();
+        assert_eq!(client.info().oppremaining, 4);
+        client
+            .landed(false, cell(3, 3), logic::AttackInfo::Hit(true), Some(3))
+            .unwrap();
+        assert_eq!(client.info().oppremaining, 3);
+
+        // a custom fleet starts from its own size
+        client.server =
+            ServerInfo::default().withfleet(&logic::FleetSpec::from(logic::FleetPreset::Small));
+        client.newgame();
+        assert_eq!(client.info().oppremaining, 3);
+    }
+
+    #[tokio::test]
+    async fn snapshotrebuildsshots() {
+        let addr = fakeserver(Capabilities::CHAT).await;
+        let mut interface = ScriptedUI::new(fleet(), []);
+        let mut client = Client::connect(addr, &mut interface, None)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+        client
+            .landed(false, cell(4, 0), logic::AttackInfo::Hit(false), None)
+            .unwrap();
+        // never reached the server
+        client
+            .landed(false, cell(7, 7), logic::AttackInfo::Hit(false), None)
+            .unwrap();
+        client.message.push(Message::Reconnected);
+
+        let (mut own, mut opp) = (logic::Board::new(fleet()), logic::Board::new(fleet()));
+        for (x, y) in [(4, 0), (4, 1), (9, 9), (0, 0)] {
+            opp.target(cell(x, y)).unwrap();
+        }
+        for (x, y) in [(3, 0), (3, 1), (3, 2), (8, 8)] {
+            own.target(cell(x, y)).unwrap();
+        }
+        client.adopt(logic::BoardSnapshot {
+            selfhits: own.hitgrid(),
+            opphits: opp.hitgrid(),
+            yourturn: true,
+        });
+
+        assert_eq!(client.shots.len(), 8);
+        assert!(!client.shots.iter().any(|shot| shot.pos == cell(7, 7)));
+        assert_eq!((client.oppremaining, client.selfremaining), (4, 4));
+        assert_eq!(client.message.len(), 9);
+        assert!(matches!(client.message.last(), Some(Message::Reconnected)));
+        assert_eq!(
+            client
+                .message
+                .iter()
+                .filter(|message| matches!(
+                    message,
+                    Message::OppShipSunken(2) | Message::ShipSunken(3)
+                ))
+                .count(),
+            2
+        );
+        assert_eq!(accuracytrend(&client.shots).last(), Some(&75));
+        let states = replaystates(&client.shots).unwrap();
+        assert_eq!(states.len(), 9);
+    }
+
+    /// counts what a spectator gets to see
+    struct Watcher {
+        draws: usize,
+        opphits: logic::HitGrid,
+    }
+
+    impl UI for Watcher {
+        type Error = io::Error;
+
+        fn buildboard(&mut self) -> Result<logic::Ships, UIError<io::Error>> {
+            Ok(logic::randomplacement())
+        }
+
+        fn displayboard(&mut self, info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            self.draws += 1;
+            self.opphits = *info.opphits;
+            Ok(())
+        }
+
+        fn selecttarget(
+            &mut self,
+            _info: ClientInfo,
+        ) -> Result<logic::Position, UIError<io::Error>> {
+            unreachable!("spectators do not fire")
+        }
+
+        fn displayvictory(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+
+        fn displayloss(&mut self, _info: ClientInfo) -> Result<(), UIError<io::Error>> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn spectatorbatchesredraws() {
+        const SHOTS: u8 = 20;
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _: prot::ClientMessage = prot::readmessage(&mut stream).await.unwrap();
+            let handshake = prot::ServerMessage::Handshake(ServerInfo::default(), 0, None);
+            prot::sendmessage(&mut stream, handshake).await.unwrap();
+            // a whole sweep in one write, as a fast server would send it
+            let mut sweep = Vec::new();
+            for i in 0..SHOTS {
+                let inform = prot::ServerMessage::InformTargetMissOpp(cell(i % 10, i / 10));
+                prot::sendmessage(&mut sweep, inform).await.unwrap();
+            }
+            prot::sendmessage(&mut sweep, prot::ServerMessage::TerminateConnection)
+                .await
+                .unwrap();
+            stream.write_all(&sweep).await.unwrap();
+        });
+
+        let mut watcher = Watcher {
+            draws: 0,
+            opphits: logic::HitGrid::new(),
+        };
+        let mut client = Client::spectate::<Watcher>(addr, None)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+        client
+            .watch(&mut watcher)
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+
+        assert_eq!(client.shots.len(), SHOTS as usize);
+        assert_eq!(&watcher.opphits, client.info().opphits);
+        assert!((0..SHOTS).all(|i| watcher.opphits.get(cell(i % 10, i / 10)).is_some()));
+        assert!(watcher.draws < SHOTS as usize);
+    }
+}
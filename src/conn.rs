@@ -0,0 +1,271 @@
+use std::{
+    fmt,
+    pin::Pin,
+    task::{self, Poll},
+};
+
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite, ReadBuf},
+    net,
+    sync::{mpsc, Mutex},
+};
+
+/// where a server is reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(std::net::SocketAddr),
+    /// a domain socket at this path, for play on the same machine
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl From<std::net::SocketAddr> for Endpoint {
+    fn from(addr: std::net::SocketAddr) -> Endpoint {
+        Endpoint::Tcp(addr)
+    }
+}
+
+#[cfg(unix)]
+impl From<std::path::PathBuf> for Endpoint {
+    fn from(path: std::path::PathBuf) -> Endpoint {
+        Endpoint::Unix(path)
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{addr}"),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// accepts connections at an endpoint
+pub enum Listener {
+    Tcp(net::TcpListener),
+    #[cfg(unix)]
+    Unix(net::UnixListener),
+    /// connections handed over within this process, see `Listener::local`
+    Local(Mutex<mpsc::Receiver<Connection>>),
+}
+
+impl Listener {
+    /// a listener accepting whatever is sent on the returned sender, one end
+    /// of a `Connection::pair` each; it fails once every sender is gone
+    pub fn local(backlog: usize) -> (mpsc::Sender<Connection>, Listener) {
+        let (tx, rx) = mpsc::channel(backlog);
+        (tx, Listener::Local(Mutex::new(rx)))
+    }
+
+    pub async fn bind(endpoint: &Endpoint) -> io::Result<Listener> {
+        match endpoint {
+            Endpoint::Tcp(addr) => Ok(net::TcpListener::bind(addr).await?.into()),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => match net::UnixListener::bind(path) {
+                // left behind by a server that is gone, nobody answers on it
+                Err(err)
+                    if err.kind() == io::ErrorKind::AddrInUse
+                        && net::UnixStream::connect(path).await.is_err() =>
+                {
+                    std::fs::remove_file(path)?;
+                    Ok(net::UnixListener::bind(path)?.into())
+                }
+                res => Ok(res?.into()),
+            },
+        }
+    }
+
+    /// the next connection to come in; cancel safe
+    pub async fn accept(&self) -> io::Result<Connection> {
+        match self {
+            Listener::Tcp(listener) => Ok(listener.accept().await?.0.into()),
+            #[cfg(unix)]
+            Listener::Unix(listener) => Ok(listener.accept().await?.0.into()),
+            Listener::Local(incoming) => incoming
+                .lock()
+                .await
+                .recv()
+                .await
+                .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionAborted)),
+        }
+    }
+}
+
+impl From<net::TcpListener> for Listener {
+    fn from(listener: net::TcpListener) -> Listener {
+        Listener::Tcp(listener)
+    }
+}
+
+#[cfg(unix)]
+impl From<net::UnixListener> for Listener {
+    fn from(listener: net::UnixListener) -> Listener {
+        Listener::Unix(listener)
+    }
+}
+
+enum Transport {
+    Tcp(net::TcpStream),
+    #[cfg(unix)]
+    Unix(net::UnixStream),
+    /// both ends in this process
+    Duplex(io::DuplexStream),
+}
+
+/// a stream to the other side over any transport, which can look at the
+/// next byte without taking it
+pub struct Connection {
+    transport: Transport,
+    /// read ahead by `peek`, handed out before anything else
+    peeked: Option<u8>,
+}
+
+impl Connection {
+    pub async fn connect(endpoint: &Endpoint) -> io::Result<Connection> {
+        match endpoint {
+            Endpoint::Tcp(addr) => Ok(net::TcpStream::connect(addr).await?.into()),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(net::UnixStream::connect(path).await?.into()),
+        }
+    }
+
+    /// two connected ends in memory, each buffering up to `capacity` bytes
+    pub fn pair(capacity: usize) -> (Connection, Connection) {
+        let (one, two) = io::duplex(capacity);
+        (
+            Connection::new(Transport::Duplex(one)),
+            Connection::new(Transport::Duplex(two)),
+        )
+    }
+
+    fn new(transport: Transport) -> Connection {
+        Connection {
+            transport,
+            peeked: None,
+        }
+    }
+
+    /// waits for the next byte and copies it into `buf` without taking it,
+    /// 0 at the end of the stream; cancel safe
+    pub async fn peek(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let peeked = std::future::poll_fn(|cx| self.poll_peek(cx)).await?;
+        match (peeked, buf.first_mut()) {
+            (Some(byte), Some(first)) => {
+                *first = byte;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// the next byte once there is one, `None` at the end of the stream
+    pub fn poll_peek(&mut self, cx: &mut task::Context) -> Poll<io::Result<Option<u8>>> {
+        if self.peeked.is_none() {
+            let mut byte = [0];
+            let mut buf = ReadBuf::new(&mut byte);
+            match Pin::new(&mut self.transport).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(())) => self.peeked = buf.filled().first().copied(),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(self.peeked))
+    }
+}
+
+impl From<net::TcpStream> for Connection {
+    fn from(stream: net::TcpStream) -> Connection {
+        Connection::new(Transport::Tcp(stream))
+    }
+}
+
+#[cfg(unix)]
+impl From<net::UnixStream> for Connection {
+    fn from(stream: net::UnixStream) -> Connection {
+        Connection::new(Transport::Unix(stream))
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if buf.remaining() > 0 {
+            if let Some(byte) = self.peeked.take() {
+                buf.put_slice(&[byte]);
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut self.transport).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.transport).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.transport).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.transport).poll_shutdown(cx)
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+            Transport::Duplex(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+            Transport::Duplex(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            Transport::Duplex(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Transport::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+            Transport::Duplex(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use tokio::time;
+
+use crate::{
+    client::{self, BotUI, Client},
+    conn::Endpoint,
+    logic, prot,
+};
+
+/// queue the kiosk bots pair in, apart from anyone who connects to play
+const KIOSKQUEUE: &str = "kiosk";
+/// how long a bot waits for its opponent before the kiosk gives up
+const HANDSHAKETIMEOUT: time::Duration = time::Duration::from_secs(30);
+pub const DEFAULTDELAY: time::Duration = time::Duration::from_secs(5);
+
+/// two bots playing game after game on a server, so it never idles; for a
+/// demo screen with spectators or a long soak of the whole stack
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    games: Option<u32>,
+    delay: time::Duration,
+    fleet: logic::FleetSpec,
+    difficulty: client::Difficulty,
+}
+
+impl Default for Kiosk {
+    fn default() -> Kiosk {
+        Kiosk {
+            games: None,
+            delay: DEFAULTDELAY,
+            fleet: logic::FleetSpec::default(),
+            difficulty: client::Difficulty::default(),
+        }
+    }
+}
+
+impl Kiosk {
+    pub fn new() -> Kiosk {
+        Kiosk::default()
+    }
+
+    /// stops after this many games instead of going on for good
+    pub fn games(mut self, games: Option<u32>) -> Kiosk {
+        self.games = games;
+        self
+    }
+
+    /// pause between the end of one game and the start of the next
+    pub fn delay(mut self, delay: time::Duration) -> Kiosk {
+        self.delay = delay;
+        self
+    }
+
+    /// fleet the bots place, has to match the server's
+    pub fn fleet(mut self, fleet: logic::FleetSpec) -> Kiosk {
+        self.fleet = fleet;
+        self
+    }
+
+    /// how hard the fleets both bots place are to find
+    pub fn difficulty(mut self, difficulty: client::Difficulty) -> Kiosk {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// plays until the game limit is reached or a game fails; the number of
+    /// games played
+    pub async fn run(self, endpoint: impl Into<Endpoint>) -> Result<u32, client::Error<BotUI>> {
+        let endpoint = endpoint.into();
+        let mut played = 0;
+        while self.games.is_none_or(|games| played < games) {
+            if played > 0 {
+                time::sleep(self.delay).await;
+            }
+            let (first, second) = tokio::join!(self.play(&endpoint), self.play(&endpoint));
+            let winner = if first? { "first" } else { "second" };
+            second?;
+            played += 1;
+            tracing::info!("kiosk game {played} won by the {winner} bot");
+        }
+        Ok(played)
+    }
+
+    /// one bot's game, whether it won
+    async fn play(&self, endpoint: &Endpoint) -> Result<bool, client::Error<BotUI>> {
+        let mut bot = BotUI::new()
+            .fleet(self.fleet.clone())
+            .difficulty(self.difficulty);
+        let queue = prot::QueueKey::new(KIOSKQUEUE).expect("kiosk queue name is valid");
+        let mut client = Client::join(
+            endpoint.clone(),
+            &mut bot,
+            Some(HANDSHAKETIMEOUT),
+            queue,
+            None,
+        )
+        .await?;
+        client.play(&mut bot).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server;
+    use tokio::net;
+
+    #[tokio::test]
+    async fn playsgamesbacktoback() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = server::Config::default();
+        let metrics = std::sync::Arc::clone(&config.metrics);
+        tokio::spawn(server::serve(listener, config));
+
+        let kiosk = Kiosk::new()
+            .games(Some(3))
+            .delay(time::Duration::from_millis(10));
+        let played = kiosk.run(addr).await.unwrap_or_else(|err| panic!("{err}"));
+        assert_eq!(played, 3);
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.started, snapshot.completed), (3, 3));
+    }
+}
@@ -1,5 +1,10 @@
 pub mod client;
+pub mod conn;
+pub mod kiosk;
 pub mod logic;
-pub(crate) mod prot;
+pub mod prot;
+pub mod selftest;
 pub mod server;
+#[cfg(test)]
+mod testing;
 pub mod tui;
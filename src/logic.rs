@@ -1,5 +1,6 @@
 use core::fmt;
-use std::{mem, ops};
+use rand::Rng;
+use std::{array, iter, mem, ops, str};
 
 #[derive(Debug, Clone, Copy)]
 pub enum ShipPlan {
@@ -27,8 +28,9 @@ impl TryFrom<ShipPlan> for Ship {
 
     fn try_from(value: ShipPlan) -> Result<Self, Self::Error> {
         if match value {
-            ShipPlan::Horizontal { pos, len } => pos.coords().0 + len <= 10,
-            ShipPlan::Vertical { pos, len } => pos.coords().1 + len <= 10,
+            // lengths come straight off the wire, adding them could wrap
+            ShipPlan::Horizontal { pos, len } => len <= BOARDSIZE.saturating_sub(pos.coords().0),
+            ShipPlan::Vertical { pos, len } => len <= BOARDSIZE.saturating_sub(pos.coords().1),
         } {
             Ok(Ship(value))
         } else {
@@ -37,6 +39,47 @@ impl TryFrom<ShipPlan> for Ship {
     }
 }
 
+impl Ship {
+    pub fn length(&self) -> u8 {
+        match self.0 {
+            ShipPlan::Horizontal { len, .. } => len,
+            ShipPlan::Vertical { len, .. } => len,
+        }
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        let (x, y) = pos.coords();
+        match self.0 {
+            ShipPlan::Horizontal { pos, len } => {
+                let (sx, sy) = pos.coords();
+                y == sy && (sx..sx + len).contains(&x)
+            }
+            ShipPlan::Vertical { pos, len } => {
+                let (sx, sy) = pos.coords();
+                x == sx && (sy..sy + len).contains(&y)
+            }
+        }
+    }
+
+    pub fn intersects(&self, other: &Ship) -> bool {
+        self.into_iter().any(|pos| other.contains(pos))
+    }
+
+    /// cells touching the ship orthogonally or diagonally, clipped to the board
+    pub fn halo(&self) -> impl Iterator<Item = Position> {
+        let ship = *self;
+        let (pos, width, height) = match self.0 {
+            ShipPlan::Horizontal { pos, len } => (pos, len, 1),
+            ShipPlan::Vertical { pos, len } => (pos, 1, len),
+        };
+        let (sx, sy) = pos.coords();
+        (sy.saturating_sub(1)..=sy + height)
+            .flat_map(move |y| (sx.saturating_sub(1)..=sx + width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Position::fromcoords(x, y))
+            .filter(move |&pos| !ship.contains(pos))
+    }
+}
+
 impl IntoIterator for Ship {
     type Item = Position;
 
@@ -47,31 +90,319 @@ impl IntoIterator for Ship {
     }
 }
 
-#[derive(thiserror::Error, Debug)]
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     #[error("ship overlap")]
     ShipOverlap,
+    #[error("ships touching")]
+    ShipsTouching,
     #[error("invalid ship lengths")]
     InvalidShipLengths,
     #[error("already occupied target position")]
     OccupiedTargetPosition,
+    #[error("position out of bounds; x: {x}, y: {y}")]
+    OutOfBounds { x: u8, y: u8 },
+    #[error("conflicting outcome for an already recorded position")]
+    ConflictingAttackInfo,
+    #[error("invalid attack info byte; {0}")]
+    InvalidAttackInfo(u8),
+    #[error("malformed board notation, expected a column letter and a row number")]
+    InvalidNotation,
+    #[error("illegal move {index}; {kind}")]
+    IllegalMove { index: usize, kind: MoveError },
+    #[error("invalid board size {0}; expected {MINBOARDSIZE} to {MAXBOARDSIZE}, positions pack each coordinate into four bits")]
+    InvalidBoardSize(u8),
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    #[error("out of turn")]
+    OutOfTurn,
+    #[error("target already fired at")]
+    DuplicateTarget,
+    #[error("target off board")]
+    OffBoard,
+    #[error("game already over")]
+    GameOver,
+}
+
+/// side length of the board, every grid, map and position is sized by it
+pub const BOARDSIZE: u8 = 10;
+/// `BOARDSIZE` as an array length
+const BOARDLEN: usize = BOARDSIZE as usize;
+
+const MINBOARDSIZE: u8 = 5;
+/// positions travel as one byte, x in the low and y in the high nibble
+const MAXBOARDSIZE: u8 = 15;
+
+/// `size` if a board that large keeps every position intact on the wire
+pub const fn checkboardsize(size: u8) -> Result<u8, Error> {
+    if size >= MINBOARDSIZE && size <= MAXBOARDSIZE {
+        Ok(size)
+    } else {
+        Err(Error::InvalidBoardSize(size))
+    }
+}
+
+// a board that does not fit the packing fails the build, not a game
+const _: u8 = match checkboardsize(BOARDSIZE) {
+    Ok(size) => size,
+    Err(_) => panic!("BOARDSIZE does not fit the position packing"),
+};
+
+/// side length of a square board, validated so positions cannot wrap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardConfig {
+    size: u8,
+}
+
+impl Default for BoardConfig {
+    fn default() -> BoardConfig {
+        BoardConfig { size: BOARDSIZE }
+    }
+}
+
+impl BoardConfig {
+    pub fn new(size: u8) -> Result<BoardConfig, Error> {
+        checkboardsize(size).map(|size| BoardConfig { size })
+    }
+
+    pub fn size(self) -> u8 {
+        self.size
+    }
+}
+
+/// lengths of the standard fleet, shortest first
+pub const SHIPLENGTHS: [u8; 5] = [2, 3, 3, 4, 5];
+
+/// classic name of a ship by its length
+pub fn shipname(len: u8) -> &'static str {
+    match len {
+        5 => "carrier",
+        4 => "battleship",
+        3 => "cruiser",
+        2 => "destroyer",
+        _ => "ship",
+    }
+}
+
+/// most ships a fleet may have
+pub const MAXSHIPS: usize = 5;
+
+/// random placements tried before a fleet is given up as not fitting
+const MAXPLACEMENTTRIES: u32 = 100_000;
+
+/// named fleets to pick instead of listing lengths
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FleetPreset {
+    #[default]
+    Classic,
+    /// the 1967 Milton Bradley board game, where the classic lengths come from
+    MiltonBradley1967,
+    /// the 1990 Milton Bradley edition, the same lengths under other names
+    MiltonBradley1990,
+    /// fewer cells to find, for quick games
+    Small,
+    /// more cells, for long games
+    Mega,
+}
+
+impl FleetPreset {
+    pub const ALL: [FleetPreset; 5] = [
+        FleetPreset::Classic,
+        FleetPreset::MiltonBradley1967,
+        FleetPreset::MiltonBradley1990,
+        FleetPreset::Small,
+        FleetPreset::Mega,
+    ];
+
+    /// ship lengths, longest first
+    pub fn fleet(self) -> &'static [u8] {
+        match self {
+            FleetPreset::Classic => &[5, 4, 3, 3, 2],
+            FleetPreset::MiltonBradley1967 => &[5, 4, 3, 3, 2],
+            FleetPreset::MiltonBradley1990 => &[5, 4, 3, 3, 2],
+            FleetPreset::Small => &[3, 2, 2],
+            FleetPreset::Mega => &[6, 5, 5, 4, 4],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FleetPreset::Classic => "classic",
+            FleetPreset::MiltonBradley1967 => "mb1967",
+            FleetPreset::MiltonBradley1990 => "mb1990",
+            FleetPreset::Small => "small",
+            FleetPreset::Mega => "mega",
+        }
+    }
+}
+
+impl fmt::Display for FleetPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown fleet preset `{0}`; expected classic, mb1967, mb1990, small or mega")]
+pub struct UnknownPreset(pub String);
+
+impl str::FromStr for FleetPreset {
+    type Err = UnknownPreset;
+
+    fn from_str(name: &str) -> Result<FleetPreset, UnknownPreset> {
+        FleetPreset::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| UnknownPreset(name.to_owned()))
+    }
+}
+
+/// ship lengths a fleet has to consist of, each length as often as it is
+/// listed, and how they may be placed; the classic fleet by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FleetSpec {
+    /// shortest first
+    lengths: Vec<u8>,
+    /// ships may not touch, not even diagonally
+    notouch: bool,
+}
+
+impl Default for FleetSpec {
+    fn default() -> FleetSpec {
+        FleetSpec {
+            lengths: SHIPLENGTHS.to_vec(),
+            notouch: false,
+        }
+    }
+}
+
+impl FleetSpec {
+    /// every ship has to fit the board, and a fleet needs one to `MAXSHIPS`
+    pub fn new(lengths: impl IntoIterator<Item = u8>) -> Result<FleetSpec, Error> {
+        let mut lengths: Vec<_> = lengths.into_iter().collect();
+        if !(1..=MAXSHIPS).contains(&lengths.len())
+            || lengths.iter().any(|&len| len == 0 || len > BOARDSIZE)
+        {
+            return Err(Error::InvalidShipLengths);
+        }
+        lengths.sort_unstable();
+        Ok(FleetSpec {
+            lengths,
+            notouch: false,
+        })
+    }
+
+    /// forbids ships from touching, not even diagonally
+    pub fn notouch(mut self, enabled: bool) -> FleetSpec {
+        self.notouch = enabled;
+        self
+    }
+
+    pub fn istouchforbidden(&self) -> bool {
+        self.notouch
+    }
+
+    /// shortest first
+    pub fn lengths(&self) -> &[u8] {
+        &self.lengths
+    }
+
+    /// whether the ships have exactly the listed lengths, in any order
+    pub fn matches(&self, ships: &[Ship]) -> bool {
+        let mut lengths: Vec<_> = ships.iter().map(Ship::length).collect();
+        lengths.sort_unstable();
+        lengths == self.lengths
+    }
+
+    /// places this fleet at random, retrying until it follows the rules;
+    /// `None` if it did not fit after `MAXPLACEMENTTRIES`
+    pub fn random(&self, rng: &mut impl Rng) -> Option<Ships> {
+        for _ in 0..MAXPLACEMENTTRIES {
+            let ships: Vec<_> = self
+                .lengths
+                .iter()
+                .map(|&len| {
+                    let plan = if rng.random() {
+                        ShipPlan::Horizontal {
+                            pos: Position::fromcoords(
+                                rng.random_range(0..=BOARDSIZE - len),
+                                rng.random_range(0..BOARDSIZE),
+                            )
+                            .unwrap(),
+                            len,
+                        }
+                    } else {
+                        ShipPlan::Vertical {
+                            pos: Position::fromcoords(
+                                rng.random_range(0..BOARDSIZE),
+                                rng.random_range(0..=BOARDSIZE - len),
+                            )
+                            .unwrap(),
+                            len,
+                        }
+                    };
+                    Ship::try_from(plan).unwrap()
+                })
+                .collect();
+
+            if let Ok(ships) = Ships::validate(&ships, self) {
+                return Some(ships);
+            }
+        }
+        None
+    }
+}
+
+impl From<FleetPreset> for FleetSpec {
+    fn from(preset: FleetPreset) -> FleetSpec {
+        let mut lengths = preset.fleet().to_vec();
+        lengths.sort_unstable();
+        FleetSpec {
+            lengths,
+            notouch: false,
+        }
+    }
+}
+
+/// a placed fleet of one to `MAXSHIPS` ships, kept inline to stay `Copy`
+#[derive(Clone, Copy)]
+pub struct Ships {
+    /// slots past `len` repeat the first ship
+    ships: [Ship; MAXSHIPS],
+    len: u8,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct Ships([Ship; 5]);
 impl Ships {
-    pub fn asarray(&self) -> &[Ship; 5] {
-        &self.0
+    pub fn asslice(&self) -> &[Ship] {
+        &self.ships[..self.len as usize]
+    }
+
+    fn new(ships: &[Ship]) -> Result<Ships, Error> {
+        let Some(&first) = ships.first().filter(|_| ships.len() <= MAXSHIPS) else {
+            return Err(Error::InvalidShipLengths);
+        };
+        Ok(Ships {
+            ships: array::from_fn(|i| ships.get(i).copied().unwrap_or(first)),
+            len: ships.len() as u8,
+        })
+    }
+}
+
+impl fmt::Debug for Ships {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Ships").field(&self.asslice()).finish()
     }
 }
 
 impl IntoIterator for Ships {
-    type Item = <[Ship; 5] as IntoIterator>::Item;
+    type Item = Ship;
 
-    type IntoIter = <[Ship; 5] as IntoIterator>::IntoIter;
+    type IntoIter = iter::Take<array::IntoIter<Ship, MAXSHIPS>>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.ships.into_iter().take(self.len as usize)
     }
 }
 
@@ -79,43 +410,43 @@ impl ops::Index<usize> for Ships {
     type Output = Ship;
 
     fn index(&self, index: usize) -> &Self::Output {
-        &self.0[index]
+        &self.asslice()[index]
     }
 }
 
-impl TryFrom<[Ship; 5]> for Ships {
-    type Error = Error;
-
-    fn try_from(ships: [Ship; 5]) -> Result<Self, Self::Error> {
-        const SHIPLENGTHS: [u8; 5] = [2, 3, 3, 4, 5];
+impl Ships {
+    /// checks the ships do not overlap, whatever their lengths
+    pub fn placed(ships: &[Ship]) -> Result<Ships, Error> {
+        if !validshippos(ships) {
+            return Err(Error::ShipOverlap);
+        }
+        Ships::new(ships)
+    }
 
-        let mut shipmap = [[false; 10]; 10];
-        let mut shiplenmap = [false; SHIPLENGTHS.len()];
-        for ship in ships {
-            let shiplen = match ship.into() {
-                ShipPlan::Horizontal { len, .. } => len,
-                ShipPlan::Vertical { len, .. } => len,
-            };
+    /// checks the ships against `fleet` and each other
+    pub fn validate(ships: &[Ship], fleet: &FleetSpec) -> Result<Ships, Error> {
+        if !fleet.matches(ships) {
+            return Err(Error::InvalidShipLengths);
+        }
 
-            *Iterator::zip(shiplenmap.iter_mut(), SHIPLENGTHS.into_iter())
-                .find_map(|(found, len)| {
-                    if !*found && len == shiplen {
-                        Some(found)
-                    } else {
-                        None
-                    }
-                })
-                .ok_or(Error::InvalidShipLengths)? = true;
+        if !validshippos(ships) {
+            return Err(Error::ShipOverlap);
+        }
 
-            for pos in ship {
-                let (x, y) = pos.coords();
-                if mem::replace(&mut shipmap[y as usize][x as usize], true) {
-                    return Err(Error::ShipOverlap);
-                }
-            }
+        if fleet.notouch && !validshipposnotouch(ships) {
+            return Err(Error::ShipsTouching);
         }
 
-        Ok(Ships(ships))
+        Ships::new(ships)
+    }
+}
+
+/// validates against the classic fleet
+impl<const N: usize> TryFrom<[Ship; N]> for Ships {
+    type Error = Error;
+
+    fn try_from(ships: [Ship; N]) -> Result<Self, Self::Error> {
+        Ships::validate(&ships, &FleetSpec::default())
     }
 }
 
@@ -142,8 +473,11 @@ impl Iterator for ShipPositionIter {
     }
 }
 
-#[derive(Clone, Copy, Default, PartialEq, Eq)]
-pub struct Position(u8);
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    x: u8,
+    y: u8,
+}
 
 impl fmt::Debug for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -153,35 +487,64 @@ impl fmt::Debug for Position {
 }
 
 impl Position {
+    /// wire form, x in the low and y in the high nibble
     pub fn frombyte(i: u8) -> Option<Position> {
-        let (x, y) = Position::coords(Position(i));
-        Position::fromcoords(x, y)
+        Position::fromcoords(i & 0x0f, i >> 4)
     }
 
     pub fn byte(self) -> u8 {
-        self.0
+        self.x | (self.y << 4)
+    }
+
+    pub fn new(x: u8, y: u8) -> Result<Position, Error> {
+        Position::fromcoords(x, y).ok_or(Error::OutOfBounds { x, y })
     }
 
     pub fn fromcoords(x: u8, y: u8) -> Option<Position> {
-        if x < 10 && y < 10 {
-            Some(Position(x + (y << 4)))
+        if x < BOARDSIZE && y < BOARDSIZE {
+            Some(Position { x, y })
         } else {
             None
         }
     }
 
     pub fn coords(self) -> (u8, u8) {
-        (self.0 & 0x0f, self.0 >> 4)
+        (self.x, self.y)
     }
 
     pub fn toboard(self) -> [&'static str; 2] {
-        const MAPX: [&str; 10] = ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J"];
-        const MAPY: [&str; 10] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"];
+        const MAPX: [&str; MAXBOARDSIZE as usize] = [
+            "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O",
+        ];
+        const MAPY: [&str; MAXBOARDSIZE as usize] = [
+            "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
+        ];
         let (x, y) = self.coords();
         [MAPX[x as usize], MAPY[y as usize]]
     }
 }
 
+/// board notation as given by `toboard`, the column letter in any case
+impl str::FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Position, Error> {
+        let mut chars = s.chars();
+        let column = chars
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .ok_or(Error::InvalidNotation)?;
+        let row = chars.as_str();
+        if !row.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::InvalidNotation);
+        }
+        let row: u8 = row.parse().map_err(|_| Error::InvalidNotation)?;
+        let x = column.to_ascii_uppercase() as u8 - b'A';
+        let y = row.checked_sub(1).ok_or(Error::InvalidNotation)?;
+        Position::new(x, y)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 struct ShipReference(u8);
 
@@ -209,35 +572,267 @@ impl ShipReference {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AttackInfo {
     Hit(bool),
     Miss,
 }
 
+impl AttackInfo {
+    /// two bit form of a cell; 0 unknown, 1 miss, 2 hit, 3 sunken
+    pub fn tobyte(info: Option<AttackInfo>) -> u8 {
+        match info {
+            None => 0,
+            Some(AttackInfo::Miss) => 1,
+            Some(AttackInfo::Hit(false)) => 2,
+            Some(AttackInfo::Hit(true)) => 3,
+        }
+    }
+
+    pub fn frombyte(byte: u8) -> Result<Option<AttackInfo>, Error> {
+        match byte {
+            0 => Ok(None),
+            1 => Ok(Some(AttackInfo::Miss)),
+            2 => Ok(Some(AttackInfo::Hit(false))),
+            3 => Ok(Some(AttackInfo::Hit(true))),
+            _ => Err(Error::InvalidAttackInfo(byte)),
+        }
+    }
+}
+
+/// known outcomes of the shots fired at a board, without its ships
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HitGrid([[Option<AttackInfo>; BOARDLEN]; BOARDLEN]);
+
+impl HitGrid {
+    pub fn new() -> HitGrid {
+        HitGrid::default()
+    }
+
+    pub fn get(&self, pos: Position) -> Option<AttackInfo> {
+        let (x, y) = pos.coords();
+        self.0[y as usize][x as usize]
+    }
+
+    /// records a known outcome; applying the same outcome again is a no-op
+    pub fn apply(&mut self, pos: Position, info: AttackInfo) -> Result<(), Error> {
+        let (x, y) = pos.coords();
+        let cell = &mut self.0[y as usize][x as usize];
+        match cell {
+            Some(recorded) if *recorded != info => Err(Error::ConflictingAttackInfo),
+            _ => {
+                *cell = Some(info);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// set of board positions, one flag per cell instead of hashing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PositionSet([[bool; BOARDLEN]; BOARDLEN]);
+
+impl PositionSet {
+    pub fn new() -> PositionSet {
+        PositionSet::default()
+    }
+
+    /// `false` if the position was already in the set
+    pub fn insert(&mut self, pos: Position) -> bool {
+        let (x, y) = pos.coords();
+        !std::mem::replace(&mut self.0[y as usize][x as usize], true)
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        let (x, y) = pos.coords();
+        self.0[y as usize][x as usize]
+    }
+
+    /// positions in the set, row by row
+    pub fn iter(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..BOARDSIZE)
+            .flat_map(|y| (0..BOARDSIZE).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.0[y as usize][x as usize])
+            .filter_map(|(x, y)| Position::fromcoords(x, y))
+    }
+}
+
+impl Extend<Position> for PositionSet {
+    fn extend<T: IntoIterator<Item = Position>>(&mut self, iter: T) {
+        for pos in iter {
+            self.insert(pos);
+        }
+    }
+}
+
+impl FromIterator<Position> for PositionSet {
+    fn from_iter<T: IntoIterator<Item = Position>>(iter: T) -> PositionSet {
+        let mut set = PositionSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl ops::Index<usize> for HitGrid {
+    type Output = [Option<AttackInfo>; BOARDLEN];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Board {
     ships: Ships,
-    shipmap: [[ShipReference; 10]; 10],
-    hitmap: [[bool; 10]; 10],
+    shipcells: Vec<Vec<Position>>,
+    shipmap: [[ShipReference; BOARDLEN]; BOARDLEN],
+    hitmap: [[bool; BOARDLEN]; BOARDLEN],
+}
+
+/// places the standard fleet at random, retrying until no ships overlap
+pub fn randomplacement() -> Ships {
+    randomfleet(&mut rand::rng())
+}
+
+/// `randomplacement` drawing from `rng`, the same seed yields the same fleet
+pub fn randomfleet(rng: &mut impl Rng) -> Ships {
+    FleetSpec::default()
+        .random(rng)
+        .expect("the classic fleet always fits")
 }
 
-pub fn validshippos(ships: &[Ship; 5]) -> bool {
-    let mut shipmap = [[false; 10]; 10];
-    for ship in ships {
-        for pos in *ship {
-            let (x, y) = pos.coords();
-            if mem::replace(&mut shipmap[y as usize][x as usize], true) {
-                return false;
+/// cells of the fleet not hit yet, the fleet is lost once none are left
+pub fn survivingcells(ships: &[Ship], hits: &HitGrid) -> u8 {
+    ships
+        .iter()
+        .flat_map(|ship| ship.into_iter())
+        .filter(|&pos| hits.get(pos).is_none())
+        .count() as u8
+}
+
+/// every position of the grid that has not been fired at yet
+pub fn legaltargets(hits: &HitGrid) -> Vec<Position> {
+    (0..BOARDSIZE)
+        .flat_map(|y| (0..BOARDSIZE).map(move |x| (x, y)))
+        .filter_map(|(x, y)| Position::fromcoords(x, y))
+        .filter(|&pos| hits.get(pos).is_none())
+        .collect()
+}
+
+/// contiguous hits grouped into horizontal and vertical runs, rows first;
+/// a hit may sit in one run of each direction where runs cross, hits with
+/// no hit neighbour form runs of their own
+pub fn hitruns(hits: &HitGrid) -> Vec<Vec<Position>> {
+    let ishit = |x: u8, y: u8| {
+        Position::fromcoords(x, y)
+            .is_some_and(|pos| matches!(hits.get(pos), Some(AttackInfo::Hit(_))))
+    };
+    let mut runs = Vec::new();
+    let mut inrun = PositionSet::new();
+    // walks every line of the grid, `cell` maps (line, step) to coordinates
+    for cell in [|line, step| (step, line), |line, step| (line, step)] {
+        for line in 0..BOARDSIZE {
+            let mut run = Vec::new();
+            for step in 0..=BOARDSIZE {
+                let (x, y) = cell(line, step);
+                if ishit(x, y) {
+                    run.extend(Position::fromcoords(x, y));
+                } else if run.len() >= 2 {
+                    inrun.extend(run.iter().copied());
+                    runs.push(std::mem::take(&mut run));
+                } else {
+                    run.clear();
+                }
+            }
+        }
+    }
+    let isolated = (0..BOARDSIZE)
+        .flat_map(|y| (0..BOARDSIZE).map(move |x| (x, y)))
+        .filter(|&(x, y)| ishit(x, y))
+        .filter_map(|(x, y)| Position::fromcoords(x, y))
+        .filter(|&pos| !inrun.contains(pos))
+        .map(|pos| vec![pos]);
+    runs.extend(isolated);
+    runs
+}
+
+/// splits the sunken cells of a grid into ships of the given lengths, which
+/// may be left over for ships afloat; `None` if no split fits the cells
+pub fn sunkenships(hits: &HitGrid, lengths: &[u8]) -> Option<Vec<Ship>> {
+    let sunken: Vec<Position> = (0..BOARDSIZE)
+        .flat_map(|y| (0..BOARDSIZE).map(move |x| (x, y)))
+        .filter_map(|(x, y)| Position::fromcoords(x, y))
+        .filter(|&pos| hits.get(pos) == Some(AttackInfo::Hit(true)))
+        .collect();
+    let mut ships = Vec::new();
+    let mut used = vec![false; lengths.len()];
+    splitsunken(&sunken, PositionSet::new(), lengths, &mut used, &mut ships).then_some(ships)
+}
+
+fn splitsunken(
+    sunken: &[Position],
+    covered: PositionSet,
+    lengths: &[u8],
+    used: &mut [bool],
+    ships: &mut Vec<Ship>,
+) -> bool {
+    // rows first, so the first cell left over is the top or left end of its ship
+    let Some(&pos) = sunken.iter().find(|&&pos| !covered.contains(pos)) else {
+        return true;
+    };
+    for idx in 0..lengths.len() {
+        let len = lengths[idx];
+        // equal lengths would only try the same ships again
+        if used[idx] || (0..idx).any(|prev| !used[prev] && lengths[prev] == len) {
+            continue;
+        }
+        for plan in [
+            ShipPlan::Horizontal { pos, len },
+            ShipPlan::Vertical { pos, len },
+        ] {
+            let Ok(ship) = Ship::try_from(plan) else {
+                continue;
+            };
+            if !ship
+                .into_iter()
+                .all(|cell| sunken.contains(&cell) && !covered.contains(cell))
+            {
+                continue;
             }
+            let mut next = covered;
+            next.extend(ship);
+            used[idx] = true;
+            ships.push(ship);
+            if splitsunken(sunken, next, lengths, used, ships) {
+                return true;
+            }
+            ships.pop();
+            used[idx] = false;
         }
     }
-    true
+    false
+}
+
+pub fn validshippos(ships: &[Ship]) -> bool {
+    ships
+        .iter()
+        .enumerate()
+        .all(|(i, ship)| ships[i + 1..].iter().all(|other| !ship.intersects(other)))
+}
+
+/// `validshippos`, and no ship lies in the halo of another
+pub fn validshipposnotouch(ships: &[Ship]) -> bool {
+    validshippos(ships)
+        && ships.iter().enumerate().all(|(i, ship)| {
+            ships[i + 1..]
+                .iter()
+                .all(|other| !ship.halo().any(|pos| other.contains(pos)))
+        })
 }
 
 impl Board {
     pub fn new(ships: Ships) -> Board {
-        let mut shipmap = [[ShipReference::empty(); 10]; 10];
+        let mut shipmap = [[ShipReference::empty(); BOARDLEN]; BOARDLEN];
         for (i, ship) in ships.into_iter().enumerate() {
             for pos in ship {
                 let (x, y) = pos.coords();
@@ -247,8 +842,12 @@ impl Board {
 
         Board {
             ships,
+            shipcells: ships
+                .into_iter()
+                .map(|ship| ship.into_iter().collect())
+                .collect(),
             shipmap,
-            hitmap: [[false; 10]; 10],
+            hitmap: [[false; BOARDLEN]; BOARDLEN],
         }
     }
 
@@ -261,26 +860,625 @@ impl Board {
         }
 
         match self.shipmap[y as usize][x as usize].inner() {
-            Some(shipref) => Some(AttackInfo::Hit(
-                self.ships[shipref as usize].into_iter().all(|p| {
-                    let (x, y) = p.coords();
-                    self.hitmap[y as usize][x as usize]
-                }),
-            )),
+            Some(shipref) => Some(AttackInfo::Hit(self.sunken(shipref as usize))),
             None => Some(AttackInfo::Miss),
         }
     }
 
-    pub fn allsunken(&self) -> bool {
-        self.ships.into_iter().all(|ship| {
-            ship.into_iter().all(|p| {
-                let (x, y) = p.coords();
-                self.hitmap[y as usize][x as usize]
-            })
+    fn sunken(&self, shipidx: usize) -> bool {
+        self.shipcells[shipidx].iter().all(|p| {
+            let (x, y) = p.coords();
+            self.hitmap[y as usize][x as usize]
         })
     }
 
+    pub fn allsunken(&self) -> bool {
+        (0..self.shipcells.len()).all(|i| self.sunken(i))
+    }
+
+    /// index and length of the ship at `pos`, if it has been sunken
+    pub fn sunkenship(&self, pos: Position) -> Option<(u8, u8)> {
+        let (x, y) = pos.coords();
+        let idx = self.shipmap[y as usize][x as usize].inner()?;
+        self.sunken(idx as usize)
+            .then(|| (idx, self.shipcells[idx as usize].len() as u8))
+    }
+
+    /// ships with at least one cell not hit yet
+    pub fn remainingships(&self) -> u8 {
+        (0..self.shipcells.len())
+            .filter(|&i| !self.sunken(i))
+            .count() as u8
+    }
+
+    pub fn sunkenships(&self) -> u8 {
+        self.shipcells.len() as u8 - self.remainingships()
+    }
+
     pub fn ships(&self) -> &Ships {
         &self.ships
     }
+
+    /// what the opponent knows about this board, without the ships
+    pub fn hitgrid(&self) -> HitGrid {
+        let mut grid = HitGrid::new();
+        for (y, (hitrow, shiprow)) in
+            Iterator::zip(self.hitmap.iter(), self.shipmap.iter()).enumerate()
+        {
+            for (x, (hit, shipref)) in Iterator::zip(hitrow.iter(), shiprow.iter()).enumerate() {
+                if *hit {
+                    grid.0[y][x] = Some(match shipref.inner() {
+                        Some(idx) => AttackInfo::Hit(self.sunken(idx as usize)),
+                        None => AttackInfo::Miss,
+                    });
+                }
+            }
+        }
+        grid
+    }
+
+    /// statistics of the shots fired at this board
+    pub fn stats(&self) -> ShotStats {
+        let mut stats = ShotStats::default();
+        for (hitrow, shiprow) in Iterator::zip(self.hitmap.iter(), self.shipmap.iter()) {
+            for (hit, shipref) in Iterator::zip(hitrow.iter(), shiprow.iter()) {
+                match (hit, shipref.inner()) {
+                    (true, Some(_)) => stats.hits += 1,
+                    (true, None) => stats.misses += 1,
+                    (false, _) => {}
+                }
+            }
+        }
+        stats
+    }
+}
+
+/// both public grids from one player's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardSnapshot {
+    pub selfhits: HitGrid,
+    pub opphits: HitGrid,
+    pub yourturn: bool,
+}
+
+/// a shot as submitted, not yet known to be on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    /// index of the firing player, in connection order
+    pub player: u8,
+    pub x: u8,
+    pub y: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameState {
+    /// boards of both players, in connection order
+    pub boards: [Board; 2],
+    /// player to fire next
+    pub turn: u8,
+    pub shots: u16,
+    pub winner: Option<u8>,
+}
+
+/// replays a submitted game under the server rules, a hit keeps the turn,
+/// and fails on the first illegal move
+pub fn verifygame(fleet1: Ships, fleet2: Ships, moves: &[Move]) -> Result<GameState, Error> {
+    let mut state = GameState {
+        boards: [Board::new(fleet1), Board::new(fleet2)],
+        turn: 0,
+        shots: 0,
+        winner: None,
+    };
+    for (index, mv) in moves.iter().enumerate() {
+        let illegal = |kind| Error::IllegalMove { index, kind };
+        if state.winner.is_some() {
+            return Err(illegal(MoveError::GameOver));
+        }
+        if mv.player != state.turn {
+            return Err(illegal(MoveError::OutOfTurn));
+        }
+        let target = Position::fromcoords(mv.x, mv.y).ok_or(illegal(MoveError::OffBoard))?;
+        let board = &mut state.boards[(state.turn as usize + 1) % 2];
+        let info = board
+            .target(target)
+            .ok_or(illegal(MoveError::DuplicateTarget))?;
+        state.shots += 1;
+        match info {
+            AttackInfo::Miss => state.turn = (state.turn + 1) % 2,
+            AttackInfo::Hit(_) if board.allsunken() => state.winner = Some(state.turn),
+            AttackInfo::Hit(_) => {}
+        }
+    }
+    Ok(state)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShotStats {
+    pub hits: u8,
+    pub misses: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameSummary {
+    /// index of the winning player, in connection order
+    pub winner: u8,
+    pub turns: u16,
+    /// shots fired by each player, in connection order
+    pub stats: [ShotStats; 2],
+}
+
+impl GameSummary {
+    /// shot statistics ordered as `[own, opponent]` for the given side
+    pub fn perspective(&self, victory: bool) -> [ShotStats; 2] {
+        let [first, second] = self.stats;
+        if (self.winner == 0) == victory {
+            [first, second]
+        } else {
+            [second, first]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ship(x: u8, y: u8, len: u8, horizontal: bool) -> Ship {
+        let pos = Position::fromcoords(x, y).unwrap();
+        let plan = if horizontal {
+            ShipPlan::Horizontal { pos, len }
+        } else {
+            ShipPlan::Vertical { pos, len }
+        };
+        Ship::try_from(plan).unwrap()
+    }
+
+    #[test]
+    fn shipsintersect() {
+        let at = |x, y| Position::fromcoords(x, y).unwrap();
+        let a = ship(2, 3, 4, true);
+        assert!(a.contains(at(2, 3)));
+        assert!(a.contains(at(5, 3)));
+        assert!(!a.contains(at(6, 3)));
+        assert!(!a.contains(at(2, 4)));
+
+        // identical
+        assert!(a.intersects(&a));
+        assert!(a.intersects(&ship(2, 3, 4, true)));
+        // crossing at (4, 3)
+        let crossing = ship(4, 1, 5, false);
+        assert!(a.intersects(&crossing) && crossing.intersects(&a));
+        // touching end to end, side by side and corner to corner
+        for touching in [
+            ship(6, 3, 2, true),
+            ship(2, 4, 4, true),
+            ship(6, 4, 3, false),
+        ] {
+            assert!(!a.intersects(&touching) && !touching.intersects(&a));
+        }
+        // far apart
+        assert!(!a.intersects(&ship(0, 9, 5, true)));
+    }
+
+    #[test]
+    fn randomfleetsvalid() {
+        use rand::SeedableRng;
+
+        for seed in 0..1000 {
+            let ships = randomfleet(&mut rand::rngs::StdRng::seed_from_u64(seed));
+            let ships: [Ship; 5] = ships.asslice().try_into().unwrap();
+            assert!(Ships::try_from(ships).is_ok(), "seed {seed}");
+        }
+        // the same seed places the same fleet
+        let [first, second] = [(); 2].map(|()| {
+            let ships = randomfleet(&mut rand::rngs::StdRng::seed_from_u64(7));
+            ships
+                .into_iter()
+                .map(ShipPlan::from)
+                .map(|plan| format!("{plan:?}"))
+        });
+        assert!(first.eq(second));
+    }
+
+    #[test]
+    fn boardsizelimits() {
+        assert_eq!(checkboardsize(10).unwrap(), 10);
+        assert_eq!(checkboardsize(15).unwrap(), 15);
+        let err = checkboardsize(16).unwrap_err();
+        assert!(matches!(err, Error::InvalidBoardSize(16)));
+        assert!(err.to_string().contains("four bits"));
+        assert!(checkboardsize(4).is_err());
+    }
+
+    #[test]
+    fn boardconfig() {
+        assert_eq!(BoardConfig::default().size(), BOARDSIZE);
+        assert_eq!(BoardConfig::new(15).unwrap().size(), 15);
+        assert!(matches!(
+            BoardConfig::new(16),
+            Err(Error::InvalidBoardSize(16))
+        ));
+        assert!(matches!(
+            BoardConfig::new(4),
+            Err(Error::InvalidBoardSize(4))
+        ));
+    }
+
+    #[test]
+    fn presetfleets() {
+        for (preset, mut lengths) in [
+            (FleetPreset::Classic, vec![5, 4, 3, 3, 2]),
+            (FleetPreset::MiltonBradley1967, vec![5, 4, 3, 3, 2]),
+            (FleetPreset::MiltonBradley1990, vec![5, 4, 3, 3, 2]),
+            (FleetPreset::Small, vec![3, 2, 2]),
+            (FleetPreset::Mega, vec![6, 5, 5, 4, 4]),
+        ] {
+            assert_eq!(preset.fleet().to_vec(), lengths);
+            assert_eq!(preset.name().parse::<FleetPreset>().unwrap(), preset);
+            let fleet = FleetSpec::from(preset);
+            lengths.sort_unstable();
+            assert_eq!(fleet.lengths(), lengths);
+            let ships = fleet.random(&mut rand::rng()).unwrap();
+            assert!(fleet.matches(ships.asslice()));
+        }
+        assert_eq!(FleetSpec::from(FleetPreset::Classic), FleetSpec::default());
+        assert_eq!(" Mega".parse::<FleetPreset>().unwrap(), FleetPreset::Mega);
+
+        let err = "russian".parse::<FleetPreset>().unwrap_err();
+        assert_eq!(err, UnknownPreset(String::from("russian")));
+        assert!(err.to_string().contains("`russian`"));
+        assert!(err.to_string().contains("mb1967"));
+    }
+
+    #[test]
+    fn notationbounds() {
+        let parse = |s: &str| s.parse::<Position>().map(Position::coords);
+        assert_eq!(parse("A1"), Ok((0, 0)));
+        assert_eq!(parse("J10"), Ok((9, 9)));
+        assert_eq!(parse("c7"), Ok((2, 6)));
+        assert_eq!(parse("K1"), Err(Error::OutOfBounds { x: 10, y: 0 }));
+        assert_eq!(parse("A11"), Err(Error::OutOfBounds { x: 0, y: 10 }));
+        for bad in [
+            "", "A", "1A", "A0", "A-1", "A+1", "AB1", "A 1", " A1", "Ä1", "A999",
+        ] {
+            assert_eq!(parse(bad), Err(Error::InvalidNotation), "{bad:?}");
+        }
+        for pos in
+            (0..BOARDSIZE).flat_map(|y| (0..BOARDSIZE).map(move |x| Position::new(x, y).unwrap()))
+        {
+            assert_eq!(pos.toboard().concat().parse(), Ok(pos));
+        }
+    }
+
+    #[test]
+    fn hitrunsgroup() {
+        let runs = |cells: &[(u8, u8)]| {
+            let mut hits = HitGrid::new();
+            for &(x, y) in cells {
+                hits.apply(Position::new(x, y).unwrap(), AttackInfo::Hit(false))
+                    .unwrap();
+            }
+            // a miss next to a run neither joins nor splits it
+            hits.apply(Position::new(9, 0).unwrap(), AttackInfo::Miss)
+                .unwrap();
+            hitruns(&hits)
+                .into_iter()
+                .map(|run| run.into_iter().map(Position::coords).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert!(runs(&[]).is_empty());
+        // a straight line, its cells in order
+        assert_eq!(
+            runs(&[(6, 0), (7, 0), (8, 0)]),
+            [vec![(6, 0), (7, 0), (8, 0)]]
+        );
+        assert_eq!(
+            runs(&[(2, 5), (2, 3), (2, 4)]),
+            [vec![(2, 3), (2, 4), (2, 5)]]
+        );
+        // an L, the corner in both legs
+        assert_eq!(
+            runs(&[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]),
+            [vec![(0, 2), (1, 2), (2, 2)], vec![(0, 0), (0, 1), (0, 2)]]
+        );
+        // a cross, the centre in both runs
+        assert_eq!(
+            runs(&[(4, 3), (4, 4), (4, 5), (3, 4), (5, 4)]),
+            [vec![(3, 4), (4, 4), (5, 4)], vec![(4, 3), (4, 4), (4, 5)]]
+        );
+        // diagonal neighbours do not join, each hit is a run of one
+        assert_eq!(
+            runs(&[(1, 1), (2, 2), (5, 7)]),
+            [vec![(1, 1)], vec![(2, 2)], vec![(5, 7)]]
+        );
+        // isolated hits come after the runs
+        assert_eq!(
+            runs(&[(0, 9), (3, 0), (4, 0)]),
+            [vec![(3, 0), (4, 0)], vec![(0, 9)]]
+        );
+    }
+
+    #[test]
+    fn sunkenshipssplit() {
+        let mut hits = HitGrid::new();
+        // a ship of two in column A touching a ship of three along row 3
+        for (x, y) in [(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)] {
+            let pos = Position::fromcoords(x, y).unwrap();
+            hits.apply(pos, AttackInfo::Hit(true)).unwrap();
+        }
+        let pos = Position::fromcoords(5, 5).unwrap();
+        hits.apply(pos, AttackInfo::Hit(false)).unwrap();
+        let ships = sunkenships(&hits, &SHIPLENGTHS).unwrap();
+        let mut lengths: Vec<u8> = ships.iter().map(Ship::length).collect();
+        lengths.sort();
+        assert_eq!(lengths, [2, 3]);
+        assert!(sunkenships(&hits, &[5, 4]).is_none());
+
+        let pos = Position::fromcoords(9, 9).unwrap();
+        hits.apply(pos, AttackInfo::Hit(true)).unwrap();
+        assert!(sunkenships(&hits, &SHIPLENGTHS).is_none());
+    }
+
+    /// the classic fleet stacked vertically in the five leftmost columns
+    fn stacked() -> Ships {
+        const LENGTHS: [u8; 5] = [5, 4, 3, 3, 2];
+        let ships: [Ship; 5] = array::from_fn(|x| ship(x as u8, 0, LENGTHS[x], false));
+        Ships::try_from(ships).unwrap()
+    }
+
+    #[test]
+    fn verifygamerejects() {
+        let mv = |player, x, y| Move { player, x, y };
+        let verify = |moves: &[Move]| verifygame(stacked(), stacked(), moves);
+
+        let illegal = |index, kind| Err(Error::IllegalMove { index, kind });
+        assert_eq!(
+            verify(&[mv(1, 9, 9)]).map(|state| state.turn),
+            illegal(0, MoveError::OutOfTurn)
+        );
+        // the same cell on the other board is fine, the second time on the
+        // same one is not
+        assert_eq!(
+            verify(&[mv(0, 9, 9), mv(1, 9, 9), mv(0, 9, 9)]).map(|state| state.turn),
+            illegal(2, MoveError::DuplicateTarget)
+        );
+        assert_eq!(
+            verify(&[mv(0, 9, 9), mv(1, 10, 0)]).map(|state| state.turn),
+            illegal(1, MoveError::OffBoard)
+        );
+
+        // every hit keeps the turn, the last one sinks the fleet
+        let mut moves: Vec<_> = stacked()
+            .into_iter()
+            .flatten()
+            .map(|pos| {
+                let (x, y) = pos.coords();
+                mv(0, x, y)
+            })
+            .collect();
+        let state = verify(&moves).unwrap();
+        assert_eq!((state.winner, state.shots), (Some(0), 17));
+        moves.push(mv(1, 0, 0));
+        assert_eq!(
+            verify(&moves).map(|state| state.turn),
+            illegal(17, MoveError::GameOver)
+        );
+    }
+
+    #[test]
+    fn hitgridapply() {
+        let mut board = Board::new(stacked());
+        // misses and hits, but nothing sunk; the board marks every cell of
+        // a sunken ship, the shot only the last one
+        let shots: Vec<_> = [(9, 9), (0, 0), (0, 1), (4, 0), (7, 3)]
+            .into_iter()
+            .map(|(x, y)| {
+                let pos = Position::fromcoords(x, y).unwrap();
+                (pos, board.target(pos).unwrap())
+            })
+            .collect();
+        let mut grid = HitGrid::new();
+        for &(pos, info) in &shots {
+            grid.apply(pos, info).unwrap();
+        }
+        assert_eq!(grid, board.hitgrid());
+
+        for &(pos, info) in &shots {
+            assert_eq!(grid.apply(pos, info), Ok(()));
+        }
+        assert_eq!(grid, board.hitgrid());
+        let miss = Position::fromcoords(9, 9).unwrap();
+        assert_eq!(
+            grid.apply(miss, AttackInfo::Hit(false)),
+            Err(Error::ConflictingAttackInfo)
+        );
+        let hit = Position::fromcoords(0, 0).unwrap();
+        assert_eq!(
+            grid.apply(hit, AttackInfo::Hit(true)),
+            Err(Error::ConflictingAttackInfo)
+        );
+        assert_eq!(grid, board.hitgrid());
+    }
+
+    #[test]
+    fn legaltargetscount() {
+        let mut hits = HitGrid::new();
+        assert_eq!(legaltargets(&hits).len(), 100);
+        let pos = Position::fromcoords(3, 4).unwrap();
+        hits.apply(pos, AttackInfo::Miss).unwrap();
+        let targets = legaltargets(&hits);
+        assert_eq!(targets.len(), 99);
+        assert!(!targets.contains(&pos));
+
+        for y in 0..BOARDSIZE {
+            for x in 0..BOARDSIZE {
+                let pos = Position::fromcoords(x, y).unwrap();
+                hits.apply(pos, AttackInfo::Miss).unwrap();
+            }
+        }
+        assert!(legaltargets(&hits).is_empty());
+    }
+
+    #[test]
+    fn halosclipped() {
+        let halo = |ship: Ship| {
+            let mut cells: Vec<_> = ship.halo().map(Position::coords).collect();
+            cells.sort();
+            cells
+        };
+        // in the corner only the cells on the board remain
+        assert_eq!(halo(ship(0, 0, 2, true)), [(0, 1), (1, 1), (2, 0), (2, 1)]);
+        assert_eq!(halo(ship(9, 8, 2, false)), [(8, 7), (8, 8), (8, 9), (9, 7)]);
+        // in the centre the full ring, ends and sides
+        let centre = halo(ship(4, 4, 3, false));
+        assert_eq!(centre.len(), 2 * 3 + 2 * 3);
+        for (x, y) in [
+            (3, 3),
+            (4, 3),
+            (5, 3),
+            (3, 7),
+            (4, 7),
+            (5, 7),
+            (3, 5),
+            (5, 5),
+        ] {
+            assert!(centre.contains(&(x, y)), "({x}, {y})");
+        }
+        assert!(!centre.contains(&(4, 5)));
+    }
+
+    #[test]
+    fn positionsetmatcheshashset() {
+        use rand::{Rng, SeedableRng};
+        use std::collections::HashSet;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut set = PositionSet::new();
+        let mut reference = HashSet::new();
+        for _ in 0..300 {
+            let pos = Position::fromcoords(
+                rng.random_range(0..BOARDSIZE),
+                rng.random_range(0..BOARDSIZE),
+            )
+            .unwrap();
+            assert_eq!(set.insert(pos), reference.insert(pos));
+            let probe = Position::fromcoords(
+                rng.random_range(0..BOARDSIZE),
+                rng.random_range(0..BOARDSIZE),
+            )
+            .unwrap();
+            assert_eq!(set.contains(probe), reference.contains(&probe));
+        }
+        let mut expected: Vec<_> = reference.into_iter().map(Position::coords).collect();
+        // row by row
+        expected.sort_by_key(|&(x, y)| (y, x));
+        let iterated: Vec<_> = set.iter().map(Position::coords).collect();
+        assert_eq!(iterated, expected);
+        assert_eq!(set.iter().collect::<PositionSet>(), set);
+    }
+
+    #[test]
+    fn attackinfobytes() {
+        for info in [
+            None,
+            Some(AttackInfo::Miss),
+            Some(AttackInfo::Hit(false)),
+            Some(AttackInfo::Hit(true)),
+        ] {
+            let byte = AttackInfo::tobyte(info);
+            assert!(byte < 4);
+            assert_eq!(AttackInfo::frombyte(byte), Ok(info));
+        }
+        for byte in 4..=u8::MAX {
+            assert_eq!(
+                AttackInfo::frombyte(byte),
+                Err(Error::InvalidAttackInfo(byte))
+            );
+        }
+    }
+
+    #[test]
+    fn remainingshipspersink() {
+        let ships = stacked();
+        let mut board = Board::new(ships);
+        assert_eq!(board.remainingships(), 5);
+        for (i, ship) in ships.into_iter().enumerate() {
+            let mut cells = ship.into_iter().peekable();
+            while let Some(pos) = cells.next() {
+                let sunk = cells.peek().is_none();
+                assert_eq!(board.target(pos), Some(AttackInfo::Hit(sunk)));
+                let sunken = i as u8 + sunk as u8;
+                assert_eq!(board.remainingships(), 5 - sunken);
+                assert_eq!(board.sunkenships(), sunken);
+            }
+        }
+        assert!(board.allsunken());
+    }
+
+    #[test]
+    fn notouchrule() {
+        let strict = FleetSpec::default().notouch(true);
+        let check = |ships: [Ship; 5]| {
+            let loose = Ships::validate(&ships, &FleetSpec::default()).map(|_| ());
+            (loose, Ships::validate(&ships, &strict).map(|_| ()))
+        };
+        // every other row, the two threes one cell apart in the same row
+        let apart = [
+            ship(0, 0, 5, true),
+            ship(0, 2, 4, true),
+            ship(0, 4, 3, true),
+            ship(4, 4, 3, true),
+            ship(0, 6, 2, true),
+        ];
+        assert_eq!(check(apart), (Ok(()), Ok(())));
+        assert!(validshipposnotouch(&apart));
+
+        // the same, but a column apart vertically
+        let columns = apart.map(|s| {
+            let (x, y) = s.into_iter().next().unwrap().coords();
+            ship(y, x, s.into_iter().count() as u8, false)
+        });
+        assert_eq!(check(columns), (Ok(()), Ok(())));
+
+        let mut diagonal = apart;
+        diagonal[1] = ship(5, 1, 4, true);
+        assert_eq!(check(diagonal), (Ok(()), Err(Error::ShipsTouching)));
+
+        let mut below = apart;
+        below[1] = ship(0, 1, 4, true);
+        assert_eq!(check(below), (Ok(()), Err(Error::ShipsTouching)));
+
+        let mut beside = apart;
+        beside[3] = ship(3, 4, 3, true);
+        assert_eq!(check(beside), (Ok(()), Err(Error::ShipsTouching)));
+    }
+
+    #[test]
+    fn survivingcellscount() {
+        let ships = stacked();
+        let mut board = Board::new(ships);
+        assert_eq!(survivingcells(ships.asslice(), &board.hitgrid()), 17);
+
+        // a miss leaves every cell standing
+        board.target(Position::fromcoords(9, 9).unwrap());
+        assert_eq!(survivingcells(ships.asslice(), &board.hitgrid()), 17);
+
+        for (i, pos) in ships.into_iter().flatten().enumerate() {
+            board.target(pos);
+            assert_eq!(
+                survivingcells(ships.asslice(), &board.hitgrid()),
+                16 - i as u8
+            );
+        }
+    }
+
+    #[test]
+    fn shipcellscached() {
+        let ships = randomfleet(&mut rand::rng());
+        let board = Board::new(ships);
+        assert_eq!(board.shipcells.len(), ships.asslice().len());
+        for (cells, ship) in board.shipcells.iter().zip(ships) {
+            assert_eq!(*cells, ship.into_iter().collect::<Vec<_>>());
+        }
+    }
 }
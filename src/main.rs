@@ -1,6 +1,9 @@
 use clap::Parser;
-use std::net;
-use ziel::{client::Client, server, tui};
+use std::{io, net, path, process, time};
+use ziel::{
+    client::{self, Client},
+    conn, kiosk, logic, prot, selftest, server, tui,
+};
 
 const DEFAULTADDR: net::SocketAddr =
     net::SocketAddr::new(net::IpAddr::V4(net::Ipv4Addr::new(127, 0, 0, 1)), 8080);
@@ -13,22 +16,351 @@ struct Args {
     #[arg(short, long, default_value_t = DEFAULTADDR)]
     addr: std::net::SocketAddr,
 
+    /// serve or connect on a Unix domain socket at this path instead of --addr
+    #[cfg(unix)]
+    #[arg(long)]
+    uds: Option<path::PathBuf>,
+
     /// act as server [default: client]
     #[arg(long)]
     server: bool,
+
+    /// when serving, keep two bots playing game after game on the server
+    #[arg(long)]
+    kiosk: bool,
+
+    /// games the kiosk plays before the server exits [default: no limit]
+    #[arg(long)]
+    kiosk_games: Option<u32>,
+
+    /// seconds between the end of one kiosk game and the next
+    #[arg(long, default_value_t = kiosk::DEFAULTDELAY.as_secs())]
+    kiosk_delay: u64,
+
+    /// name announced to connecting clients
+    #[arg(long, default_value = "ziel-server")]
+    server_name: String,
+
+    /// seconds a message may take to send before a player counts as disconnected
+    #[arg(long, default_value_t = server::DEFAULTWRITETIMEOUT.as_secs())]
+    write_timeout: u64,
+
+    /// seconds a player may take to answer the server, including picking a target
+    #[arg(long, default_value_t = server::DEFAULTREADTIMEOUT.as_secs())]
+    read_timeout: u64,
+
+    /// seconds a player has to select a target before losing the game
+    #[arg(long, default_value_t = server::DEFAULTTURNTIMEOUT.as_secs())]
+    turn_timeout: u64,
+
+    /// seconds a game waits for a dropped player to reconnect, 0 disables
+    /// reconnecting
+    #[arg(long, default_value_t = server::DEFAULTRECONNECTTIMEOUT.as_secs())]
+    reconnect_timeout: u64,
+
+    /// chat messages a player may send in a row before being throttled
+    #[arg(long, default_value_t = server::DEFAULTCHATBURST)]
+    chat_burst: u32,
+
+    /// milliseconds a throttled player waits for each further chat message,
+    /// 0 leaves chat unlimited
+    #[arg(long, default_value_t = server::DEFAULTCHATINTERVAL.as_millis() as u64)]
+    chat_interval: u64,
+
+    /// seconds a pause both players agreed on may hold the game, 0 declines
+    /// every pause
+    #[arg(long, default_value_t = server::DEFAULTMAXPAUSE.as_secs())]
+    max_pause: u64,
+
+    /// games hosted at the same time before further players are refused
+    #[arg(long, default_value_t = server::DEFAULTMAXGAMES)]
+    max_games: usize,
+
+    /// write a JSON lines transcript of every game into this directory
+    #[arg(long)]
+    transcript_dir: Option<path::PathBuf>,
+
+    /// write a replayable record of every game into this directory
+    #[arg(long)]
+    record_dir: Option<path::PathBuf>,
+
+    /// only be paired with players who name the same queue [default: the
+    /// default queue]
+    #[arg(long)]
+    queue: Option<prot::QueueKey>,
+
+    /// name shown to the opponent, up to 16 printable ASCII characters
+    #[arg(long)]
+    name: Option<prot::PlayerName>,
+
+    /// seconds to wait for the server to complete the handshake, which
+    /// includes waiting for an opponent [default: no limit]
+    #[arg(long)]
+    handshake_timeout: Option<u64>,
+
+    /// layout code to start ship placement from
+    #[arg(long)]
+    layout: Option<String>,
+
+    /// disable board animations
+    #[arg(long)]
+    no_anim: bool,
+
+    /// show own and opponent shots in separate message columns
+    #[arg(long)]
+    split_log: bool,
+
+    /// mark hits and misses with glyphs as well as color
+    #[arg(long)]
+    glyphs: bool,
+
+    /// interval between redraws while waiting for input, in milliseconds
+    #[arg(long, default_value_t = 250)]
+    tick_rate: u64,
+
+    /// fleet to place: classic, mb1967, mb1990, small or mega; announced when
+    /// serving, has to match the server's when playing
+    #[arg(long, default_value_t = logic::FleetPreset::Classic)]
+    fleet: logic::FleetPreset,
+
+    /// ships may not touch, not even diagonally; enforced when serving,
+    /// checked while placing when playing
+    #[arg(long)]
+    no_touch: bool,
+
+    /// every turn fires one shot per own ship afloat
+    #[arg(long)]
+    salvo: bool,
+
+    /// play as a computer opponent without a terminal interface
+    #[arg(long)]
+    bot: bool,
+
+    /// how hard the fleets of --bot and --kiosk are to find: easy places
+    /// ships anywhere, hard never lets them touch
+    #[arg(long, default_value_t = client::Difficulty::Easy)]
+    difficulty: client::Difficulty,
+
+    /// concede the match by declining rematches once this many games are
+    /// lost; the bot accepts rematches until then
+    #[arg(long)]
+    resign_after: Option<u8>,
+
+    /// watch the game the server started last instead of playing
+    #[arg(long)]
+    spectate: bool,
+
+    /// when serving, spectators who give this password see both fleets;
+    /// when spectating, give it to the server
+    #[arg(long)]
+    admin_password: Option<String>,
+
+    /// step through a game record from --record-dir instead of playing
+    #[arg(long)]
+    replay: Option<path::PathBuf>,
+
+    /// seat to replay from, 0 for the player who connected first
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=1))]
+    replay_side: u8,
+
+    /// saturated board colors, easier to make out on a stream
+    #[arg(long)]
+    high_contrast: bool,
+
+    /// height over width of a terminal cell, to keep the boards square
+    #[arg(long, default_value_t = tui::DEFAULTCELLASPECT)]
+    cell_aspect: f32,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// play a scripted game over loopback and verify the outcome
+    Selftest,
+}
+
+/// where to serve or connect, the domain socket if one is given
+fn endpoint(args: &Args) -> conn::Endpoint {
+    #[cfg(unix)]
+    if let Some(path) = &args.uds {
+        return conn::Endpoint::Unix(path.clone());
+    }
+    args.addr.into()
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    let fleet = logic::FleetSpec::from(args.fleet).notouch(args.no_touch);
+    let endpoint = endpoint(&args);
+    if args
+        .admin_password
+        .as_ref()
+        .is_some_and(|password| password.len() > prot::MAXPASSWORDLEN)
+    {
+        eprintln!("admin password longer than {} bytes", prot::MAXPASSWORDLEN);
+        process::exit(1);
+    }
 
-    if args.server {
+    if let Some(Command::Selftest) = args.command {
+        match selftest::run().await {
+            Ok(()) => println!("selftest passed"),
+            Err(err) => {
+                eprintln!("selftest failed; {err}");
+                process::exit(1);
+            }
+        }
+    } else if args.server {
         tracing_subscriber::fmt::init();
-        server::listen(args.addr).await?;
+        let info = match prot::ServerInfo::new(args.server_name) {
+            Ok(info) => info,
+            Err(err) => {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        };
+        let config = server::Config {
+            info,
+            writetimeout: time::Duration::from_secs(args.write_timeout),
+            readtimeout: time::Duration::from_secs(args.read_timeout),
+            turntimeout: time::Duration::from_secs(args.turn_timeout),
+            reconnecttimeout: time::Duration::from_secs(args.reconnect_timeout),
+            chatburst: args.chat_burst,
+            chatinterval: time::Duration::from_millis(args.chat_interval),
+            maxpause: time::Duration::from_secs(args.max_pause),
+            adminpassword: args.admin_password,
+            maxgames: args.max_games,
+            transcriptdir: args.transcript_dir,
+            recorddir: args.record_dir,
+            fleet: fleet.clone(),
+            salvo: args.salvo,
+            metrics: Default::default(),
+        };
+        let server = server::listen(endpoint.clone(), config);
+        let res = if args.kiosk {
+            let kiosk = kiosk::Kiosk::new()
+                .games(args.kiosk_games)
+                .delay(time::Duration::from_secs(args.kiosk_delay))
+                .fleet(fleet)
+                .difficulty(args.difficulty)
+                .run(endpoint);
+            tokio::select! {
+                biased;
+                // polled first, so the server is bound before the bots connect
+                res = server => res,
+                played = kiosk => match played {
+                    Ok(played) => {
+                        tracing::info!("kiosk done after {played} games");
+                        Ok(())
+                    }
+                    Err(err) => Err(io::Error::other(format!("kiosk game failed; {err}"))),
+                },
+            }
+        } else {
+            server.await
+        };
+        if let Err(err) = res {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    } else if args.bot {
+        let mut bot = client::BotUI::new()
+            .rematches(args.resign_after.is_some())
+            .fleet(fleet)
+            .difficulty(args.difficulty);
+        let mut client = Client::join(
+            endpoint,
+            &mut bot,
+            args.handshake_timeout.map(time::Duration::from_secs),
+            args.queue.unwrap_or_default(),
+            args.name,
+        )
+        .await?;
+        if let Some(losses) = args.resign_after {
+            client = client.resignafter(losses);
+        }
+        let victory = client.play(&mut bot).await?;
+        println!("{}", if victory { "victory" } else { "loss" });
+        if args.resign_after.is_some() {
+            let score = client.score();
+            println!("match {}-{}", score.wins, score.losses);
+        }
     } else {
-        let mut interface = tui::Interface::new();
-        let mut client = Client::connect(args.addr, &mut interface).await?;
-        client.play(&mut interface).await?;
+        let mut interface = tui::Interface::new()
+            .animations(!args.no_anim)
+            .tickrate(time::Duration::from_millis(args.tick_rate))
+            .cellaspect(args.cell_aspect)
+            .splitlog(args.split_log)
+            .glyphs(args.glyphs)
+            .fleet(fleet);
+        if args.high_contrast {
+            interface = interface.theme(tui::Theme::highcontrast());
+        }
+        if let Some(code) = args.layout {
+            interface = interface.favourite(code);
+        }
+        if let Some(path) = args.replay {
+            let replay = prot::GameRecord::load(&path)
+                .await
+                .map_err(|err| format!("{}: {err}", path.display()))
+                .and_then(|record| {
+                    client::Replay::new(&record, args.replay_side)
+                        .map_err(|err| format!("{}: {err}", path.display()))
+                });
+            let replay = match replay {
+                Ok(replay) => replay,
+                Err(err) => {
+                    drop(interface);
+                    eprintln!("{err}");
+                    process::exit(1);
+                }
+            };
+            return match interface.playback(&replay) {
+                Err(err) if err.kind() != io::ErrorKind::Interrupted => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+        let handshaketimeout = args.handshake_timeout.map(time::Duration::from_secs);
+        // outlives the game, to concede with once the player quits
+        let mut joined = None;
+        let game = async {
+            if args.spectate {
+                let client = joined.insert(match args.admin_password {
+                    Some(password) => Client::oversee(endpoint, handshaketimeout, password).await?,
+                    None => Client::spectate(endpoint, handshaketimeout).await?,
+                });
+                return client.watch(&mut interface).await.map(|()| false);
+            }
+            let queue = args.queue.unwrap_or_default();
+            let mut client =
+                Client::join(endpoint, &mut interface, handshaketimeout, queue, args.name).await?;
+            if let Some(losses) = args.resign_after {
+                client = client.resignafter(losses);
+            }
+            joined.insert(client).play(&mut interface).await
+        };
+        // Ctrl-C arrives as SIGINT before raw mode and as a key press in it;
+        // either way the game is conceded, dropping the client closes the
+        // connection and dropping the interface restores the terminal
+        let res = tokio::select! {
+            res = game => res,
+            _ = tokio::signal::ctrl_c() => Err(client::Error::Interface(
+                io::Error::from(io::ErrorKind::Interrupted).into(),
+            )),
+        };
+        match res {
+            Ok(_) => {}
+            Err(client::Error::Interface(err))
+                if err.inner().kind() == io::ErrorKind::Interrupted =>
+            {
+                if let Some(client) = &mut joined {
+                    client.resign().await;
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
     }
     Ok(())
 }
@@ -1,7 +1,7 @@
-use std::array;
+use std::{array, fmt};
 use tokio::{
-    io::{self, AsyncReadExt, AsyncWriteExt},
-    net,
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time,
 };
 
 use crate::logic;
@@ -18,12 +18,326 @@ pub enum Error {
     Networking(#[from] io::Error),
     #[error("unsuccessful handshake")]
     UnsuccessfulHandshake,
+    #[error("unsupported by server; {0:?}")]
+    Unsupported(Capabilities),
+    #[error("invalid server name; expected 1 to {MAXSERVERNAMELEN} bytes, got {0}")]
+    InvalidServerName(usize),
+    #[error("invalid queue name; expected up to {MAXQUEUENAMELEN} printable ASCII characters, got {0:?}")]
+    InvalidQueueName(String),
+    #[error("invalid player name; expected 1 to {MAXPLAYERNAMELEN} printable ASCII characters, got {0:?}")]
+    InvalidPlayerName(String),
+    #[error("invalid fleet; {0}")]
+    InvalidFleet(logic::Error),
+    #[error("invalid layout code")]
+    InvalidCode,
+    #[error("invalid game record")]
+    InvalidRecord,
+    #[error("refused by server; no free slot")]
+    Refused,
+    #[error("timed out waiting for peer")]
+    Timeout,
+    #[error("protocol version mismatch; ours: {ours}, theirs: {theirs}")]
+    VersionMismatch { ours: u8, theirs: u8 },
+    #[error("unknown typemarker; {0}")]
+    UnknownType(u8),
+    #[error(
+        "checksum mismatch; typemarker: {typemarker}, expected: {expected:#010x}, got: {got:#010x}"
+    )]
+    Checksum {
+        typemarker: u8,
+        expected: u32,
+        got: u32,
+    },
+}
+
+/// sent with both handshakes, bump on every incompatible wire change
+pub const PROTOCOLVERSION: u8 = 8;
+
+const MAXSERVERNAMELEN: usize = 32;
+const MAXQUEUENAMELEN: usize = 16;
+const MAXPLAYERNAMELEN: usize = 16;
+
+/// name and version a server announces when acknowledging the handshake
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerInfo {
+    name: String,
+    version: String,
+    /// ship lengths every player places, shortest first
+    fleet: Vec<u8>,
+}
+
+impl Default for ServerInfo {
+    fn default() -> ServerInfo {
+        ServerInfo {
+            name: String::from("ziel-server"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            fleet: logic::SHIPLENGTHS.to_vec(),
+        }
+    }
+}
+
+impl ServerInfo {
+    /// identity of this build under the given name
+    pub fn new(name: impl Into<String>) -> Result<ServerInfo, Error> {
+        let name = name.into();
+        if name.is_empty() || name.len() > MAXSERVERNAMELEN {
+            return Err(Error::InvalidServerName(name.len()));
+        }
+        Ok(ServerInfo {
+            name,
+            ..ServerInfo::default()
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// ship lengths of the fleet the server plays with, shortest first
+    pub fn fleet(&self) -> &[u8] {
+        &self.fleet
+    }
+
+    /// announces `fleet` instead of the classic one
+    pub fn withfleet(mut self, fleet: &logic::FleetSpec) -> ServerInfo {
+        self.fleet = fleet.lengths().to_vec();
+        self
+    }
+
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        for field in [&self.name, &self.version] {
+            buffer.push(field.len() as u8);
+            buffer.extend_from_slice(field.as_bytes());
+        }
+        buffer.extend_from_slice(&self.fleet);
+    }
+
+    fn decode(body: &[u8]) -> Option<ServerInfo> {
+        let (&namelen, body) = body.split_first()?;
+        let (name, body) = body.split_at_checked(namelen as usize)?;
+        let (&versionlen, body) = body.split_first()?;
+        let (version, body) = body.split_at_checked(versionlen as usize)?;
+        let fleet = logic::FleetSpec::new(body.iter().copied()).ok()?;
+        if name.is_empty() || name.len() > MAXSERVERNAMELEN {
+            return None;
+        }
+        Some(ServerInfo {
+            name: String::from_utf8(name.to_owned()).ok()?,
+            version: String::from_utf8(version.to_owned()).ok()?,
+            fleet: fleet.lengths().to_vec(),
+        })
+    }
+}
+
+/// queue a player asks to be paired in, players in different queues never
+/// play each other; empty for the default queue
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QueueKey(String);
+
+impl QueueKey {
+    /// the queue called `name`, the default one if `name` is empty
+    pub fn new(name: impl Into<String>) -> Result<QueueKey, Error> {
+        let name = name.into();
+        if name.len() > MAXQUEUENAMELEN || !name.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(Error::InvalidQueueName(name));
+        }
+        Ok(QueueKey(name))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for QueueKey {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<QueueKey, Error> {
+        QueueKey::new(name)
+    }
+}
+
+impl fmt::Display for QueueKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_str() {
+            "" => f.write_str("default"),
+            name => f.write_str(name),
+        }
+    }
+}
+
+/// name a player goes by, passed on to their opponent; spaces are allowed
+/// between the printable characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerName(String);
+
+impl PlayerName {
+    pub fn new(name: impl Into<String>) -> Result<PlayerName, Error> {
+        let name = name.into();
+        if name.is_empty()
+            || name.len() > MAXPLAYERNAMELEN
+            || name.trim() != name
+            || !name.bytes().all(|b| b == b' ' || b.is_ascii_graphic())
+        {
+            return Err(Error::InvalidPlayerName(name));
+        }
+        Ok(PlayerName(name))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn decode(name: &[u8]) -> Option<PlayerName> {
+        PlayerName::new(String::from_utf8(name.to_owned()).ok()?).ok()
+    }
+}
+
+impl std::str::FromStr for PlayerName {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<PlayerName, Error> {
+        PlayerName::new(name)
+    }
+}
+
+impl fmt::Display for PlayerName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// why a player left before the game was decided on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaveReason {
+    Resigned,
+    Timeout,
+    Disconnected,
+    /// left before the first shot was fired
+    Abandoned,
+}
+
+impl LeaveReason {
+    fn byte(self) -> u8 {
+        match self {
+            LeaveReason::Resigned => 0,
+            LeaveReason::Timeout => 1,
+            LeaveReason::Disconnected => 2,
+            LeaveReason::Abandoned => 3,
+        }
+    }
+
+    fn frombyte(byte: u8) -> Option<LeaveReason> {
+        match byte {
+            0 => Some(LeaveReason::Resigned),
+            1 => Some(LeaveReason::Timeout),
+            2 => Some(LeaveReason::Disconnected),
+            3 => Some(LeaveReason::Abandoned),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LeaveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LeaveReason::Resigned => "resigned",
+            LeaveReason::Timeout => "timed out",
+            LeaveReason::Disconnected => "disconnected",
+            LeaveReason::Abandoned => "abandoned the game",
+        })
+    }
+}
+
+/// where a pause both players have to agree on stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseNews {
+    /// the opponent asks for a pause
+    Offered,
+    /// the pause asked for was declined
+    Declined,
+    /// both agreed, the game holds for at most this many seconds unless
+    /// either player resumes it earlier
+    Held(u16),
+    /// the game goes on
+    Resumed,
+}
+
+impl PauseNews {
+    fn encode(self) -> Vec<u8> {
+        match self {
+            PauseNews::Offered => vec![0],
+            PauseNews::Declined => vec![1],
+            PauseNews::Held(secs) => {
+                let [low, high] = secs.to_le_bytes();
+                vec![2, low, high]
+            }
+            PauseNews::Resumed => vec![3],
+        }
+    }
+
+    fn decode(body: &[u8]) -> Option<PauseNews> {
+        match body {
+            [0] => Some(PauseNews::Offered),
+            [1] => Some(PauseNews::Declined),
+            [2, low, high] => Some(PauseNews::Held(u16::from_le_bytes([*low, *high]))),
+            [3] => Some(PauseNews::Resumed),
+            _ => None,
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// optional features a server may offer beyond the base game
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Capabilities: u8 {
+        const TLS = 1 << 0;
+        const SALVO = 1 << 1;
+        const CHAT = 1 << 2;
+        const FASTMODE = 1 << 3;
+        const CUSTOMFLEETS = 1 << 4;
+        /// ship positions may be sent as one byte per ship plus orientations
+        const COMPACTFLEET = 1 << 5;
+        /// players may hold the game, turn clock and all, once both agree
+        const PAUSE = 1 << 6;
+    }
+}
+
+impl Capabilities {
+    /// rules a server imposes on its players once advertised, a client has
+    /// to support every one of them
+    pub const MODES: Capabilities = Capabilities::TLS
+        .union(Capabilities::SALVO)
+        .union(Capabilities::FASTMODE)
+        .union(Capabilities::CUSTOMFLEETS);
+
+    /// capabilities of this build
+    pub fn supported() -> Capabilities {
+        Capabilities::COMPACTFLEET
+            | Capabilities::CHAT
+            | Capabilities::SALVO
+            | Capabilities::PAUSE
+            | Capabilities::CUSTOMFLEETS
+    }
+
+    /// fails naming the required capabilities that are missing
+    pub fn require(self, required: Capabilities) -> Result<(), Error> {
+        if self.contains(required) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported(required.difference(self)))
+        }
+    }
 }
 
 impl From<RawMessage> for Error {
     fn from(value: RawMessage) -> Error {
         Error::Message {
-            typemarker: value.typemarker,
+            typemarker: value.typemarker as u8,
             sizemarker: value.body.len() as u32,
             body: value.body,
         }
@@ -32,17 +346,17 @@ impl From<RawMessage> for Error {
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct RawMessageRef<'b> {
-    pub typemarker: u8,
+    pub typemarker: MessageType,
     pub body: &'b [u8],
 }
 
 pub struct RawMessage {
-    pub typemarker: u8,
+    pub typemarker: MessageType,
     pub body: Vec<u8>,
 }
 
 impl RawMessage {
-    pub fn as_ref(&self) -> RawMessageRef {
+    pub fn as_ref(&self) -> RawMessageRef<'_> {
         RawMessageRef {
             typemarker: self.typemarker,
             body: &self.body,
@@ -61,30 +375,89 @@ impl<'b> RawMessageRef<'b> {
 
 #[derive(Debug)]
 pub enum ClientMessage {
-    Handshake,
+    /// protocol version of the client, builds before versioning count as 0,
+    /// the queue it is paired in and the name it goes by, if any
+    Handshake(u8, QueueKey, Option<PlayerName>),
+    /// a handshake from a client that only watches the game started last,
+    /// with its protocol version and, to be shown both fleets, the admin
+    /// password
+    Spectate(u8, Option<String>),
+    /// a handshake taking back the seat of a dropped connection, with the
+    /// protocol version and the token from the original handshake
+    Reconnect(u8, u64),
+    QueryCapabilities,
+    /// answers a request with a plea for the full public state instead
+    RequestResync,
 
     Acknowledge,
 
     ShipPositions(logic::Ships),
+    /// the same fleet, encoded with lengths implied by `logic::SHIPLENGTHS`
+    CompactShipPositions(logic::Ships),
     Target(logic::Position),
+    /// distinct targets fired at once, one per ship still afloat
+    Salvo(Vec<logic::Position>),
+    /// answers a target or salvo request by conceding the game
+    Surrender,
+    /// answers a rematch offer
+    Rematch(bool),
+    /// text for the opponent, sent without being asked and never answered
+    Chat(String),
+    /// `true` asks for a pause or accepts the opponent's, `false` declines
+    /// it or ends the pause holding; sent without being asked and never
+    /// answered
+    Pause(bool),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ServerMessage {
-    Handshake,
+    /// with the token a dropped player reconnects with, 0 when the server
+    /// does not take players back, and the opponent's name if they gave one
+    Handshake(ServerInfo, u64, Option<PlayerName>),
+    /// sent instead of the handshake when the client speaks another
+    /// protocol version, carrying the server's
+    VersionMismatch(u8),
+    Capabilities(Capabilities),
+    Snapshot(logic::BoardSnapshot),
+    /// sent instead of the handshake when the connection cannot be placed
+    Refused,
 
     Invalid,
+    /// the ship positions were refused for the given reason
+    InvalidShips(logic::Error),
 
     RequestShipPositions,
-    RequestTarget,
+    /// seconds the server waits for the answer, if it runs a turn clock
+    RequestTarget(Option<u16>),
+    /// asks for this many distinct targets at once, answered within the
+    /// given seconds
+    RequestSalvo(u8, u16),
 
     InformTargetSelection,
-    InformTargetHitYou(logic::Position, bool),
+    /// the length of the ship, if the hit sank it
+    InformTargetHitYou(logic::Position, Option<u8>),
     InformTargetMissYou(logic::Position),
-    InformTargetHitOpp(logic::Position, bool),
+    InformTargetHitOpp(logic::Position, Option<u8>),
     InformTargetMissOpp(logic::Position),
     InformVictory,
     InformLoss,
+    GameSummary(logic::GameSummary),
+    /// the opponent is gone, sent right before the victory it hands over
+    OpponentLeft(LeaveReason),
+    /// the opponent dropped and the game waits this many seconds for them,
+    /// 0 once they are back; may arrive at any time and is never answered
+    OpponentAway(u16),
+    /// both fleets, the first player's first; only ever sent to a spectator
+    /// who gave the admin password, after each snapshot of a new game
+    Fleets([logic::Ships; 2]),
+    /// sent after the summary of a decided game; once both players accept,
+    /// the next game starts with a ship request instead of the termination
+    OfferRematch,
+    /// text from the opponent, may arrive at any time and is never answered
+    Chat(String),
+    /// a pause was asked for, agreed on or ended; may arrive at any time and
+    /// is never answered
+    Pause(PauseNews),
 
     TerminateConnection,
 }
@@ -92,125 +465,455 @@ pub enum ServerMessage {
 // STREAM HANDLING  000..100
 // LOGIC  HANDLING  100..150
 // LOGIC  INFORMING 150..200
+// PLAYER TALKING   200..250
+
+// frames are typemarker, little endian u32 body size, body and, from
+// version 3 on, a little endian CRC-32 over all of the former; handshake and
+// refusal frames go without, so that any version can still tell the other
+// side it does not match
 
 // FRM       SERVER | CLIENT
 // 001 HANDSHAKE    | HANDSHAKE
+// 001 VERSION MIS. | SPECTATE
+// 001              | RECONNECT
 // 002              | ACKNOWLEDGMENT
 // 003 INVALID      |
 // 004 TERMINATE    |
+// 005 CAPABILITIES | QUERY CAPAB.
+// 006 SNAPSHOT     | REQ. RESYNC
+// 007 REFUSED      |
 // -----------------|----------------
 // 100 REQ. SHIPS   | RET. SHIPS
 // 101 REQ. TARGET  | RET. TARGET
+// 101              | SURRENDER
+// 102 OFFER REMATCH| RET. REMATCH
 // -----------------|----------------
 // 150 TARG. SELEC. |
-// 151 TARG. MISS   |
-// 152 TARG. HIT    |
+// 151 TARG. HIT    |
+// 152 TARG. MISS   |
 // 153 VICTORY      |
 // 154 LOSS         |
+// 155 SUMMARY      |
+// 156 OPP. LEFT    |
+// 157 OPP. AWAY    |
+// 158 FLEETS       |
+// -----------------|----------------
+// 200 CHAT         | CHAT
+// 201 PAUSE NEWS   | PAUSE
 
+/// declares `MessageType` and derives its decoding from the same list, so a
+/// marker added to the enum cannot be missed by `TryFrom<u8>`
+macro_rules! messagetypes {
+    ($(#[$meta:meta])* pub enum MessageType {
+        $($(#[$variantmeta:meta])* $variant:ident = $marker:literal,)*
+    }) => {
+        $(#[$meta])*
+        pub enum MessageType {
+            $($(#[$variantmeta])* $variant = $marker,)*
+        }
+
+        impl MessageType {
+            /// every type, in marker order
+            pub const ALL: &[MessageType] = &[$(MessageType::$variant,)*];
+        }
+
+        impl TryFrom<u8> for MessageType {
+            type Error = Error;
+
+            fn try_from(typemarker: u8) -> Result<Self, Self::Error> {
+                match typemarker {
+                    $($marker => Ok(MessageType::$variant),)*
+                    _ => Err(Error::UnknownType(typemarker)),
+                }
+            }
+        }
+    };
+}
+
+messagetypes! {
+    /// every typemarker on the wire, see the table above; markers are the
+    /// discriminants, so two messages can never end up sharing one by accident
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum MessageType {
+        Handshake = 1,
+        Acknowledgment = 2,
+        Invalid = 3,
+        TerminateConnection = 4,
+        Capabilities = 5,
+        Snapshot = 6,
+        Refused = 7,
+
+        ShipPositions = 100,
+        Target = 101,
+        Rematch = 102,
+
+        TargetSelection = 150,
+        /// body: 0 for the own, 1 for the opponent board, the position, then the
+        /// length of the ship the hit sank or 0
+        TargetHit = 151,
+        TargetMiss = 152,
+        Victory = 153,
+        Loss = 154,
+        GameSummary = 155,
+        OpponentLeft = 156,
+        /// body: little endian u16 seconds
+        OpponentAway = 157,
+        /// body: both fleets as in a ship positions answer with lengths,
+        /// the first player's first
+        Fleets = 158,
+
+        /// body is the UTF-8 text alone, forwarded between the players as is
+        Chat = 200,
+        /// body: the news from the server, see `PauseNews`
+        Pause = 201,
+    }
+}
+
+/// followed by the protocol version and the queue name, nothing for the
+/// default queue; a zero byte after the queue name starts the player name.
+/// the answer carries the token and the opponent's name, prefixed with its
+/// length, ahead of the server info
 const HANDSHAKE: RawMessageRef = RawMessageRef {
-    typemarker: 1,
+    typemarker: MessageType::Handshake,
     body: b"HELO",
 };
+/// followed by the protocol version and the admin password, if any
+const SPECTATE: &[u8] = b"WTCH";
+/// ship positions with their lengths, three bytes per ship; a fleet of
+/// fewer than `logic::MAXSHIPS` leaves the slots after its last ship zero
+const FLEETLEN: usize = 3 * logic::MAXSHIPS;
+/// followed by the protocol version and the little endian token
+const RECONNECT: &[u8] = b"RCON";
+const VERSIONMISMATCH: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Handshake,
+    body: b"VERS",
+};
 const ACKNOWLEDGMENT: RawMessageRef = RawMessageRef {
-    typemarker: 2,
+    typemarker: MessageType::Acknowledgment,
     body: b"ACK",
 };
 const INVALID: RawMessageRef = RawMessageRef {
-    typemarker: 3,
+    typemarker: MessageType::Invalid,
     body: b"INVALID",
 };
+const INVALIDSHIPOVERLAP: u8 = 1;
+const INVALIDSHIPLENGTHS: u8 = 2;
+const INVALIDOUTOFBOUNDS: u8 = 3;
+const INVALIDSHIPSTOUCHING: u8 = 4;
 const TERMINATECONNECTION: RawMessageRef = RawMessageRef {
-    typemarker: 4,
+    typemarker: MessageType::TerminateConnection,
     body: b"TERM",
 };
+const QUERYCAPABILITIES: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Capabilities,
+    body: b"CAPS",
+};
+
+const REQUESTRESYNC: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Snapshot,
+    body: b"RESYNC",
+};
+
+const REFUSED: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Refused,
+    body: b"REFUSED",
+};
+
+/// bytes of a single chat message
+pub const MAXCHATLEN: usize = 60;
+/// bytes of the admin password, it travels in the spectator handshake
+pub const MAXPASSWORDLEN: usize = MAXBODYLEN - SPECTATE.len() - 1;
 
-const SHIPPOSITIONS: u8 = 100;
 const REQUESTSHIPPOSITIONS: RawMessageRef = RawMessageRef {
-    typemarker: SHIPPOSITIONS,
+    typemarker: MessageType::ShipPositions,
     body: b"REQ SHIPP",
 };
-const TARGET: u8 = 101;
 const REQUESTTARGET: RawMessageRef = RawMessageRef {
-    typemarker: TARGET,
+    typemarker: MessageType::Target,
     body: b"TARG",
 };
+const SURRENDER: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Target,
+    body: b"SURRENDER",
+};
+const OFFERREMATCH: RawMessageRef = RawMessageRef {
+    typemarker: MessageType::Rematch,
+    body: b"REMATCH?",
+};
+/// prefixes the answer to a rematch offer, followed by 1 to accept or 0
+const REMATCHANSWER: &[u8] = b"REMATCH";
+/// prefixes a player's pause request, followed by 1 to ask or accept and 0
+/// to decline or resume
+const PAUSEANSWER: &[u8] = b"PAUSE";
+/// prefixes the salvo request and answer, followed by the shot count and
+/// seconds or by the targets
+const SALVO: &[u8] = b"SALV";
 
 const INFORMTARGETSELECTION: RawMessageRef = RawMessageRef {
-    typemarker: 150,
+    typemarker: MessageType::TargetSelection,
     body: b"INFO TARG",
 };
-const INFORMTARGETHIT: u8 = 151;
-const INFORMTARGETMISS: u8 = 152;
 const INFORMVICTORY: RawMessageRef = RawMessageRef {
-    typemarker: 153,
+    typemarker: MessageType::Victory,
     body: b"VICTORY",
 };
 const INFORMLOSS: RawMessageRef = RawMessageRef {
-    typemarker: 154,
+    typemarker: MessageType::Loss,
     body: b"LOSS",
 };
+const GAMESUMMARYVERSION: u8 = 1;
+
+/// bytes of a packed grid, four cells per byte
+const PACKEDGRIDLEN: usize = (logic::BOARDSIZE as usize).pow(2).div_ceil(4);
+
+/// cells row by row as `AttackInfo::tobyte`, four to a byte, first cell in
+/// the low bits
+fn encodegrid(grid: &logic::HitGrid, buffer: &mut Vec<u8>) {
+    let cells: Vec<_> = (0..logic::BOARDSIZE as usize)
+        .flat_map(|y| grid[y].iter().copied())
+        .map(logic::AttackInfo::tobyte)
+        .collect();
+    buffer.extend(cells.chunks(4).map(|chunk| {
+        chunk
+            .iter()
+            .enumerate()
+            .fold(0, |byte, (i, cell)| byte | cell << (i * 2))
+    }));
+}
+
+fn decodegrid(packed: &[u8]) -> Option<logic::HitGrid> {
+    let mut grid = logic::HitGrid::new();
+    let size = logic::BOARDSIZE as usize;
+    for i in 0..size * size {
+        let byte = (packed[i / 4] >> (i % 4 * 2)) & 0b11;
+        if let Some(info) = logic::AttackInfo::frombyte(byte).ok()? {
+            let pos = logic::Position::fromcoords((i % size) as u8, (i / size) as u8)?;
+            grid.apply(pos, info).ok()?;
+        }
+    }
+    Some(grid)
+}
+
+/// names the last cell of a ship that does not fit on the board
+fn shipend(shipplan: logic::ShipPlan) -> logic::Error {
+    let (pos, dx, dy) = match shipplan {
+        logic::ShipPlan::Horizontal { pos, len } => (pos, len.saturating_sub(1), 0),
+        logic::ShipPlan::Vertical { pos, len } => (pos, 0, len.saturating_sub(1)),
+    };
+    let (x, y) = pos.coords();
+    logic::Error::OutOfBounds {
+        x: x.saturating_add(dx),
+        y: y.saturating_add(dy),
+    }
+}
 
 impl TryFrom<RawMessage> for ClientMessage {
     type Error = Error;
 
     fn try_from(message: RawMessage) -> Result<Self, Self::Error> {
         match message.as_ref() {
-            HANDSHAKE => Ok(ClientMessage::Handshake),
+            HANDSHAKE => Ok(ClientMessage::Handshake(0, QueueKey::default(), None)),
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'H', b'E', b'L', b'O', version, rest @ ..],
+            } => {
+                let (queue, name) = match rest.iter().position(|&b| b == 0) {
+                    Some(end) => (&rest[..end], Some(PlayerName::decode(&rest[end + 1..]))),
+                    None => (rest, None),
+                };
+                let queue = String::from_utf8(queue.to_owned())
+                    .ok()
+                    .and_then(|name| QueueKey::new(name).ok());
+                match (queue, name) {
+                    (Some(queue), None) => Ok(ClientMessage::Handshake(*version, queue, None)),
+                    (Some(queue), Some(Some(name))) => {
+                        Ok(ClientMessage::Handshake(*version, queue, Some(name)))
+                    }
+                    _ => Err(Error::from(message)),
+                }
+            }
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'W', b'T', b'C', b'H', version, password @ ..],
+            } => Ok(ClientMessage::Spectate(
+                *version,
+                match password {
+                    [] => None,
+                    password => Some(
+                        String::from_utf8(password.to_owned()).map_err(|_| Error::from(message))?,
+                    ),
+                },
+            )),
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'R', b'C', b'O', b'N', version, token @ ..],
+            } if token.len() == 8 => Ok(ClientMessage::Reconnect(
+                *version,
+                u64::from_le_bytes(array::from_fn(|i| token[i])),
+            )),
+            QUERYCAPABILITIES => Ok(ClientMessage::QueryCapabilities),
+            REQUESTRESYNC => Ok(ClientMessage::RequestResync),
             ACKNOWLEDGMENT => Ok(ClientMessage::Acknowledge),
+            SURRENDER => Ok(ClientMessage::Surrender),
+            RawMessageRef {
+                typemarker: MessageType::Rematch,
+                body: [b'R', b'E', b'M', b'A', b'T', b'C', b'H', answer @ (0 | 1)],
+            } => Ok(ClientMessage::Rematch(*answer == 1)),
             RawMessageRef {
-                typemarker: SHIPPOSITIONS,
+                typemarker: MessageType::ShipPositions,
                 body,
-            } if body.len() == 15 => {
-                let mut error = false;
-                let positions = array::from_fn(|i| {
-                    let horizontal = body[i * 3] != 0;
-                    let pos = logic::Position::frombyte(body[i * 3 + 1]).unwrap_or_else(|| {
-                        error = true;
-                        logic::Position::default()
-                    });
-                    let len = body[i * 3 + 2];
+            } if body.len() == FLEETLEN => {
+                let slots = body.chunks_exact(3);
+                let count = slots.clone().take_while(|slot| *slot != [0; 3]).count();
+                if count == 0 || body[count * 3..].iter().any(|&byte| byte != 0) {
+                    return Err(Error::from(message));
+                }
 
-                    let shipplan = if horizontal {
+                // plain plans first, a ship only exists once it fits the board
+                let mut plans = Vec::with_capacity(count);
+                for slot in slots.take(count) {
+                    let Some(pos) = logic::Position::frombyte(slot[1]) else {
+                        return Err(Error::from(message));
+                    };
+                    let len = slot[2];
+                    plans.push(if slot[0] != 0 {
                         logic::ShipPlan::Horizontal { pos, len }
                     } else {
                         logic::ShipPlan::Vertical { pos, len }
-                    };
-                    logic::Ship::try_from(shipplan).unwrap_or_else(|_| {
-                        error = true;
-                        unsafe {
-                            // SAFE: error gets caught below
-                            std::mem::transmute(shipplan)
-                        }
-                    })
-                });
+                    });
+                }
 
-                if error {
-                    return Err(Error::from(message));
+                let mut ships = Vec::with_capacity(plans.len());
+                for plan in plans {
+                    match logic::Ship::try_from(plan) {
+                        Ok(ship) => ships.push(ship),
+                        Err(()) => return Err(Error::InvalidFleet(shipend(plan))),
+                    }
                 }
 
+                // the lengths are up to the fleet the server plays with
                 Ok(ClientMessage::ShipPositions(
-                    logic::Ships::try_from(positions).map_err(|_| Error::from(message))?,
+                    logic::Ships::placed(&ships).map_err(Error::InvalidFleet)?,
+                ))
+            }
+            RawMessageRef {
+                typemarker: MessageType::ShipPositions,
+                body: [positions @ .., orientations],
+            } if positions.len() == logic::SHIPLENGTHS.len() => {
+                if orientations >> logic::SHIPLENGTHS.len() != 0 {
+                    return Err(Error::from(message));
+                }
+                let mut ships = Vec::with_capacity(logic::SHIPLENGTHS.len());
+                for (i, (position, len)) in
+                    Iterator::zip(positions.iter(), logic::SHIPLENGTHS).enumerate()
+                {
+                    let Some(pos) = logic::Position::frombyte(*position) else {
+                        return Err(Error::from(message));
+                    };
+                    let shipplan = if orientations & (1 << i) != 0 {
+                        logic::ShipPlan::Horizontal { pos, len }
+                    } else {
+                        logic::ShipPlan::Vertical { pos, len }
+                    };
+                    match logic::Ship::try_from(shipplan) {
+                        Ok(ship) => ships.push(ship),
+                        Err(()) => return Err(Error::InvalidFleet(shipend(shipplan))),
+                    }
+                }
+
+                Ok(ClientMessage::CompactShipPositions(
+                    logic::Ships::validate(&ships, &logic::FleetSpec::default())
+                        .map_err(Error::InvalidFleet)?,
                 ))
             }
             RawMessageRef {
-                typemarker: TARGET,
+                typemarker: MessageType::Target,
                 body: [position],
             } => Ok(ClientMessage::Target(
                 logic::Position::frombyte(*position).ok_or(Error::from(message))?,
             )),
+            RawMessageRef {
+                typemarker: MessageType::Target,
+                body: [b'S', b'A', b'L', b'V', positions @ ..],
+            } if (1..=logic::MAXSHIPS).contains(&positions.len()) => Ok(ClientMessage::Salvo(
+                positions
+                    .iter()
+                    .map(|&pos| logic::Position::frombyte(pos))
+                    .collect::<Option<_>>()
+                    .ok_or(Error::from(message))?,
+            )),
+            RawMessageRef {
+                typemarker: MessageType::Chat,
+                body,
+            } => Ok(ClientMessage::Chat(
+                decodechat(body).ok_or(Error::from(message))?,
+            )),
+            RawMessageRef {
+                typemarker: MessageType::Pause,
+                body: [b'P', b'A', b'U', b'S', b'E', answer @ (0 | 1)],
+            } => Ok(ClientMessage::Pause(*answer == 1)),
             _ => Err(Error::from(message)),
         }
     }
 }
 
+/// `None` unless 1 to `MAXCHATLEN` bytes of valid UTF-8
+fn decodechat(body: &[u8]) -> Option<String> {
+    if body.is_empty() || body.len() > MAXCHATLEN {
+        return None;
+    }
+    std::str::from_utf8(body).ok().map(String::from)
+}
+
+/// cuts `text` down to at most `MAXCHATLEN` bytes on a character boundary
+pub fn clampchat(text: &str) -> &str {
+    let mut end = text.len().min(MAXCHATLEN);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
 impl From<ClientMessage> for RawMessage {
     fn from(message: ClientMessage) -> RawMessage {
         match message {
-            ClientMessage::Handshake => HANDSHAKE.to_owned(),
+            ClientMessage::Handshake(version, queue, name) => {
+                let mut body = HANDSHAKE.body.to_owned();
+                body.push(version);
+                body.extend_from_slice(queue.name().as_bytes());
+                if let Some(name) = name {
+                    body.push(0);
+                    body.extend_from_slice(name.name().as_bytes());
+                }
+                RawMessage {
+                    typemarker: MessageType::Handshake,
+                    body,
+                }
+            }
+            ClientMessage::Spectate(version, password) => {
+                let mut body = SPECTATE.to_owned();
+                body.push(version);
+                body.extend_from_slice(password.unwrap_or_default().as_bytes());
+                RawMessage {
+                    typemarker: MessageType::Handshake,
+                    body,
+                }
+            }
+            ClientMessage::Reconnect(version, token) => {
+                let mut body = RECONNECT.to_owned();
+                body.push(version);
+                body.extend_from_slice(&token.to_le_bytes());
+                RawMessage {
+                    typemarker: MessageType::Handshake,
+                    body,
+                }
+            }
+            ClientMessage::QueryCapabilities => QUERYCAPABILITIES.to_owned(),
+            ClientMessage::RequestResync => REQUESTRESYNC.to_owned(),
             ClientMessage::Acknowledge => ACKNOWLEDGMENT.to_owned(),
             ClientMessage::ShipPositions(ships) => {
-                let mut buffer = vec![0; 15];
+                let mut buffer = vec![0; FLEETLEN];
                 for (i, ship) in ships.into_iter().enumerate() {
                     match ship.into() {
                         logic::ShipPlan::Horizontal { pos, len } => {
@@ -226,14 +929,60 @@ impl From<ClientMessage> for RawMessage {
                     }
                 }
                 RawMessage {
-                    typemarker: SHIPPOSITIONS,
+                    typemarker: MessageType::ShipPositions,
+                    body: buffer,
+                }
+            }
+            ClientMessage::CompactShipPositions(ships) => {
+                let mut ships = ships.asslice().to_vec();
+                ships.sort_by_key(logic::Ship::length);
+                let mut buffer = vec![0; ships.len() + 1];
+                for (i, ship) in ships.iter().enumerate() {
+                    let (horizontal, pos) = match logic::ShipPlan::from(ship) {
+                        logic::ShipPlan::Horizontal { pos, .. } => (true, pos),
+                        logic::ShipPlan::Vertical { pos, .. } => (false, pos),
+                    };
+                    buffer[i] = pos.byte();
+                    buffer[ships.len()] |= (horizontal as u8) << i;
+                }
+                RawMessage {
+                    typemarker: MessageType::ShipPositions,
                     body: buffer,
                 }
             }
             ClientMessage::Target(pos) => RawMessage {
-                typemarker: TARGET,
+                typemarker: MessageType::Target,
                 body: vec![pos.byte()],
             },
+            ClientMessage::Salvo(positions) => RawMessage {
+                typemarker: MessageType::Target,
+                body: SALVO
+                    .iter()
+                    .copied()
+                    .chain(positions.iter().map(|pos| pos.byte()))
+                    .collect(),
+            },
+            ClientMessage::Surrender => SURRENDER.to_owned(),
+            ClientMessage::Rematch(accepted) => {
+                let mut body = REMATCHANSWER.to_owned();
+                body.push(accepted as u8);
+                RawMessage {
+                    typemarker: MessageType::Rematch,
+                    body,
+                }
+            }
+            ClientMessage::Chat(text) => RawMessage {
+                typemarker: MessageType::Chat,
+                body: text.into_bytes(),
+            },
+            ClientMessage::Pause(asked) => {
+                let mut body = PAUSEANSWER.to_owned();
+                body.push(asked as u8);
+                RawMessage {
+                    typemarker: MessageType::Pause,
+                    body,
+                }
+            }
         }
     }
 }
@@ -243,34 +992,108 @@ impl TryFrom<RawMessage> for ServerMessage {
 
     fn try_from(message: RawMessage) -> Result<Self, Self::Error> {
         match message.as_ref() {
-            HANDSHAKE => Ok(ServerMessage::Handshake),
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'H', b'E', b'L', b'O', PROTOCOLVERSION, rest @ ..],
+            } if rest.len() >= 8 => {
+                let (token, rest) = rest.split_at(8);
+                let token = u64::from_le_bytes(array::from_fn(|i| token[i]));
+                let decoded = rest.split_first().and_then(|(&namelen, rest)| {
+                    let (name, info) = rest.split_at_checked(namelen as usize)?;
+                    let name = match name {
+                        [] => None,
+                        name => Some(PlayerName::decode(name)?),
+                    };
+                    Some((ServerInfo::decode(info)?, name))
+                });
+                let (info, name) = decoded.ok_or(Error::from(message))?;
+                Ok(ServerMessage::Handshake(info, token, name))
+            }
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'H', b'E', b'L', b'O', version, ..],
+            } => Err(Error::VersionMismatch {
+                ours: PROTOCOLVERSION,
+                theirs: *version,
+            }),
+            RawMessageRef {
+                typemarker: MessageType::Handshake,
+                body: [b'V', b'E', b'R', b'S', version],
+            } => Ok(ServerMessage::VersionMismatch(*version)),
+            RawMessageRef {
+                typemarker: MessageType::Capabilities,
+                body: [capabilities],
+            } => Ok(ServerMessage::Capabilities(
+                Capabilities::from_bits_truncate(*capabilities),
+            )),
+            RawMessageRef {
+                typemarker: MessageType::Snapshot,
+                body: [yourturn @ (0 | 1), cells @ ..],
+            } if cells.len() == 2 * PACKEDGRIDLEN => {
+                let (selfhits, opphits) = cells.split_at(PACKEDGRIDLEN);
+                match (decodegrid(selfhits), decodegrid(opphits)) {
+                    (Some(selfhits), Some(opphits)) => {
+                        Ok(ServerMessage::Snapshot(logic::BoardSnapshot {
+                            selfhits,
+                            opphits,
+                            yourturn: *yourturn != 0,
+                        }))
+                    }
+                    _ => Err(Error::from(message)),
+                }
+            }
+            REFUSED => Ok(ServerMessage::Refused),
             INVALID => Ok(ServerMessage::Invalid),
+            RawMessageRef {
+                typemarker: MessageType::Invalid,
+                body: [b'I', b'N', b'V', b'A', b'L', b'I', b'D', reason @ ..],
+            } => Ok(ServerMessage::InvalidShips(match reason {
+                [INVALIDSHIPOVERLAP] => logic::Error::ShipOverlap,
+                [INVALIDSHIPLENGTHS] => logic::Error::InvalidShipLengths,
+                [INVALIDOUTOFBOUNDS, x, y] => logic::Error::OutOfBounds { x: *x, y: *y },
+                [INVALIDSHIPSTOUCHING] => logic::Error::ShipsTouching,
+                _ => return Err(Error::from(message)),
+            })),
             REQUESTSHIPPOSITIONS => Ok(ServerMessage::RequestShipPositions),
-            REQUESTTARGET => Ok(ServerMessage::RequestTarget),
+            OFFERREMATCH => Ok(ServerMessage::OfferRematch),
+            REQUESTTARGET => Ok(ServerMessage::RequestTarget(None)),
             RawMessageRef {
-                typemarker: INFORMTARGETHIT,
+                typemarker: MessageType::Target,
+                body: [b'T', b'A', b'R', b'G', low, high],
+            } => Ok(ServerMessage::RequestTarget(Some(u16::from_le_bytes([
+                *low, *high,
+            ])))),
+            RawMessageRef {
+                typemarker: MessageType::Target,
+                body: [b'S', b'A', b'L', b'V', shots, low, high],
+            } if *shots > 0 => Ok(ServerMessage::RequestSalvo(
+                *shots,
+                u16::from_le_bytes([*low, *high]),
+            )),
+            RawMessageRef {
+                typemarker: MessageType::TargetHit,
                 body: [0, pos, sunken],
             } => {
-                let sunken = *sunken != 0;
+                let sunken = (*sunken != 0).then_some(*sunken);
                 let pos = logic::Position::frombyte(*pos).ok_or(Error::from(message))?;
                 Ok(ServerMessage::InformTargetHitYou(pos, sunken))
             }
             RawMessageRef {
-                typemarker: INFORMTARGETHIT,
+                typemarker: MessageType::TargetHit,
                 body: [1, pos, sunken],
             } => {
-                let sunken = *sunken != 0;
+                let sunken = (*sunken != 0).then_some(*sunken);
                 let pos = logic::Position::frombyte(*pos).ok_or(Error::from(message))?;
                 Ok(ServerMessage::InformTargetHitOpp(pos, sunken))
             }
             RawMessageRef {
-                typemarker: INFORMTARGETMISS,
+                typemarker: MessageType::TargetMiss,
                 body: [0, pos],
             } => Ok(ServerMessage::InformTargetMissYou(
                 logic::Position::frombyte(*pos).ok_or(Error::from(message))?,
             )),
             RawMessageRef {
-                typemarker: INFORMTARGETMISS,
+                typemarker: MessageType::TargetMiss,
                 body: [1, pos],
             } => Ok(ServerMessage::InformTargetMissOpp(
                 logic::Position::frombyte(*pos).ok_or(Error::from(message))?,
@@ -278,7 +1101,66 @@ impl TryFrom<RawMessage> for ServerMessage {
             INFORMTARGETSELECTION => Ok(ServerMessage::InformTargetSelection),
             INFORMVICTORY => Ok(ServerMessage::InformVictory),
             INFORMLOSS => Ok(ServerMessage::InformLoss),
+            RawMessageRef {
+                typemarker: MessageType::GameSummary,
+                body:
+                    [GAMESUMMARYVERSION, winner @ (0 | 1), turnslow, turnshigh, hits1, misses1, hits2, misses2],
+            } => Ok(ServerMessage::GameSummary(logic::GameSummary {
+                winner: *winner,
+                turns: u16::from_le_bytes([*turnslow, *turnshigh]),
+                stats: [
+                    logic::ShotStats {
+                        hits: *hits1,
+                        misses: *misses1,
+                    },
+                    logic::ShotStats {
+                        hits: *hits2,
+                        misses: *misses2,
+                    },
+                ],
+            })),
+            RawMessageRef {
+                typemarker: MessageType::OpponentLeft,
+                body: [reason],
+            } => Ok(ServerMessage::OpponentLeft(
+                LeaveReason::frombyte(*reason).ok_or(Error::from(message))?,
+            )),
+            RawMessageRef {
+                typemarker: MessageType::OpponentAway,
+                body: [low, high],
+            } => Ok(ServerMessage::OpponentAway(u16::from_le_bytes([
+                *low, *high,
+            ]))),
+            RawMessageRef {
+                typemarker: MessageType::Fleets,
+                body,
+            } if body.len() == 2 * FLEETLEN => {
+                let fleet = |body: &[u8]| match ClientMessage::try_from(RawMessage {
+                    typemarker: MessageType::ShipPositions,
+                    body: body.to_owned(),
+                }) {
+                    Ok(ClientMessage::ShipPositions(ships)) => Some(ships),
+                    _ => None,
+                };
+                let (first, second) = body.split_at(FLEETLEN);
+                match (fleet(first), fleet(second)) {
+                    (Some(first), Some(second)) => Ok(ServerMessage::Fleets([first, second])),
+                    _ => Err(Error::from(message)),
+                }
+            }
             TERMINATECONNECTION => Ok(ServerMessage::TerminateConnection),
+            RawMessageRef {
+                typemarker: MessageType::Chat,
+                body,
+            } => Ok(ServerMessage::Chat(
+                decodechat(body).ok_or(Error::from(message))?,
+            )),
+            RawMessageRef {
+                typemarker: MessageType::Pause,
+                body,
+            } => Ok(ServerMessage::Pause(
+                PauseNews::decode(body).ok_or(Error::from(message))?,
+            )),
             _ => Err(Error::from(message)),
         }
     }
@@ -287,37 +1169,386 @@ impl TryFrom<RawMessage> for ServerMessage {
 impl From<ServerMessage> for RawMessage {
     fn from(message: ServerMessage) -> Self {
         match message {
-            ServerMessage::Handshake => HANDSHAKE.to_owned(),
+            ServerMessage::Handshake(info, token, name) => {
+                let mut body = HANDSHAKE.body.to_owned();
+                body.push(PROTOCOLVERSION);
+                body.extend_from_slice(&token.to_le_bytes());
+                let name = name.as_ref().map_or("", PlayerName::name);
+                body.push(name.len() as u8);
+                body.extend_from_slice(name.as_bytes());
+                info.encode(&mut body);
+                RawMessage {
+                    typemarker: MessageType::Handshake,
+                    body,
+                }
+            }
+            ServerMessage::VersionMismatch(version) => {
+                let mut body = VERSIONMISMATCH.body.to_owned();
+                body.push(version);
+                RawMessage {
+                    typemarker: MessageType::Handshake,
+                    body,
+                }
+            }
+            ServerMessage::Capabilities(capabilities) => RawMessage {
+                typemarker: MessageType::Capabilities,
+                body: vec![capabilities.bits()],
+            },
+            ServerMessage::Snapshot(snapshot) => {
+                let mut body = vec![snapshot.yourturn as u8];
+                encodegrid(&snapshot.selfhits, &mut body);
+                encodegrid(&snapshot.opphits, &mut body);
+                RawMessage {
+                    typemarker: MessageType::Snapshot,
+                    body,
+                }
+            }
+            ServerMessage::Refused => REFUSED.to_owned(),
             ServerMessage::Invalid => INVALID.to_owned(),
-            ServerMessage::RequestTarget => REQUESTTARGET.to_owned(),
+            ServerMessage::InvalidShips(reason) => {
+                let mut body = INVALID.body.to_owned();
+                match reason {
+                    logic::Error::ShipOverlap => body.push(INVALIDSHIPOVERLAP),
+                    logic::Error::ShipsTouching => body.push(INVALIDSHIPSTOUCHING),
+                    logic::Error::OutOfBounds { x, y } => {
+                        body.extend_from_slice(&[INVALIDOUTOFBOUNDS, x, y])
+                    }
+                    // nothing else is raised while validating a fleet
+                    _ => body.push(INVALIDSHIPLENGTHS),
+                }
+                RawMessage {
+                    typemarker: MessageType::Invalid,
+                    body,
+                }
+            }
+            ServerMessage::RequestTarget(None) => REQUESTTARGET.to_owned(),
+            ServerMessage::RequestTarget(Some(secs)) => {
+                let mut body = REQUESTTARGET.body.to_owned();
+                body.extend_from_slice(&secs.to_le_bytes());
+                RawMessage {
+                    typemarker: MessageType::Target,
+                    body,
+                }
+            }
+            ServerMessage::RequestSalvo(shots, secs) => {
+                let mut body = SALVO.to_owned();
+                body.push(shots);
+                body.extend_from_slice(&secs.to_le_bytes());
+                RawMessage {
+                    typemarker: MessageType::Target,
+                    body,
+                }
+            }
             ServerMessage::RequestShipPositions => REQUESTSHIPPOSITIONS.to_owned(),
+            ServerMessage::OfferRematch => OFFERREMATCH.to_owned(),
             ServerMessage::InformTargetHitYou(pos, sunken) => RawMessage {
-                typemarker: INFORMTARGETHIT,
-                body: vec![0, pos.byte(), sunken as u8],
+                typemarker: MessageType::TargetHit,
+                body: vec![0, pos.byte(), sunken.unwrap_or(0)],
             },
             ServerMessage::InformTargetHitOpp(pos, sunken) => RawMessage {
-                typemarker: INFORMTARGETHIT,
-                body: vec![1, pos.byte(), sunken as u8],
+                typemarker: MessageType::TargetHit,
+                body: vec![1, pos.byte(), sunken.unwrap_or(0)],
             },
             ServerMessage::InformTargetMissYou(pos) => RawMessage {
-                typemarker: INFORMTARGETMISS,
+                typemarker: MessageType::TargetMiss,
                 body: vec![0, pos.byte()],
             },
             ServerMessage::InformTargetMissOpp(pos) => RawMessage {
-                typemarker: INFORMTARGETMISS,
+                typemarker: MessageType::TargetMiss,
                 body: vec![1, pos.byte()],
             },
             ServerMessage::InformVictory => INFORMVICTORY.to_owned(),
             ServerMessage::InformLoss => INFORMLOSS.to_owned(),
+            ServerMessage::GameSummary(summary) => {
+                let [turnslow, turnshigh] = summary.turns.to_le_bytes();
+                let [stats1, stats2] = summary.stats;
+                RawMessage {
+                    typemarker: MessageType::GameSummary,
+                    body: vec![
+                        GAMESUMMARYVERSION,
+                        summary.winner,
+                        turnslow,
+                        turnshigh,
+                        stats1.hits,
+                        stats1.misses,
+                        stats2.hits,
+                        stats2.misses,
+                    ],
+                }
+            }
+            ServerMessage::OpponentLeft(reason) => RawMessage {
+                typemarker: MessageType::OpponentLeft,
+                body: vec![reason.byte()],
+            },
+            ServerMessage::OpponentAway(secs) => RawMessage {
+                typemarker: MessageType::OpponentAway,
+                body: secs.to_le_bytes().to_vec(),
+            },
+            ServerMessage::Fleets(fleets) => RawMessage {
+                typemarker: MessageType::Fleets,
+                body: fleets
+                    .into_iter()
+                    .flat_map(|ships| RawMessage::from(ClientMessage::ShipPositions(ships)).body)
+                    .collect(),
+            },
             ServerMessage::InformTargetSelection => INFORMTARGETSELECTION.to_owned(),
             ServerMessage::TerminateConnection => TERMINATECONNECTION.to_owned(),
+            ServerMessage::Chat(text) => RawMessage {
+                typemarker: MessageType::Chat,
+                body: text.into_bytes(),
+            },
+            ServerMessage::Pause(news) => RawMessage {
+                typemarker: MessageType::Pause,
+                body: news.encode(),
+            },
+        }
+    }
+}
+
+/// crockford base32, no padding and no easily confused letters
+const CODEALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+impl logic::Ships {
+    /// the compact ship positions encoding, as a short copy-pasteable code
+    pub fn tocode(&self) -> String {
+        let RawMessage { body, .. } = RawMessage::from(ClientMessage::CompactShipPositions(*self));
+        let mut code = String::new();
+        let (mut acc, mut bits) = (0u32, 0);
+        for byte in body {
+            acc = (acc << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                code.push(CODEALPHABET[(acc >> bits) as usize & 0x1f] as char);
+            }
+        }
+        if bits > 0 {
+            code.push(CODEALPHABET[(acc << (5 - bits)) as usize & 0x1f] as char);
+        }
+        code
+    }
+
+    /// parses and validates a code produced by `tocode`; case insensitive,
+    /// and `I`, `L`, `O` are read as the digits they resemble
+    pub fn fromcode(code: &str) -> Result<logic::Ships, Error> {
+        let mut body = Vec::new();
+        let (mut acc, mut bits) = (0u32, 0);
+        for c in code.trim().chars() {
+            let c = match c.to_ascii_uppercase() {
+                'I' | 'L' => '1',
+                'O' => '0',
+                c => c,
+            };
+            let value = CODEALPHABET
+                .iter()
+                .position(|&symbol| symbol as char == c)
+                .ok_or(Error::InvalidCode)?;
+            acc = (acc << 5) | value as u32;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                body.push((acc >> bits) as u8);
+            }
+        }
+        // leftover bits are padding and must be zero
+        if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+            return Err(Error::InvalidCode);
+        }
+
+        match ClientMessage::try_from(RawMessage {
+            typemarker: MessageType::ShipPositions,
+            body,
+        }) {
+            Ok(ClientMessage::CompactShipPositions(ships)) => Ok(ships),
+            Err(Error::InvalidFleet(reason)) => Err(Error::InvalidFleet(reason)),
+            _ => Err(Error::InvalidCode),
+        }
+    }
+}
+
+/// every resolved event of one game, enough to rebuild both boards shot by
+/// shot
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub fleets: [logic::Ships; 2],
+    /// the player who fired, the target and the outcome, in order
+    pub shots: Vec<(u8, logic::Position, logic::AttackInfo)>,
+    /// `None` while running or if the game was not decided
+    pub winner: Option<u8>,
+}
+
+/// starts every record file, followed by `RECORDVERSION`
+const RECORDMAGIC: &[u8] = b"ZREC";
+const RECORDVERSION: u8 = 1;
+
+impl GameRecord {
+    pub fn new(fleets: [logic::Ships; 2]) -> GameRecord {
+        GameRecord {
+            fleets,
+            shots: Vec::new(),
+            winner: None,
+        }
+    }
+
+    /// magic and version, both fleets prefixed by their length, the winner
+    /// or 0xff, then three bytes per shot; a fleet is in the compact encoding
+    /// unless its lengths are not the classic ones
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = RECORDMAGIC.to_owned();
+        buffer.push(RECORDVERSION);
+        for ships in self.fleets {
+            let message = if logic::FleetSpec::default().matches(ships.asslice()) {
+                ClientMessage::CompactShipPositions(ships)
+            } else {
+                ClientMessage::ShipPositions(ships)
+            };
+            let RawMessage { body, .. } = message.into();
+            buffer.push(body.len() as u8);
+            buffer.extend(body);
         }
+        buffer.push(self.winner.unwrap_or(u8::MAX));
+        for &(player, pos, info) in &self.shots {
+            buffer.extend([player, pos.byte(), logic::AttackInfo::tobyte(Some(info))]);
+        }
+        buffer
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<GameRecord, Error> {
+        GameRecord::decodeparts(bytes).ok_or(Error::InvalidRecord)
+    }
+
+    fn decodeparts(bytes: &[u8]) -> Option<GameRecord> {
+        let body = bytes.strip_prefix(RECORDMAGIC)?;
+        let (&RECORDVERSION, mut body) = body.split_first()? else {
+            return None;
+        };
+        let mut fleets = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let (&len, rest) = body.split_first()?;
+            let (fleet, rest) = rest.split_at_checked(len as usize)?;
+            let message = RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body: fleet.to_owned(),
+            };
+            let (ClientMessage::CompactShipPositions(ships) | ClientMessage::ShipPositions(ships)) =
+                message.try_into().ok()?
+            else {
+                return None;
+            };
+            fleets.push(ships);
+            body = rest;
+        }
+        let (&winner, body) = body.split_first()?;
+        if body.len() % 3 != 0 {
+            return None;
+        }
+        let shots = body
+            .chunks(3)
+            .map(|shot| {
+                let pos = logic::Position::frombyte(shot[1])?;
+                let info = logic::AttackInfo::frombyte(shot[2]).ok()??;
+                (shot[0] < 2).then_some((shot[0], pos, info))
+            })
+            .collect::<Option<_>>()?;
+        Some(GameRecord {
+            fleets: fleets.try_into().ok()?,
+            shots,
+            winner: (winner < 2).then_some(winner),
+        })
+    }
+
+    pub async fn load(path: impl AsRef<std::path::Path>) -> Result<GameRecord, Error> {
+        GameRecord::decode(&tokio::fs::read(path).await?)
+    }
+}
+
+/// CRC-32 as used by zlib and Ethernet, reflected polynomial 0xEDB88320
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// whether frames of this type end in a checksum, see the framing notes;
+/// handshakes and refusals are the frames a peer of any version has to
+/// understand, and peers before version 3 neither send nor expect a checksum
+fn checksummed(typemarker: u8) -> bool {
+    typemarker != MessageType::Handshake as u8 && typemarker != MessageType::Refused as u8
+}
+
+/// serializes a message as typemarker, little endian body size, body and
+/// checksum
+pub fn encodeframe(message: &RawMessage) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(9 + message.body.len());
+    frame.push(message.typemarker as u8);
+    frame.extend_from_slice(&u32::to_le_bytes(message.body.len() as u32));
+    frame.extend_from_slice(&message.body);
+    if checksummed(message.typemarker as u8) {
+        frame.extend_from_slice(&u32::to_le_bytes(crc32(&frame)));
     }
+    frame
 }
 
-pub async fn readmessage<M>(stream: &mut net::TcpStream) -> Result<M, Error>
+fn verifychecksum(header: &[u8], body: &[u8], checksum: [u8; 4]) -> Result<(), Error> {
+    let got = u32::from_le_bytes(checksum);
+    let mut frame = Vec::with_capacity(header.len() + body.len());
+    frame.extend_from_slice(header);
+    frame.extend_from_slice(body);
+    let expected = crc32(&frame);
+    if got != expected {
+        return Err(Error::Checksum {
+            typemarker: header[0],
+            expected,
+            got,
+        });
+    }
+    Ok(())
+}
+
+/// parses a complete frame as produced by `encodeframe`
+pub fn decodeframe(frame: &[u8]) -> Result<RawMessage, Error> {
+    let invalid = || Error::Message {
+        typemarker: frame.first().copied().unwrap_or_default(),
+        sizemarker: frame.len() as u32,
+        body: frame.to_owned(),
+    };
+
+    let [typemarker, s1, s2, s3, s4, body @ ..] = frame else {
+        return Err(invalid());
+    };
+    let body = if checksummed(*typemarker) {
+        let [body @ .., c1, c2, c3, c4] = body else {
+            return Err(invalid());
+        };
+        verifychecksum(&frame[..5], body, [*c1, *c2, *c3, *c4])?;
+        body
+    } else {
+        body
+    };
+    if u32::from_le_bytes([*s1, *s2, *s3, *s4]) as usize != body.len() {
+        return Err(invalid());
+    }
+    Ok(RawMessage {
+        typemarker: MessageType::try_from(*typemarker)?,
+        body: body.to_owned(),
+    })
+}
+
+/// longest body a peer may announce; the largest message, a handshake reply
+/// with a full server name, stays well below
+pub const MAXBODYLEN: usize = 96;
+
+/// reads one frame off any byte stream, a socket, a read half or an in
+/// memory pipe
+pub async fn readmessage<M, S>(stream: &mut S) -> Result<M, Error>
 where
     M: TryFrom<RawMessage, Error = Error>,
+    S: AsyncRead + Unpin,
 {
     let mut typemarker = [0u8; 1];
     let mut sizemarker = [0u8; 4];
@@ -325,24 +1556,452 @@ where
     stream.read_exact(&mut sizemarker).await?;
     let typemarker = typemarker[0];
     let sizemarker = u32::from_le_bytes(sizemarker);
+    // refuse before allocating whatever the peer claims
+    if sizemarker as usize > MAXBODYLEN {
+        return Err(Error::Message {
+            typemarker,
+            sizemarker,
+            body: Vec::new(),
+        });
+    }
     let mut body = vec![0u8; sizemarker as usize];
     stream.read_exact(&mut body).await?;
-    let raw = RawMessage { typemarker, body };
+    if checksummed(typemarker) {
+        let mut checksum = [0u8; 4];
+        stream.read_exact(&mut checksum).await?;
+        let mut header = [typemarker; 5];
+        header[1..].copy_from_slice(&u32::to_le_bytes(sizemarker));
+        verifychecksum(&header, &body, checksum)?;
+    }
+    // the whole frame is off the stream by now, whatever its type
+    let raw = RawMessage {
+        typemarker: MessageType::try_from(typemarker)?,
+        body,
+    };
     M::try_from(raw)
 }
 
-pub async fn sendmessage<M>(stream: &mut net::TcpStream, message: M) -> Result<(), Error>
+/// `readmessage`, failing with `Error::Timeout` once `duration` passed
+/// without a complete message
+pub async fn readmessagewithin<M, S>(stream: &mut S, duration: time::Duration) -> Result<M, Error>
+where
+    M: TryFrom<RawMessage, Error = Error>,
+    S: AsyncRead + Unpin,
+{
+    time::timeout(duration, readmessage(stream))
+        .await
+        .map_err(|_| Error::Timeout)?
+}
+
+/// writes and flushes one frame, the counterpart of `readmessage`
+pub async fn sendmessage<M, S>(stream: &mut S, message: M) -> Result<(), Error>
 where
     RawMessage: From<M>,
+    S: AsyncWrite + Unpin,
 {
-    let message = RawMessage::from(message);
-    let typemarker = [message.typemarker; 1];
-    let sizemarker = u32::to_le_bytes(message.body.len() as u32);
-    let body = message.body;
-    stream.write_all(&typemarker).await?;
-    stream.write_all(&sizemarker).await?;
-    stream.write_all(&body).await?;
+    let frame = encodeframe(&RawMessage::from(message));
+    stream.write_all(&frame).await?;
     stream.flush().await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilitiesrequire() {
+        assert!(Capabilities::supported()
+            .require(Capabilities::SALVO | Capabilities::CHAT)
+            .is_ok());
+        assert!(matches!(
+            Capabilities::supported().require(Capabilities::TLS | Capabilities::SALVO),
+            Err(Error::Unsupported(missing)) if missing == Capabilities::TLS
+        ));
+    }
+
+    #[tokio::test]
+    async fn oversizedbodyrefused() {
+        // no body follows, reading one would fail with a network error instead
+        let frame = [MessageType::Chat as u8, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(matches!(
+            readmessage::<ServerMessage, _>(&mut frame.as_slice()).await,
+            Err(Error::Message {
+                typemarker: 200,
+                sizemarker: u32::MAX,
+                ..
+            })
+        ));
+        let mut frame = encodeframe(&RawMessage::from(ServerMessage::Chat(
+            "x".repeat(MAXBODYLEN + 1),
+        )));
+        assert!(matches!(
+            readmessage::<ServerMessage, _>(&mut frame.as_slice()).await,
+            Err(Error::Message { body, .. }) if body.is_empty()
+        ));
+        // a body at the limit is read in full, then refused as too long a chat
+        frame = encodeframe(&RawMessage::from(ServerMessage::Chat(
+            "x".repeat(MAXBODYLEN),
+        )));
+        assert!(matches!(
+            readmessage::<ServerMessage, _>(&mut frame.as_slice()).await,
+            Err(Error::Message { body, .. }) if body.len() == MAXBODYLEN
+        ));
+    }
+
+    #[tokio::test]
+    async fn checksumcatchesflips() {
+        let frame = encodeframe(&RawMessage::from(ServerMessage::InformTargetMissOpp(
+            logic::Position::new(3, 4).unwrap(),
+        )));
+        assert!(decodeframe(&frame).is_ok());
+        // typemarker and size are covered as well as the body and the sum
+        for i in [0, 1, 5, frame.len() - 1] {
+            let mut flipped = frame.clone();
+            flipped[i] ^= 0x01;
+            assert!(
+                matches!(decodeframe(&flipped), Err(Error::Checksum { .. })),
+                "byte {i}"
+            );
+        }
+        let mut flipped = frame.clone();
+        flipped[5] ^= 0x80;
+        assert!(matches!(
+            readmessage::<ServerMessage, _>(&mut flipped.as_slice()).await,
+            Err(Error::Checksum {
+                typemarker: 152,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn handshakeskipschecksum() {
+        let frame = encodeframe(&RawMessage::from(ServerMessage::VersionMismatch(
+            PROTOCOLVERSION,
+        )));
+        assert_eq!(frame.len(), 5 + 5);
+        let refused = encodeframe(&RawMessage::from(ServerMessage::Refused));
+        assert_eq!(refused.len(), 5 + REFUSED.body.len());
+        // a server from before checksums still gets its version across
+        let old = [1, 5, 0, 0, 0, b'H', b'E', b'L', b'O', 2];
+        assert!(matches!(
+            readmessage::<ServerMessage, _>(&mut old.as_slice()).await,
+            Err(Error::VersionMismatch { theirs: 2, .. })
+        ));
+        assert!(matches!(
+            decodeframe(&old),
+            Ok(RawMessage {
+                typemarker: MessageType::Handshake,
+                ..
+            })
+        ));
+    }
+
+    /// cells of every ship, in an order that ignores the order of the ships
+    fn cells(ships: &logic::Ships) -> Vec<Vec<(u8, u8)>> {
+        let mut cells: Vec<Vec<(u8, u8)>> = ships
+            .into_iter()
+            .map(|ship| ship.into_iter().map(logic::Position::coords).collect())
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    fn decodeships(message: ClientMessage) -> logic::Ships {
+        match ClientMessage::try_from(RawMessage::from(message)) {
+            Ok(
+                ClientMessage::ShipPositions(ships) | ClientMessage::CompactShipPositions(ships),
+            ) => ships,
+            other => panic!("ships did not survive the wire: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn compactfleetroundtrip() {
+        let fleet = logic::FleetSpec::default();
+        for _ in 0..50 {
+            let ships = fleet.random(&mut rand::rng()).unwrap();
+            let verbose = decodeships(ClientMessage::ShipPositions(ships));
+            let compact = decodeships(ClientMessage::CompactShipPositions(verbose));
+            assert_eq!(cells(&compact), cells(&ships));
+            assert_eq!(
+                cells(&decodeships(ClientMessage::ShipPositions(compact))),
+                cells(&ships)
+            );
+
+            let code = ships.tocode();
+            assert_eq!(code.len(), 10);
+            assert_eq!(
+                cells(&logic::Ships::fromcode(&code).unwrap()),
+                cells(&ships)
+            );
+            let sloppy = format!(
+                " {} ",
+                code.to_ascii_lowercase()
+                    .replace('1', "l")
+                    .replace('0', "O")
+            );
+            assert_eq!(
+                cells(&logic::Ships::fromcode(&sloppy).unwrap()),
+                cells(&ships)
+            );
+        }
+    }
+
+    #[test]
+    fn verbosefleetfuzz() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for round in 0..100_000 {
+            let mut body = [0u8; FLEETLEN];
+            if round % 2 == 0 {
+                rng.fill(&mut body[..]);
+            } else {
+                // plausible slots get past the byte checks more often
+                for slot in body.chunks_exact_mut(3) {
+                    slot.copy_from_slice(&[
+                        rng.random_range(0..2),
+                        rng.random(),
+                        rng.random_range(0..=logic::BOARDSIZE + 1),
+                    ]);
+                }
+            }
+            let decoded = ClientMessage::try_from(RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body: body.to_vec(),
+            });
+            // whatever decodes is a fleet on the board
+            if let Ok(ClientMessage::ShipPositions(ships)) = decoded {
+                assert!(logic::validshippos(ships.asslice()));
+                for pos in ships.into_iter().flatten() {
+                    let (x, y) = pos.coords();
+                    assert!(x < logic::BOARDSIZE && y < logic::BOARDSIZE, "{body:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compactfleetrejects() {
+        // verbose positions carry their lengths, the fleet check is the server's
+        let mega = logic::FleetSpec::from(logic::FleetPreset::Mega);
+        let ships = decodeships(ClientMessage::ShipPositions(
+            mega.random(&mut rand::rng()).unwrap(),
+        ));
+        let classic = logic::FleetSpec::default();
+        assert_eq!(
+            logic::Ships::validate(ships.asslice(), &classic).unwrap_err(),
+            logic::Error::InvalidShipLengths
+        );
+
+        // every ship at A1, all vertical
+        let overlapping = [0, 0, 0, 0, 0, 0];
+        assert!(matches!(
+            ClientMessage::try_from(RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body: overlapping.to_vec(),
+            }),
+            Err(Error::InvalidFleet(logic::Error::ShipOverlap))
+        ));
+        // the carrier, horizontal from H1, leaves the board
+        let offboard = [0x00, 0x01, 0x02, 0x03, 0x07, 0b10000];
+        assert!(matches!(
+            ClientMessage::try_from(RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body: offboard.to_vec(),
+            }),
+            Err(Error::InvalidFleet(logic::Error::OutOfBounds {
+                x: 11,
+                y: 0
+            }))
+        ));
+        // unused orientation bits
+        let mut body = RawMessage::from(ClientMessage::CompactShipPositions(
+            classic.random(&mut rand::rng()).unwrap(),
+        ))
+        .body;
+        body[5] |= 0x80;
+        assert!(matches!(
+            ClientMessage::try_from(RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body,
+            }),
+            Err(Error::Message { .. })
+        ));
+
+        let code = classic.random(&mut rand::rng()).unwrap().tocode();
+        let (head, last) = code.split_at(code.len() - 1);
+        let last = CODEALPHABET
+            .iter()
+            .position(|&c| c == last.as_bytes()[0])
+            .unwrap();
+        for bad in [
+            // a padding bit set
+            format!("{head}{}", CODEALPHABET[last | 1] as char),
+            // a character too many, or too few
+            format!("{code}0"),
+            head.to_owned(),
+            // outside the alphabet
+            format!("{head}U"),
+            format!("{head}-"),
+            String::new(),
+        ] {
+            assert!(logic::Ships::fromcode(&bad).is_err(), "{bad:?}");
+        }
+        assert!(matches!(
+            logic::Ships::fromcode(&format!("{head}U")),
+            Err(Error::InvalidCode)
+        ));
+        assert!(matches!(
+            logic::Ships::fromcode(&format!("{head}{}", CODEALPHABET[last | 1] as char)),
+            Err(Error::InvalidCode)
+        ));
+        // a valid code of ships that overlap
+        assert!(matches!(
+            logic::Ships::fromcode("0000000000"),
+            Err(Error::InvalidFleet(logic::Error::ShipOverlap))
+        ));
+    }
+
+    #[test]
+    fn typemarkersroundtrip() {
+        for &typemarker in MessageType::ALL {
+            assert_eq!(MessageType::try_from(typemarker as u8).unwrap(), typemarker);
+        }
+        let known: Vec<u8> = MessageType::ALL.iter().map(|&t| t as u8).collect();
+        for marker in (0..=u8::MAX).filter(|marker| !known.contains(marker)) {
+            assert!(
+                matches!(MessageType::try_from(marker), Err(Error::UnknownType(m)) if m == marker)
+            );
+        }
+    }
+
+    #[test]
+    fn snapshotroundtrip() {
+        let mut board = logic::Board::new(logic::randomfleet(&mut rand::rng()));
+        let ships = *board.ships();
+        // sink the first ship, hit the second and miss somewhere
+        for pos in ships[0].into_iter().chain(ships[1].into_iter().take(1)) {
+            board.target(pos).unwrap();
+        }
+        let miss = (0..logic::BOARDSIZE * logic::BOARDSIZE)
+            .filter_map(|i| logic::Position::fromcoords(i % logic::BOARDSIZE, i / logic::BOARDSIZE))
+            .find(|&pos| {
+                ships
+                    .into_iter()
+                    .all(|ship| ship.into_iter().all(|p| p != pos))
+            })
+            .unwrap();
+        board.target(miss).unwrap();
+
+        for yourturn in [false, true] {
+            let snapshot = logic::BoardSnapshot {
+                selfhits: board.hitgrid(),
+                opphits: logic::HitGrid::new(),
+                yourturn,
+            };
+            let message = RawMessage::from(ServerMessage::Snapshot(snapshot));
+            assert_eq!(message.body.len(), 1 + 2 * PACKEDGRIDLEN);
+            let Ok(ServerMessage::Snapshot(decoded)) = ServerMessage::try_from(message) else {
+                panic!("snapshot did not survive the wire");
+            };
+            assert_eq!(decoded, snapshot);
+        }
+    }
+
+    #[test]
+    fn presetfleetonwire() {
+        let fleet = logic::FleetSpec::from(logic::FleetPreset::Mega);
+        let info = ServerInfo::default().withfleet(&fleet);
+        let message = RawMessage::from(ServerMessage::Handshake(info.clone(), 7, None));
+        let Ok(ServerMessage::Handshake(decoded, 7, None)) = ServerMessage::try_from(message)
+        else {
+            panic!("handshake did not survive the wire");
+        };
+        assert_eq!(decoded, info);
+        assert_eq!(decoded.fleet(), [4, 4, 5, 5, 6]);
+
+        // records keep the lengths of fleets the compact encoding cannot hold
+        let ships = fleet.random(&mut rand::rng()).unwrap();
+        let record = GameRecord::decode(&GameRecord::new([ships; 2]).encode()).unwrap();
+        for decoded in record.fleets {
+            assert!(fleet.matches(decoded.asslice()));
+        }
+    }
+
+    #[test]
+    fn shortfleetonwire() {
+        let fleet = logic::FleetSpec::from(logic::FleetPreset::Small);
+        let ships = fleet.random(&mut rand::rng()).unwrap();
+        let message = RawMessage::from(ClientMessage::ShipPositions(ships));
+        // unused slots stay zero, the body keeps its size
+        assert_eq!(message.body.len(), FLEETLEN);
+        assert!(message.body[9..].iter().all(|&byte| byte == 0));
+        let body = message.body.clone();
+        let Ok(ClientMessage::ShipPositions(decoded)) = ClientMessage::try_from(message) else {
+            panic!("short fleet did not survive the wire");
+        };
+        assert_eq!(decoded.asslice().len(), 3);
+        assert!(fleet.matches(decoded.asslice()));
+
+        // a ship after an unused slot, or no ship at all, is malformed
+        let mut gap = body.clone();
+        gap[3..6].fill(0);
+        gap[9..12].copy_from_slice(&body[3..6]);
+        for body in [gap, vec![0; FLEETLEN]] {
+            assert!(ClientMessage::try_from(RawMessage {
+                typemarker: MessageType::ShipPositions,
+                body,
+            })
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn playernamesonwire() {
+        let queue = QueueKey::new("ranked").unwrap();
+        let name = PlayerName::new("grace hopper").unwrap();
+        for name in [None, Some(name.clone())] {
+            let hello = ClientMessage::Handshake(PROTOCOLVERSION, queue.clone(), name.clone());
+            let Ok(ClientMessage::Handshake(PROTOCOLVERSION, decoded, decodedname)) =
+                ClientMessage::try_from(RawMessage::from(hello))
+            else {
+                panic!("handshake did not survive the wire");
+            };
+            assert_eq!((decoded, decodedname), (queue.clone(), name.clone()));
+
+            let ack = ServerMessage::Handshake(ServerInfo::default(), 7, name.clone());
+            let Ok(ServerMessage::Handshake(_, 7, decodedname)) =
+                ServerMessage::try_from(RawMessage::from(ack))
+            else {
+                panic!("acknowledgment did not survive the wire");
+            };
+            assert_eq!(decodedname, name);
+        }
+
+        for invalid in ["", " ", "a\tb", "ümlaut", "seventeen chars!!"] {
+            assert!(matches!(
+                PlayerName::new(invalid),
+                Err(Error::InvalidPlayerName(_))
+            ));
+        }
+        // a zero byte promises a name, it has to be a valid one
+        for name in [&b""[..], b"a\nb"] {
+            let mut body = b"HELO".to_vec();
+            body.push(PROTOCOLVERSION);
+            body.push(0);
+            body.extend_from_slice(name);
+            let hello = RawMessage {
+                typemarker: MessageType::Handshake,
+                body,
+            };
+            assert!(matches!(
+                ClientMessage::try_from(hello),
+                Err(Error::Message { .. })
+            ));
+        }
+    }
+}
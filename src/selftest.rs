@@ -0,0 +1,109 @@
+use tokio::{io, net, time};
+
+use crate::{client, logic, server};
+
+const TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("networking error; {0}")]
+    Networking(#[from] io::Error),
+    #[error("client error; {0}")]
+    Client(String),
+    #[error("discrepancy; {0}")]
+    Discrepancy(&'static str),
+}
+
+/// the standard fleet stacked vertically in the five leftmost columns
+fn fleet() -> logic::Ships {
+    const SHIPLEN: [u8; 5] = [5, 4, 3, 3, 2];
+    let ships: [logic::Ship; 5] = std::array::from_fn(|i| {
+        logic::Ship::try_from(logic::ShipPlan::Vertical {
+            pos: logic::Position::fromcoords(i as u8, 0).unwrap(),
+            len: SHIPLEN[i],
+        })
+        .unwrap()
+    });
+    logic::Ships::try_from(ships).unwrap()
+}
+
+async fn playscripted(
+    addr: std::net::SocketAddr,
+    interface: &mut client::ScriptedUI,
+) -> Result<bool, client::Error<client::ScriptedUI>> {
+    let mut client = client::Client::connect(addr, interface, Some(TIMEOUT)).await?;
+    client.play(interface).await
+}
+
+/// plays a deterministic game between two scripted clients over loopback;
+/// the first player only fires at ships and the second only at open water,
+/// so the first player wins regardless of who moves first
+pub async fn run() -> Result<(), Error> {
+    let listener = net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0)).await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(server::serve(listener, server::Config::default()));
+
+    let mut winner = client::ScriptedUI::new(fleet(), fleet().into_iter().flatten());
+    let mut loser = client::ScriptedUI::new(
+        fleet(),
+        (5..10)
+            .flat_map(|x| (0..10).map(move |y| (x, y)))
+            .filter_map(|(x, y)| logic::Position::fromcoords(x, y)),
+    );
+
+    let played = time::timeout(TIMEOUT, async {
+        tokio::join!(
+            playscripted(addr, &mut winner),
+            playscripted(addr, &mut loser)
+        )
+    })
+    .await;
+    server.abort();
+
+    let (winnerres, loserres) = played.map_err(|_| Error::Discrepancy("game timed out"))?;
+    let winnerres = winnerres.map_err(|err| Error::Client(err.to_string()))?;
+    let loserres = loserres.map_err(|err| Error::Client(err.to_string()))?;
+    if !winnerres || loserres {
+        return Err(Error::Discrepancy("wrong winner"));
+    }
+
+    let (Some(winnerview), Some(loserview)) = (winner.end(), loser.end()) else {
+        return Err(Error::Discrepancy("end screen not shown"));
+    };
+    if !winnerview.victory || loserview.victory {
+        return Err(Error::Discrepancy("wrong end screen"));
+    }
+    if winnerview.opphits != loserview.selfhits || winnerview.selfhits != loserview.opphits {
+        return Err(Error::Discrepancy("boards differ between players"));
+    }
+
+    let (hits, misses) = (0..10)
+        .flat_map(|y| (0..10).map(move |x| (x, y)))
+        .filter_map(|(x, y)| winnerview.opphits[y][x])
+        .fold((0, 0), |(hits, misses), info| match info {
+            logic::AttackInfo::Hit(_) => (hits + 1, misses),
+            logic::AttackInfo::Miss => (hits, misses + 1),
+        });
+    if hits != 17 || misses != 0 {
+        return Err(Error::Discrepancy("unexpected final board"));
+    }
+
+    match (winnerview.summary, loserview.summary) {
+        (Some(summary), Some(other))
+            if summary == other && summary.perspective(true)[0].hits == 17 =>
+        {
+            Ok(())
+        }
+        _ => Err(Error::Discrepancy("unexpected game summary")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn selftestpasses() {
+        run().await.unwrap();
+    }
+}
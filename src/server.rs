@@ -1,6 +1,21 @@
-use tokio::{io, net, sync::mpsc};
+use rand::{Rng, SeedableRng};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, path, pin,
+    sync::{self, atomic},
+    task::{self, Poll},
+};
 
-use crate::{logic, prot};
+use tokio::{
+    io,
+    sync::{mpsc, watch},
+    time,
+};
+
+use crate::{
+    conn::{Connection, Endpoint, Listener},
+    logic, prot,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -12,6 +27,35 @@ pub enum Error {
     Middleware(CommandRequest, CommandResult),
     #[error("logic error; {0}")]
     Logic(#[from] logic::Error),
+    #[error("player disconnected")]
+    Disconnected,
+    #[error("player timed out")]
+    TimedOut,
+    #[error("player surrendered")]
+    Surrendered,
+}
+
+impl Error {
+    /// whether the connection is gone, as opposed to the player misbehaving
+    fn dropped(&self) -> bool {
+        matches!(
+            self,
+            Error::Networking(_)
+                | Error::Protocol(prot::Error::Networking(_))
+                | Error::Disconnected
+                | Error::TimedOut
+        )
+    }
+
+    /// what the remaining player is told, if this error means a player is gone
+    fn leavereason(&self, started: bool) -> Option<prot::LeaveReason> {
+        match self {
+            Error::TimedOut => Some(prot::LeaveReason::Timeout),
+            err if err.dropped() && started => Some(prot::LeaveReason::Disconnected),
+            err if err.dropped() => Some(prot::LeaveReason::Abandoned),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -19,15 +63,25 @@ pub enum CommandRequest {
     Handshake,
 
     RequestShips,
-    RequestTarget,
+    /// asks for a target within the given time
+    RequestTarget(time::Duration),
+    /// asks for this many distinct targets within the given time
+    RequestSalvo(u8, time::Duration),
+    InformInvalid,
+    InformInvalidShips(logic::Error),
+    /// boxed, two full grids would bloat every request
+    InformSnapshot(Box<logic::BoardSnapshot>),
 
     InformTargetSelection,
-    InformTargetHitYou(logic::Position, bool),
+    InformTargetHitYou(logic::Position, Option<u8>),
     InformTargetMissYou(logic::Position),
-    InformTargetHitOpp(logic::Position, bool),
+    InformTargetHitOpp(logic::Position, Option<u8>),
     InformTargetMissOpp(logic::Position),
     InformVictory,
     InformLoss,
+    InformGameSummary(logic::GameSummary),
+    InformOpponentLeft(prot::LeaveReason),
+    OfferRematch,
 
     TerminateConnection,
 }
@@ -37,113 +91,800 @@ pub enum CommandResult {
     Success,
     Invalid,
     GetShips(logic::Ships),
+    /// ship positions were sent but refused
+    InvalidShips(logic::Error),
     GetTarget(logic::Position),
+    GetSalvo(Vec<logic::Position>),
+    /// asked for the full public state instead of answering
+    Resync,
+    /// no target arrived in time
+    Expired,
+    /// conceded instead of selecting a target
+    Surrender,
+    /// whether the player accepted another game
+    Rematch(bool),
+}
+
+/// settings shared by every game a server hosts
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// identity announced to clients during the handshake
+    pub info: prot::ServerInfo,
+    /// how long a single message may take to send before the player is
+    /// considered disconnected
+    pub writetimeout: time::Duration,
+    /// how long a player may take to answer any single request but a target
+    /// request
+    pub readtimeout: time::Duration,
+    /// how long a player may take to select a target before losing the game
+    pub turntimeout: time::Duration,
+    /// games played at the same time, further players are refused
+    pub maxgames: usize,
+    /// every game, finished or not, is written here as JSON lines
+    pub transcriptdir: Option<path::PathBuf>,
+    /// every game that got past placement is written here as a
+    /// `prot::GameRecord`
+    pub recorddir: Option<path::PathBuf>,
+    /// ship lengths every player has to place
+    pub fleet: logic::FleetSpec,
+    /// every turn fires one shot per own ship afloat, instead of firing
+    /// until a shot misses
+    pub salvo: bool,
+    /// how long the game waits for a dropped player to reconnect, zero
+    /// ends it right away
+    pub reconnecttimeout: time::Duration,
+    /// chat messages a player may send in a row before being throttled
+    pub chatburst: u32,
+    /// time it takes a throttled player to earn one more chat message, zero
+    /// leaves chat unlimited
+    pub chatinterval: time::Duration,
+    /// longest a pause both players agreed on holds the game, zero declines
+    /// every pause
+    pub maxpause: time::Duration,
+    /// spectators who present it are shown both fleets, nobody is without
+    pub adminpassword: Option<String>,
+    /// counters of every game hosted, keep a clone to read them
+    pub metrics: sync::Arc<Metrics>,
+}
+
+pub const DEFAULTWRITETIMEOUT: time::Duration = time::Duration::from_secs(10);
+pub const DEFAULTREADTIMEOUT: time::Duration = time::Duration::from_secs(300);
+pub const DEFAULTTURNTIMEOUT: time::Duration = time::Duration::from_secs(60);
+pub const DEFAULTMAXGAMES: usize = 64;
+pub const DEFAULTRECONNECTTIMEOUT: time::Duration = time::Duration::from_secs(30);
+pub const DEFAULTCHATBURST: u32 = 5;
+pub const DEFAULTCHATINTERVAL: time::Duration = time::Duration::from_secs(2);
+pub const DEFAULTMAXPAUSE: time::Duration = time::Duration::from_secs(120);
+/// chat messages and opponent news held for a player, further ones are dropped
+const CHATBACKLOG: usize = 8;
+/// connections that sent their handshake, waiting to be placed
+const GREETEDBACKLOG: usize = 16;
+/// messages held for a spectator, one who falls further behind is dropped
+const SPECTATORBACKLOG: usize = 64;
+/// fleets refused in a row before the player forfeits the game
+const MAXFLEETATTEMPTS: u32 = 5;
+
+/// feed of one spectator's connection
+struct Spectator {
+    feed: mpsc::Sender<prot::ServerMessage>,
+    /// gave the admin password, the fleets are revealed to them
+    admin: bool,
+}
+/// connection that sent its first message, not yet placed in a game
+type Greeted = (Connection, prot::ClientMessage);
+/// connection presenting a player's token, with its protocol version
+type Rejoin = (Connection, u8);
+/// reconnection attempts held for a player at once, further ones are refused
+const REJOINBACKLOG: usize = 2;
+/// how often the server logs its metrics, if they changed
+const METRICSINTERVAL: time::Duration = time::Duration::from_secs(60);
+
+impl Config {
+    /// what the server advertises, modes only if this server plays them
+    pub fn capabilities(&self) -> prot::Capabilities {
+        let mut capabilities = prot::Capabilities::supported() - prot::Capabilities::MODES;
+        capabilities.set(prot::Capabilities::SALVO, self.salvo);
+        capabilities.set(prot::Capabilities::PAUSE, !self.maxpause.is_zero());
+        // compact positions imply the classic lengths
+        let custom = self.fleet.lengths() != logic::SHIPLENGTHS;
+        capabilities.set(prot::Capabilities::CUSTOMFLEETS, custom);
+        capabilities.set(prot::Capabilities::COMPACTFLEET, !custom);
+        capabilities
+    }
+
+    /// who the server is and the fleet it plays with, as told to clients
+    pub fn announced(&self) -> prot::ServerInfo {
+        self.info.clone().withfleet(&self.fleet)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            info: prot::ServerInfo::default(),
+            writetimeout: DEFAULTWRITETIMEOUT,
+            readtimeout: DEFAULTREADTIMEOUT,
+            turntimeout: DEFAULTTURNTIMEOUT,
+            maxgames: DEFAULTMAXGAMES,
+            transcriptdir: None,
+            recorddir: None,
+            fleet: logic::FleetSpec::default(),
+            salvo: false,
+            reconnecttimeout: DEFAULTRECONNECTTIMEOUT,
+            chatburst: DEFAULTCHATBURST,
+            chatinterval: DEFAULTCHATINTERVAL,
+            maxpause: DEFAULTMAXPAUSE,
+            adminpassword: None,
+            metrics: sync::Arc::default(),
+        }
+    }
+}
+
+/// counters shared by every game of a server, each rematch counts as a game
+#[derive(Debug, Default)]
+pub struct Metrics {
+    started: atomic::AtomicU64,
+    /// decided by sinking the whole fleet
+    completed: atomic::AtomicU64,
+    /// decided by a player resigning, running out of time or leaving
+    forfeited: atomic::AtomicU64,
+    /// malformed or unexpected messages that ended a connection or a game
+    protocolerrors: atomic::AtomicU64,
+    /// pairs of players currently hosted
+    active: atomic::AtomicU64,
+}
+
+/// counters of a server at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetricsSnapshot {
+    pub started: u64,
+    pub completed: u64,
+    pub forfeited: u64,
+    pub protocolerrors: u64,
+    pub active: u64,
+}
+
+impl Metrics {
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            started: self.started.load(atomic::Ordering::Relaxed),
+            completed: self.completed.load(atomic::Ordering::Relaxed),
+            forfeited: self.forfeited.load(atomic::Ordering::Relaxed),
+            protocolerrors: self.protocolerrors.load(atomic::Ordering::Relaxed),
+            active: self.active.load(atomic::Ordering::Relaxed),
+        }
+    }
+
+    fn bump(counter: &atomic::AtomicU64) {
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+    }
+
+    /// counts `err` unless the peer merely went away or went quiet
+    fn protocolerror(&self, err: &prot::Error) {
+        if !matches!(err, prot::Error::Networking(_) | prot::Error::Timeout) {
+            Metrics::bump(&self.protocolerrors);
+        }
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "games started: {}, completed: {}, forfeited: {}, active: {}, protocol errors: {}",
+            self.started, self.completed, self.forfeited, self.active, self.protocolerrors
+        )
+    }
+}
+
+/// audit log of one game, one JSON object per line
+struct Transcript {
+    /// unix milliseconds, also names the file
+    started: u128,
+    lines: Vec<String>,
+    ended: bool,
+}
+
+impl Transcript {
+    fn new(info: &prot::ServerInfo) -> Transcript {
+        let mut transcript = Transcript {
+            started: unixmillis(),
+            lines: Vec::new(),
+            ended: false,
+        };
+        transcript.push(format!(
+            r#""event":"start","server":{},"version":{}"#,
+            jsonstring(info.name()),
+            jsonstring(info.version()),
+        ));
+        transcript
+    }
+
+    fn push(&mut self, fields: String) {
+        let millis = unixmillis();
+        self.lines.push(format!(r#"{{"time":{millis},{fields}}}"#));
+    }
+
+    fn fleets(&mut self, fleets: [&logic::Ships; 2]) {
+        for (player, ships) in fleets.into_iter().enumerate() {
+            // layout codes imply the classic lengths
+            if logic::FleetSpec::default().matches(ships.asslice()) {
+                self.push(format!(
+                    r#""event":"fleet","player":{player},"code":"{}""#,
+                    ships.tocode()
+                ));
+            } else {
+                self.push(format!(r#""event":"fleet","player":{player}"#));
+            }
+        }
+    }
+
+    fn shot(&mut self, player: u8, target: logic::Position, info: logic::AttackInfo) {
+        let [x, y] = target.toboard();
+        let outcome = match info {
+            logic::AttackInfo::Miss => "miss",
+            logic::AttackInfo::Hit(false) => "hit",
+            logic::AttackInfo::Hit(true) => "sunk",
+        };
+        self.push(format!(
+            r#""event":"shot","player":{player},"target":"{x}{y}","outcome":"{outcome}""#
+        ));
+    }
+
+    fn firstmove(&mut self, player: u8) {
+        self.push(format!(r#""event":"first","player":{player}"#));
+    }
+
+    /// the same players start another game, recorded in the same transcript
+    fn rematch(&mut self) {
+        self.ended = false;
+        self.push(String::from(r#""event":"rematch""#));
+    }
+
+    /// `reason` is `None` for games decided on the board
+    fn end(&mut self, winner: Option<u8>, reason: Option<&str>) {
+        let winner = winner.map_or("null".to_owned(), |winner| winner.to_string());
+        let reason = jsonstring(reason.unwrap_or("fleet sunk"));
+        self.ended = true;
+        self.push(format!(
+            r#""event":"end","winner":{winner},"reason":{reason}"#
+        ));
+    }
+
+    async fn save(self, dir: Option<&path::Path>) {
+        let Some(dir) = dir else {
+            return;
+        };
+        let path = dir.join(format!("game-{}.jsonl", self.started));
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+        if let Err(err) = tokio::fs::write(&path, contents).await {
+            tracing::warn!("error writing transcript {}; {err}", path.display());
+        }
+    }
+}
+
+fn unixmillis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn jsonstring(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// token bucket over the chat one player relays, refilled by one token per
+/// `interval` up to `burst`
+#[derive(Debug)]
+struct ChatLimit {
+    burst: u32,
+    interval: time::Duration,
+    tokens: u32,
+    /// when the last token was earned, or the bucket was last full
+    refilled: time::Instant,
+}
+
+impl ChatLimit {
+    fn new(burst: u32, interval: time::Duration) -> ChatLimit {
+        ChatLimit {
+            burst,
+            interval,
+            tokens: burst,
+            refilled: time::Instant::now(),
+        }
+    }
+
+    /// whether one more message may pass at `now`, using up a token if so
+    fn take(&mut self, now: time::Instant) -> bool {
+        if self.interval.is_zero() {
+            return true;
+        }
+        let elapsed = now.saturating_duration_since(self.refilled);
+        let earned = elapsed.as_nanos() / self.interval.as_nanos();
+        let earned = u32::try_from(earned).unwrap_or(u32::MAX);
+        if earned > 0 {
+            self.tokens = self.tokens.saturating_add(earned).min(self.burst);
+            self.refilled = if self.tokens == self.burst {
+                now
+            } else {
+                self.refilled + self.interval * earned
+            };
+        }
+        match self.tokens.checked_sub(1) {
+            Some(tokens) => {
+                self.tokens = tokens;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 struct Middleware {
-    stream: net::TcpStream,
+    /// the handshake, read before the connection was placed in a game, or
+    /// whatever the player sent while the game waited on someone else
+    pending: Option<Result<prot::ClientMessage, Error>>,
+    info: prot::ServerInfo,
+    /// the name from the opponent's handshake, passed on with this player's
+    oppname: Option<prot::PlayerName>,
+    capabilities: prot::Capabilities,
+    writetimeout: time::Duration,
+    readtimeout: time::Duration,
+    /// a target request ran out of time, its late answer is still to come
+    expired: bool,
+    stream: Connection,
     serverrx: mpsc::Receiver<CommandRequest>,
     clienttx: mpsc::Sender<Result<CommandResult, Error>>,
+    /// chat and news about this player to the opponent's middleware,
+    /// bypassing the instance; only messages that are never answered
+    chattx: mpsc::Sender<prot::ServerMessage>,
+    chatrx: mpsc::Receiver<prot::ServerMessage>,
+    /// throttles the chat this player sends, nothing else
+    chatlimit: ChatLimit,
+    /// handed out with the handshake, 0 if the player cannot come back
+    token: u64,
+    reconnecttimeout: time::Duration,
+    /// connections presenting `token`, each replaces the current one
+    rejoin: mpsc::Receiver<Rejoin>,
+    /// a connection that came in while a message was being read
+    rejoined: Option<Rejoin>,
+    /// the game as this player sees it, to catch them up after a reconnect
+    seat: watch::Receiver<logic::BoardSnapshot>,
+    /// set once this player resigned while waiting, read by the instance
+    resigned: watch::Sender<bool>,
+    /// set once the opponent resigned while waiting, ends this player's turn
+    oppresigned: watch::Receiver<bool>,
+    /// a target request is being answered, resigning is its answer then
+    targeting: bool,
+    /// shared with the opponent's middleware, the game's pause
+    pause: watch::Sender<Pause>,
+    /// 0 for the player who connected first
+    player: u8,
+    maxpause: time::Duration,
+}
+
+/// where a pause between the two players of a game stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pause {
+    Running,
+    /// the player asked for a pause, the opponent has yet to answer
+    Asked(u8),
+    /// both agreed, the game holds until then unless either resumes earlier
+    Held(time::Instant),
+}
+
+impl Pause {
+    /// when the pause ends, `None` unless one holds at `now`
+    fn holds(self, now: time::Instant) -> Option<time::Instant> {
+        match self {
+            Pause::Held(until) if until > now => Some(until),
+            _ => None,
+        }
+    }
+}
+
+/// resolves once `limit` passed, not counting the time a pause held the game
+async fn unpaused(limit: time::Duration, mut pause: watch::Receiver<Pause>) {
+    let mut left = limit;
+    loop {
+        let now = time::Instant::now();
+        let held = pause.borrow_and_update().holds(now);
+        tokio::select! {
+            () = time::sleep_until(held.unwrap_or(now + left)) => {
+                if held.is_none() {
+                    return;
+                }
+            }
+            Ok(()) = pause.changed() => {}
+        }
+        if held.is_none() {
+            left = left.saturating_sub(now.elapsed());
+        }
+    }
+}
+
+/// runs `fut` to the end, taking the time it ran while no pause held the game
+/// off `left`
+async fn clocked<T>(
+    left: &mut time::Duration,
+    pause: &watch::Receiver<Pause>,
+    fut: impl std::future::Future<Output = T>,
+) -> T {
+    let mut pause = pause.clone();
+    let mut fut = pin::pin!(fut);
+    loop {
+        let now = time::Instant::now();
+        let held = pause.borrow_and_update().holds(now);
+        let res = tokio::select! {
+            res = fut.as_mut() => Some(res),
+            () = time::sleep_until(held.unwrap_or(now)), if held.is_some() => None,
+            Ok(()) = pause.changed() => None,
+        };
+        if held.is_none() {
+            *left = left.saturating_sub(now.elapsed());
+        }
+        if let Some(res) = res {
+            return res;
+        }
+    }
 }
 
 impl Middleware {
+    /// a peer that stops answering is treated like one that stopped reading;
+    /// chat for the player is delivered while they think
+    async fn read(&mut self) -> Result<prot::ClientMessage, Error> {
+        let mut clock = pin::pin!(unpaused(self.readtimeout, self.pause.subscribe()));
+        loop {
+            let message = match self.pending.take() {
+                Some(res) => res?,
+                None => self.receive(clock.as_mut()).await?,
+            };
+            // chat may come in between any two messages, best effort
+            if let prot::ClientMessage::Chat(text) = message {
+                self.relay(text);
+                continue;
+            }
+            if let prot::ClientMessage::Pause(ask) = message {
+                self.pausing(ask).await?;
+                continue;
+            }
+            // the answer to a target request that ran out of time
+            if std::mem::take(&mut self.expired)
+                && matches!(
+                    message,
+                    prot::ClientMessage::Target(_)
+                        | prot::ClientMessage::Salvo(_)
+                        | prot::ClientMessage::Surrender
+                        | prot::ClientMessage::RequestResync
+                )
+            {
+                continue;
+            }
+            // resigning while the opponent moves, the game ends on its own
+            if !self.targeting && matches!(message, prot::ClientMessage::Surrender) {
+                self.resigned.send_replace(true);
+                continue;
+            }
+            return Ok(message);
+        }
+    }
+
+    /// `clock` runs out once the player took too long
+    async fn receive(
+        &mut self,
+        mut clock: pin::Pin<&mut impl std::future::Future<Output = ()>>,
+    ) -> Result<prot::ClientMessage, Error> {
+        let mut byte = [0];
+        loop {
+            // the player may come back before this end noticed they left;
+            // peeking, as readiness outlives the data that was read
+            tokio::select! {
+                res = self.stream.peek(&mut byte) => {
+                    res.map_err(|_| Error::Disconnected)?;
+                    break;
+                }
+                Some(message) = self.chatrx.recv() => self.write(message).await?,
+                Some(rejoin) = self.rejoin.recv() => {
+                    self.rejoined = Some(rejoin);
+                    return Err(Error::Disconnected);
+                }
+                () = clock.as_mut() => return Err(Error::TimedOut),
+            }
+        }
+        let message = tokio::select! {
+            message = prot::readmessage(&mut self.stream) => message,
+            () = clock => return Err(Error::TimedOut),
+        };
+        match message {
+            // hung up, mostly an early end of file
+            Err(prot::Error::Networking(_)) => Err(Error::Disconnected),
+            res => Ok(res?),
+        }
+    }
+
+    /// the player asked for or accepted a pause, or declined or ended one if
+    /// not `ask`; the opponent hears of it past the instance, both players
+    /// once a pause holds or ends
+    async fn pausing(&mut self, ask: bool) -> Result<(), Error> {
+        let now = time::Instant::now();
+        let (player, maxpause) = (self.player, self.maxpause);
+        let secs = u16::try_from(maxpause.as_secs()).unwrap_or(u16::MAX).max(1);
+        // the news, and whether it goes to this player and the opponent
+        let mut told = None;
+        self.pause.send_if_modified(|pause| {
+            let (next, news) = match (*pause, ask) {
+                (_, true) if maxpause.is_zero() => {
+                    told = Some((prot::PauseNews::Declined, true, false));
+                    return false;
+                }
+                (held, false) if held.holds(now).is_some() => {
+                    (Pause::Running, (prot::PauseNews::Resumed, true, true))
+                }
+                (held, true) if held.holds(now).is_some() => return false,
+                (Pause::Asked(asker), true) if asker != player => (
+                    Pause::Held(now + maxpause),
+                    (prot::PauseNews::Held(secs), true, true),
+                ),
+                (Pause::Asked(asker), false) if asker != player => {
+                    (Pause::Running, (prot::PauseNews::Declined, false, true))
+                }
+                (Pause::Asked(_), _) | (_, false) => return false,
+                (_, true) => (
+                    Pause::Asked(player),
+                    (prot::PauseNews::Offered, false, true),
+                ),
+            };
+            *pause = next;
+            told = Some(news);
+            true
+        });
+        let Some((news, own, opponent)) = told else {
+            return Ok(());
+        };
+        if opponent {
+            let _ = self.chattx.try_send(prot::ServerMessage::Pause(news));
+        }
+        if own {
+            self.write(prot::ServerMessage::Pause(news)).await?;
+        }
+        Ok(())
+    }
+
+    /// passes chat on to the opponent, dropped if they are far behind or
+    /// this player sends too much of it
+    fn relay(&mut self, text: String) {
+        if !self.chatlimit.take(time::Instant::now()) {
+            tracing::debug!("chat throttled");
+            return;
+        }
+        let _ = self.chattx.try_send(prot::ServerMessage::Chat(text));
+    }
+
+    /// a peer that stops reading stalls the write, treat it as gone; chat
+    /// waiting for the player goes out first
+    async fn send(&mut self, message: prot::ServerMessage) -> Result<(), Error> {
+        while let Ok(message) = self.chatrx.try_recv() {
+            self.write(message).await?;
+        }
+        self.write(message).await
+    }
+
+    async fn write(&mut self, message: prot::ServerMessage) -> Result<(), Error> {
+        match time::timeout(
+            self.writetimeout,
+            prot::sendmessage(&mut self.stream, message),
+        )
+        .await
+        {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(prot::Error::Networking(_))) => Err(Error::Disconnected),
+            Ok(Err(err)) => Err(err.into()),
+            Err(_) => Err(Error::TimedOut),
+        }
+    }
+
     async fn handlecmd(&mut self, cmd: CommandRequest) -> Result<CommandResult, Error> {
         match cmd {
-            CommandRequest::Handshake => match prot::readmessage(&mut self.stream).await? {
-                prot::ClientMessage::Handshake => {
-                    prot::sendmessage(&mut self.stream, prot::ServerMessage::Handshake).await?;
-                    Ok(CommandResult::Success)
+            CommandRequest::Handshake => match self.read().await? {
+                prot::ClientMessage::Handshake(version, ..) if version != prot::PROTOCOLVERSION => {
+                    self.send(prot::ServerMessage::VersionMismatch(prot::PROTOCOLVERSION))
+                        .await?;
+                    Err(prot::Error::VersionMismatch {
+                        ours: prot::PROTOCOLVERSION,
+                        theirs: version,
+                    }
+                    .into())
+                }
+                prot::ClientMessage::Handshake(..) => {
+                    self.send(prot::ServerMessage::Handshake(
+                        self.info.clone(),
+                        self.token,
+                        self.oppname.clone(),
+                    ))
+                    .await?;
+
+                    match self.read().await? {
+                        prot::ClientMessage::QueryCapabilities => {
+                            self.send(prot::ServerMessage::Capabilities(self.capabilities))
+                                .await?;
+                            Ok(CommandResult::Success)
+                        }
+                        _ => Ok(CommandResult::Invalid),
+                    }
                 }
                 _ => Ok(CommandResult::Invalid),
             },
             CommandRequest::RequestShips => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::RequestShipPositions)
-                    .await?;
+                // a resignation or pause does not carry over into a rematch
+                self.resigned.send_replace(false);
+                self.pause.send_replace(Pause::Running);
+                self.send(prot::ServerMessage::RequestShipPositions).await?;
 
-                match prot::readmessage(&mut self.stream).await? {
-                    prot::ClientMessage::ShipPositions(ships) => Ok(CommandResult::GetShips(ships)),
-                    _ => Ok(CommandResult::Invalid),
+                match self.read().await {
+                    Ok(
+                        prot::ClientMessage::ShipPositions(ships)
+                        | prot::ClientMessage::CompactShipPositions(ships),
+                    ) => Ok(CommandResult::GetShips(ships)),
+                    Ok(_) => Ok(CommandResult::Invalid),
+                    Err(Error::Protocol(prot::Error::InvalidFleet(reason))) => {
+                        Ok(CommandResult::InvalidShips(reason))
+                    }
+                    Err(err) => Err(err),
                 }
             }
-            CommandRequest::RequestTarget => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::RequestTarget).await?;
+            CommandRequest::RequestTarget(limit) | CommandRequest::RequestSalvo(_, limit) => {
+                let secs = u16::try_from(limit.as_secs()).unwrap_or(u16::MAX);
+                self.send(match cmd {
+                    CommandRequest::RequestSalvo(shots, _) => {
+                        prot::ServerMessage::RequestSalvo(shots, secs)
+                    }
+                    _ => prot::ServerMessage::RequestTarget(Some(secs)),
+                })
+                .await?;
 
-                match prot::readmessage(&mut self.stream).await? {
+                let mut oppresigned = self.oppresigned.clone();
+                let clock = unpaused(limit, self.pause.subscribe());
+                self.targeting = true;
+                let message = tokio::select! {
+                    message = self.read() => Some(message),
+                    () = clock => None,
+                    // the opponent conceded meanwhile, the turn is over
+                    Ok(_) = oppresigned.wait_for(|&resigned| resigned) => None,
+                };
+                self.targeting = false;
+                let Some(message) = message else {
+                    self.expired = true;
+                    return Ok(CommandResult::Expired);
+                };
+                let message = message?;
+                // firing or resigning ends a pause, the game goes on
+                if self.pause.borrow().holds(time::Instant::now()).is_some() {
+                    self.pausing(false).await?;
+                }
+                match message {
                     prot::ClientMessage::Target(pos) => Ok(CommandResult::GetTarget(pos)),
+                    prot::ClientMessage::Salvo(targets) => Ok(CommandResult::GetSalvo(targets)),
+                    prot::ClientMessage::RequestResync => Ok(CommandResult::Resync),
+                    prot::ClientMessage::Surrender => Ok(CommandResult::Surrender),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
+            CommandRequest::InformInvalid => {
+                self.send(prot::ServerMessage::Invalid).await?;
+
+                match self.read().await? {
+                    prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
+            CommandRequest::InformSnapshot(snapshot) => {
+                self.send(prot::ServerMessage::Snapshot(*snapshot)).await?;
+
+                match self.read().await? {
+                    prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
+            CommandRequest::InformInvalidShips(reason) => {
+                self.send(prot::ServerMessage::InvalidShips(reason)).await?;
+
+                match self.read().await? {
+                    prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformTargetSelection => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::InformTargetSelection)
+                self.send(prot::ServerMessage::InformTargetSelection)
                     .await?;
 
-                match prot::readmessage(&mut self.stream).await? {
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformTargetHitYou(pos, sunken) => {
-                prot::sendmessage(
-                    &mut self.stream,
-                    prot::ServerMessage::InformTargetHitYou(pos, sunken),
-                )
-                .await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformTargetHitYou(pos, sunken))
+                    .await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformTargetHitOpp(pos, sunken) => {
-                prot::sendmessage(
-                    &mut self.stream,
-                    prot::ServerMessage::InformTargetHitOpp(pos, sunken),
-                )
-                .await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformTargetHitOpp(pos, sunken))
+                    .await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformTargetMissYou(pos) => {
-                prot::sendmessage(
-                    &mut self.stream,
-                    prot::ServerMessage::InformTargetMissYou(pos),
-                )
-                .await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformTargetMissYou(pos))
+                    .await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformTargetMissOpp(pos) => {
-                prot::sendmessage(
-                    &mut self.stream,
-                    prot::ServerMessage::InformTargetMissOpp(pos),
-                )
-                .await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformTargetMissOpp(pos))
+                    .await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformVictory => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::InformVictory).await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformVictory).await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
             CommandRequest::InformLoss => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::InformLoss).await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::InformLoss).await?;
+                match self.read().await? {
+                    prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
+            CommandRequest::InformGameSummary(summary) => {
+                self.send(prot::ServerMessage::GameSummary(summary)).await?;
+                match self.read().await? {
+                    prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
+            CommandRequest::InformOpponentLeft(reason) => {
+                self.send(prot::ServerMessage::OpponentLeft(reason)).await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
             }
+            CommandRequest::OfferRematch => {
+                self.send(prot::ServerMessage::OfferRematch).await?;
+                match self.read().await? {
+                    prot::ClientMessage::Rematch(accepted) => Ok(CommandResult::Rematch(accepted)),
+                    _ => Ok(CommandResult::Invalid),
+                }
+            }
             CommandRequest::TerminateConnection => {
-                prot::sendmessage(&mut self.stream, prot::ServerMessage::TerminateConnection)
-                    .await?;
-                match prot::readmessage(&mut self.stream).await? {
+                self.send(prot::ServerMessage::TerminateConnection).await?;
+                match self.read().await? {
                     prot::ClientMessage::Acknowledge => Ok(CommandResult::Success),
                     _ => Ok(CommandResult::Invalid),
                 }
@@ -151,67 +892,308 @@ impl Middleware {
         }
     }
 
+    /// waits for the player to come back with their token and catches them
+    /// up; `false` once `reconnecttimeout` passed without them
+    async fn resume(&mut self) -> bool {
+        let deadline = time::Instant::now() + self.reconnecttimeout;
+        let secs = u16::try_from(self.reconnecttimeout.as_secs()).unwrap_or(u16::MAX);
+        // best effort, the opponent learns the outcome from the game anyway
+        let _ = self
+            .chattx
+            .try_send(prot::ServerMessage::OpponentAway(secs.max(1)));
+        loop {
+            let rejoin = match self.rejoined.take() {
+                Some(rejoin) => Some(rejoin),
+                None => time::timeout_at(deadline, self.rejoin.recv())
+                    .await
+                    .ok()
+                    .flatten(),
+            };
+            let Some((stream, version)) = rejoin else {
+                return false;
+            };
+            // whatever was in flight on the old connection is asked again
+            self.stream = stream;
+            self.pending = None;
+            self.expired = false;
+            if version != prot::PROTOCOLVERSION {
+                let _ = self
+                    .write(prot::ServerMessage::VersionMismatch(prot::PROTOCOLVERSION))
+                    .await;
+                continue;
+            }
+            match self.catchup().await {
+                Ok(()) => {
+                    tracing::info!("player reconnected");
+                    let _ = self.chattx.try_send(prot::ServerMessage::OpponentAway(0));
+                    return true;
+                }
+                Err(err) => tracing::info!("error resuming after a reconnect; {err}"),
+            }
+        }
+    }
+
+    async fn catchup(&mut self) -> Result<(), Error> {
+        self.write(prot::ServerMessage::Handshake(
+            self.info.clone(),
+            self.token,
+            self.oppname.clone(),
+        ))
+        .await?;
+        let snapshot = *self.seat.borrow();
+        self.write(prot::ServerMessage::Snapshot(snapshot)).await?;
+        match self.read().await? {
+            prot::ClientMessage::Acknowledge => Ok(()),
+            _ => Err(Error::Disconnected),
+        }
+    }
+
     async fn run(mut self) {
-        while let Some(cmd) = self.serverrx.recv().await {
-            let cmdres = self.handlecmd(cmd).await;
+        let mut byte = [0];
+        loop {
+            let cmd = tokio::select! {
+                cmd = self.serverrx.recv() => match cmd {
+                    Some(cmd) => cmd,
+                    None => break,
+                },
+                // delivered right away while the player waits for the opponent
+                Some(message) = self.chatrx.recv() => {
+                    let _ = self.write(message).await;
+                    continue;
+                }
+                // and theirs passed on just as soon, as is resigning; anything
+                // else, or the connection failing, waits for the next request
+                Ok(_) = self.stream.peek(&mut byte), if self.pending.is_none() => {
+                    let clock = pin::pin!(unpaused(self.readtimeout, self.pause.subscribe()));
+                    match self.receive(clock).await {
+                        Ok(prot::ClientMessage::Chat(text)) => self.relay(text),
+                        Ok(prot::ClientMessage::Pause(ask)) => {
+                            if let Err(err) = self.pausing(ask).await {
+                                self.pending = Some(Err(err));
+                            }
+                        }
+                        Ok(prot::ClientMessage::Surrender) if !self.expired => {
+                            self.resigned.send_replace(true);
+                        }
+                        res => self.pending = Some(res),
+                    }
+                    continue;
+                }
+                // back while the game waits on the opponent
+                Some(rejoin) = self.rejoin.recv() => {
+                    self.rejoined = Some(rejoin);
+                    self.resume().await;
+                    continue;
+                }
+            };
+            let mut cmdres = self.handlecmd(cmd.clone()).await;
+            // the game holds still while a dropped player finds their way back,
+            // then the request they missed is repeated
+            while self.token != 0
+                && !matches!(cmd, CommandRequest::Handshake)
+                && cmdres.as_ref().is_err_and(Error::dropped)
+                && self.resume().await
+            {
+                cmdres = self.handlecmd(cmd.clone()).await;
+            }
+            let dropped = cmdres.as_ref().is_err_and(Error::dropped);
+            if dropped {
+                // closed before the error is reported, so the instance knows
+                // which player is gone and stops sending
+                self.serverrx.close();
+            }
             let _ = self.clienttx.send(cmdres).await;
+            if dropped {
+                break;
+            }
         }
     }
 }
 
-pub struct Instance {
+/// where a played turn left the game
+enum Outcome {
+    Continue,
+    /// both players were told, another game may follow
+    Decided,
+    /// the player to move ran out of time, both connections are closed
+    Expired,
+}
+
+pub struct Instance<'t> {
     turn: u8,
+    /// player moving first in the current game, alternates between rematches
+    first: u8,
+    /// targets selected in the current game, a whole salvo counts once
+    turns: u16,
     boards: [logic::Board; 2],
     senders: [mpsc::Sender<CommandRequest>; 2],
     receivers: [mpsc::Receiver<Result<CommandResult, Error>>; 2],
+    transcript: &'t mut Transcript,
+    /// time a player has to select a target
+    turntimeout: time::Duration,
+    /// one shot per own ship afloat each turn
+    salvo: bool,
+    /// checked again for every rematch
+    fleet: logic::FleetSpec,
+    spectators: Vec<Spectator>,
+    /// spectators waiting to be seated at the next turn
+    joining: mpsc::Receiver<Spectator>,
+    /// the current game, saved once it ends
+    record: prot::GameRecord,
+    recorddir: Option<path::PathBuf>,
+    metrics: sync::Arc<Metrics>,
+    /// each player's view, read by their middleware after a reconnect
+    seats: [watch::Sender<logic::BoardSnapshot>; 2],
+    /// whether each player resigned while the other was to move
+    resigned: [watch::Receiver<bool>; 2],
+    /// stops the turn clock while both players agreed to pause
+    pause: watch::Receiver<Pause>,
 }
 
-impl Instance {
+impl Instance<'_> {
+    /// `rng` flips the coin for who moves first, a seeded one makes the
+    /// outcome reproducible
+    #[allow(clippy::too_many_arguments)]
     async fn run(
+        senders: [mpsc::Sender<CommandRequest>; 2],
+        receivers: [mpsc::Receiver<Result<CommandResult, Error>>; 2],
+        joining: mpsc::Receiver<Spectator>,
+        seats: [watch::Sender<logic::BoardSnapshot>; 2],
+        resigned: [watch::Receiver<bool>; 2],
+        pause: watch::Receiver<Pause>,
+        config: Config,
+        mut rng: impl Rng,
+    ) -> Result<(), Error> {
+        let first = rng.random_range(0..2);
+        let mut transcript = Transcript::new(&config.info);
+        let res = Instance::start(
+            senders,
+            receivers,
+            joining,
+            seats,
+            resigned,
+            pause,
+            &mut transcript,
+            &config,
+            first,
+        )
+        .await;
+        if let (Err(err), false) = (&res, transcript.ended) {
+            transcript.end(None, Some(&err.to_string()));
+        }
+        transcript.save(config.transcriptdir.as_deref()).await;
+        res
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
         mut senders: [mpsc::Sender<CommandRequest>; 2],
         mut receivers: [mpsc::Receiver<Result<CommandResult, Error>>; 2],
+        joining: mpsc::Receiver<Spectator>,
+        seats: [watch::Sender<logic::BoardSnapshot>; 2],
+        resigned: [watch::Receiver<bool>; 2],
+        pause: watch::Receiver<Pause>,
+        transcript: &mut Transcript,
+        config: &Config,
+        first: u8,
     ) -> Result<(), Error> {
         for sender in &senders {
-            sender.send(CommandRequest::Handshake).await.unwrap();
+            sender
+                .send(CommandRequest::Handshake)
+                .await
+                .map_err(|_| Error::Disconnected)?;
         }
 
         for receiver in &mut receivers {
-            if matches!(receiver.recv().await.unwrap()?, CommandResult::Invalid) {
+            if matches!(
+                receiver.recv().await.ok_or(Error::Disconnected)??,
+                CommandResult::Invalid
+            ) {
                 return Err(prot::Error::UnsuccessfulHandshake.into());
             }
         }
 
-        let [rx1, rx2] = &mut receivers;
-        let [tx1, tx2] = &mut senders;
-
-        let (ship1, ship2) =
-            tokio::join!(Instance::getships(tx1, rx1), Instance::getships(tx2, rx2),);
+        let boards = Instance::placeships(
+            &mut senders,
+            &mut receivers,
+            transcript,
+            &config.fleet,
+            &config.metrics,
+        )
+        .await?;
+        let record = prot::GameRecord::new(boards.each_ref().map(|board| *board.ships()));
 
+        transcript.firstmove(first);
         Instance {
-            turn: 0,
-            boards: [logic::Board::new(ship1?), logic::Board::new(ship2?)],
+            turn: first,
+            first,
+            turns: 0,
+            boards,
             senders,
             receivers,
+            transcript,
+            turntimeout: config.turntimeout,
+            salvo: config.salvo,
+            fleet: config.fleet.clone(),
+            spectators: Vec::new(),
+            joining,
+            record,
+            recorddir: config.recorddir.clone(),
+            metrics: sync::Arc::clone(&config.metrics),
+            seats,
+            resigned,
+            pause,
         }
         .play()
         .await
     }
 
+    /// asks both players for their ships at once; a player leaving meanwhile
+    /// hands the game to the other
+    async fn placeships(
+        senders: &mut [mpsc::Sender<CommandRequest>; 2],
+        receivers: &mut [mpsc::Receiver<Result<CommandResult, Error>>; 2],
+        transcript: &mut Transcript,
+        fleet: &logic::FleetSpec,
+        metrics: &Metrics,
+    ) -> Result<[logic::Board; 2], Error> {
+        Metrics::bump(&metrics.started);
+        let [rx1, rx2] = &mut *receivers;
+        let [tx1, tx2] = &mut *senders;
+
+        let (loser, err) = match tokio::join!(
+            Instance::getships(tx1, rx1, fleet),
+            Instance::getships(tx2, rx2, fleet),
+        ) {
+            (Ok(ship1), Ok(ship2)) => {
+                transcript.fleets([&ship1, &ship2]);
+                return Ok([logic::Board::new(ship1), logic::Board::new(ship2)]);
+            }
+            (Err(err), _) => (0, err),
+            (_, Err(err)) => (1, err),
+        };
+        // a player out of attempts is still connected, the seat decides
+        let forfeit = match err {
+            Error::Logic(_) => Some((prot::LeaveReason::Abandoned, Some(loser))),
+            ref err => err.leavereason(false).map(|reason| (reason, None)),
+        };
+        if let Some((reason, loser)) = forfeit {
+            Instance::forfeit(senders, receivers, reason, loser, transcript, metrics).await;
+        }
+        Err(err)
+    }
+
     async fn gettarget(
         txplayer: &mut mpsc::Sender<CommandRequest>,
         txopp: &mut mpsc::Sender<CommandRequest>,
         rxplayer: &mut mpsc::Receiver<Result<CommandResult, Error>>,
         rxopp: &mut mpsc::Receiver<Result<CommandResult, Error>>,
-    ) -> Result<logic::Position, Error> {
+        snapshot: &logic::BoardSnapshot,
+        left: &mut time::Duration,
+        pause: &watch::Receiver<Pause>,
+    ) -> Result<Option<logic::Position>, Error> {
         let (target, acknowledged) = tokio::join!(
-            async {
-                txplayer.send(CommandRequest::RequestTarget).await.unwrap();
-                let res = rxplayer.recv().await.unwrap()?;
-                match res {
-                    CommandResult::GetTarget(target) => Ok(target),
-                    other => Err(Error::Middleware(CommandRequest::RequestShips, other)),
-                }
-            },
+            Instance::requesttarget(txplayer, rxplayer, snapshot, left, pause),
             Instance::informmw(rxopp, txopp, CommandRequest::InformTargetSelection)
         );
 
@@ -219,112 +1201,617 @@ impl Instance {
         target
     }
 
-    async fn getships(
+    /// a resync answers with `snapshot` and asks again; `left` is the time
+    /// left in the turn, asking again does not start it over; `None` once the
+    /// player ran out of time, `Error::Surrendered` if they conceded instead
+    async fn requesttarget(
         tx: &mut mpsc::Sender<CommandRequest>,
         rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
-    ) -> Result<logic::Ships, Error> {
-        {
-            tx.send(CommandRequest::RequestShips).await.unwrap();
-            match rx.recv().await.unwrap()? {
-                CommandResult::GetShips(ships) => Ok(ships),
-                other => Err(Error::Middleware(CommandRequest::RequestShips, other)),
+        snapshot: &logic::BoardSnapshot,
+        left: &mut time::Duration,
+        pause: &watch::Receiver<Pause>,
+    ) -> Result<Option<logic::Position>, Error> {
+        loop {
+            let limit = *left;
+            let res = clocked(left, pause, async {
+                tx.send(CommandRequest::RequestTarget(limit))
+                    .await
+                    .map_err(|_| Error::Disconnected)?;
+                rx.recv().await.ok_or(Error::Disconnected)?
+            })
+            .await?;
+            match res {
+                CommandResult::GetTarget(target) => return Ok(Some(target)),
+                CommandResult::Expired => return Ok(None),
+                CommandResult::Surrender => return Err(Error::Surrendered),
+                CommandResult::Resync => {
+                    let resync = CommandRequest::InformSnapshot(Box::new(*snapshot));
+                    clocked(left, pause, Instance::informmw(rx, tx, resync)).await?
+                }
+                other => {
+                    return Err(Error::Middleware(
+                        CommandRequest::RequestTarget(limit),
+                        other,
+                    ))
+                }
             }
         }
     }
 
-    fn getplayeropppair<T>(turn: u8, arr: &mut [T; 2]) -> (&mut T, &mut T) {
-        let [elem1, elem2] = arr;
-        if turn % 2 == 0 {
-            (elem1, elem2)
-        } else {
-            (elem2, elem1)
+    /// `requesttarget` for a salvo of `shots` targets
+    async fn requestsalvo(
+        tx: &mut mpsc::Sender<CommandRequest>,
+        rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+        snapshot: &logic::BoardSnapshot,
+        shots: u8,
+        left: &mut time::Duration,
+        pause: &watch::Receiver<Pause>,
+    ) -> Result<Option<Vec<logic::Position>>, Error> {
+        loop {
+            let limit = *left;
+            let res = clocked(left, pause, async {
+                tx.send(CommandRequest::RequestSalvo(shots, limit))
+                    .await
+                    .map_err(|_| Error::Disconnected)?;
+                rx.recv().await.ok_or(Error::Disconnected)?
+            })
+            .await?;
+            match res {
+                CommandResult::GetSalvo(targets) => return Ok(Some(targets)),
+                CommandResult::Expired => return Ok(None),
+                CommandResult::Surrender => return Err(Error::Surrendered),
+                CommandResult::Resync => {
+                    let resync = CommandRequest::InformSnapshot(Box::new(*snapshot));
+                    clocked(left, pause, Instance::informmw(rx, tx, resync)).await?
+                }
+                other => {
+                    return Err(Error::Middleware(
+                        CommandRequest::RequestSalvo(shots, limit),
+                        other,
+                    ))
+                }
+            }
         }
     }
 
-    async fn informmw(
-        rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+    async fn getships(
         tx: &mut mpsc::Sender<CommandRequest>,
-        cmd: CommandRequest,
-    ) -> Result<(), Error> {
-        tx.send(cmd.clone()).await.unwrap();
-        let res = rx.recv().await.unwrap()?;
-        match res {
-            CommandResult::Success => Ok(()),
-            other => Err(Error::Middleware(cmd, other)),
+        rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+        fleet: &logic::FleetSpec,
+    ) -> Result<logic::Ships, Error> {
+        let mut attempts = 0;
+        loop {
+            tx.send(CommandRequest::RequestShips)
+                .await
+                .map_err(|_| Error::Disconnected)?;
+            let reason = match rx.recv().await.ok_or(Error::Disconnected)?? {
+                // parsing only checks for overlaps, never trust that it
+                // ran; the fleet is only checked here
+                CommandResult::GetShips(ships) => {
+                    match logic::Ships::validate(ships.asslice(), fleet) {
+                        Ok(ships) => return Ok(ships),
+                        Err(reason) => reason,
+                    }
+                }
+                CommandResult::InvalidShips(reason) => reason,
+                other => return Err(Error::Middleware(CommandRequest::RequestShips, other)),
+            };
+            // tell the player why and ask again, until they run out of attempts
+            Instance::informmw(rx, tx, CommandRequest::InformInvalidShips(reason)).await?;
+            attempts += 1;
+            if attempts == MAXFLEETATTEMPTS {
+                return Err(Error::Logic(reason));
+            }
         }
     }
 
-    async fn playturn(&mut self) -> Result<bool, Error> {
-        let (_boardplayer, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
-        let (rxplayer, rxopp) = Instance::getplayeropppair(self.turn, &mut self.receivers);
-        let (txplayer, txopp) = Instance::getplayeropppair(self.turn, &mut self.senders);
+    /// both public grids as the first player sees them, spectators watch
+    /// from their seat
+    fn spectatorsnapshot(&self) -> logic::BoardSnapshot {
+        let [board1, board2] = &self.boards;
+        logic::BoardSnapshot {
+            selfhits: board1.hitgrid(),
+            opphits: board2.hitgrid(),
+            yourturn: self.turn.is_multiple_of(2),
+        }
+    }
 
-        let target = Instance::gettarget(txplayer, txopp, rxplayer, rxopp).await?;
-        let info = match boardopp.target(target) {
-            Some(info) => info,
-            None => return Err(Error::Logic(logic::Error::OccupiedTargetPosition)),
-        };
+    /// updates what each player is shown should they reconnect
+    fn publish(&self) {
+        let [board1, board2] = &self.boards;
+        let [seat1, seat2] = &self.seats;
+        seat1.send_replace(logic::BoardSnapshot {
+            selfhits: board1.hitgrid(),
+            opphits: board2.hitgrid(),
+            yourturn: self.turn.is_multiple_of(2),
+        });
+        seat2.send_replace(logic::BoardSnapshot {
+            selfhits: board2.hitgrid(),
+            opphits: board1.hitgrid(),
+            yourturn: !self.turn.is_multiple_of(2),
+        });
+    }
+
+    fn fleets(&self) -> prot::ServerMessage {
+        prot::ServerMessage::Fleets(self.boards.each_ref().map(|board| *board.ships()))
+    }
+
+    /// catches up spectators who joined since the last turn
+    fn seatspectators(&mut self) {
+        while let Ok(spectator) = self.joining.try_recv() {
+            let snapshot = prot::ServerMessage::Snapshot(self.spectatorsnapshot());
+            let seated = spectator.feed.try_send(snapshot).is_ok()
+                && (!spectator.admin || spectator.feed.try_send(self.fleets()).is_ok());
+            if seated {
+                self.spectators.push(spectator);
+            }
+        }
+    }
+
+    /// best effort, a spectator who cannot keep up is dropped
+    fn broadcast(&mut self, message: prot::ServerMessage) {
+        self.spectators
+            .retain(|spectator| spectator.feed.try_send(message.clone()).is_ok());
+    }
+
+    /// shows the fleets of a new game to the admin spectators
+    fn reveal(&mut self) {
+        let fleets = self.fleets();
+        self.spectators.retain(|spectator| {
+            !spectator.admin || spectator.feed.try_send(fleets.clone()).is_ok()
+        });
+    }
+
+    /// tells spectators who won, `winner` is 0 for the first player
+    fn broadcastend(&mut self, winner: u8, summary: logic::GameSummary) {
+        self.broadcast(if winner == 0 {
+            prot::ServerMessage::InformVictory
+        } else {
+            prot::ServerMessage::InformLoss
+        });
+        self.broadcast(prot::ServerMessage::GameSummary(summary));
+    }
+
+    fn getplayeropppair<T>(turn: u8, arr: &mut [T; 2]) -> (&mut T, &mut T) {
+        let [elem1, elem2] = arr;
+        if turn.is_multiple_of(2) {
+            (elem1, elem2)
+        } else {
+            (elem2, elem1)
+        }
+    }
+
+    async fn informmw(
+        rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+        tx: &mut mpsc::Sender<CommandRequest>,
+        cmd: CommandRequest,
+    ) -> Result<(), Error> {
+        tx.send(cmd.clone())
+            .await
+            .map_err(|_| Error::Disconnected)?;
+        let res = rx.recv().await.ok_or(Error::Disconnected)??;
+        match res {
+            CommandResult::Success => Ok(()),
+            other => Err(Error::Middleware(cmd, other)),
+        }
+    }
+
+    /// hands both commands over shooter first, so every player sees the
+    /// same order, then waits for both acknowledgments concurrently
+    async fn informboth(
+        rxplayer: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+        txplayer: &mut mpsc::Sender<CommandRequest>,
+        cmdplayer: CommandRequest,
+        rxopp: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+        txopp: &mut mpsc::Sender<CommandRequest>,
+        cmdopp: CommandRequest,
+    ) -> Result<(), Error> {
+        txplayer
+            .send(cmdplayer.clone())
+            .await
+            .map_err(|_| Error::Disconnected)?;
+        txopp
+            .send(cmdopp.clone())
+            .await
+            .map_err(|_| Error::Disconnected)?;
+        let (resplayer, resopp) = tokio::join!(rxplayer.recv(), rxopp.recv());
+        for (res, cmd) in [(resplayer, cmdplayer), (resopp, cmdopp)] {
+            match res.ok_or(Error::Disconnected)?? {
+                CommandResult::Success => {}
+                other => return Err(Error::Middleware(cmd, other)),
+            }
+        }
+        Ok(())
+    }
+
+    async fn playturn(&mut self) -> Result<Outcome, Error> {
+        if self.resignedwaiting().await? {
+            return Ok(Outcome::Decided);
+        }
+        self.seatspectators();
+        self.publish();
+        let (boardplayer, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
+        let (rxplayer, rxopp) = Instance::getplayeropppair(self.turn, &mut self.receivers);
+        let (txplayer, txopp) = Instance::getplayeropppair(self.turn, &mut self.senders);
+
+        let snapshot = logic::BoardSnapshot {
+            selfhits: boardplayer.hitgrid(),
+            opphits: boardopp.hitgrid(),
+            yourturn: true,
+        };
+        // one clock for the whole turn, rejected targets do not wind it back
+        let mut left = self.turntimeout;
+        let pause = &self.pause;
+        let mut target = Instance::gettarget(
+            txplayer, txopp, rxplayer, rxopp, &snapshot, &mut left, pause,
+        )
+        .await?;
+        // already fired at; reject and ask the same player again
+        let (target, info) = loop {
+            let Some(pos) = target else {
+                if self.resignedwaiting().await? {
+                    return Ok(Outcome::Decided);
+                }
+                self.concede(self.turn % 2, prot::LeaveReason::Timeout)
+                    .await?;
+                return Ok(Outcome::Expired);
+            };
+            match boardopp.target(pos) {
+                Some(info) => break (pos, info),
+                None => {
+                    let invalid =
+                        Instance::informmw(rxplayer, txplayer, CommandRequest::InformInvalid);
+                    clocked(&mut left, pause, invalid).await?;
+                    target =
+                        Instance::requesttarget(txplayer, rxplayer, &snapshot, &mut left, pause)
+                            .await?;
+                }
+            }
+        };
+        self.turns += 1;
+        self.report(target, info).await?;
         match info {
             logic::AttackInfo::Miss => {
-                let (success1, success2) = tokio::join!(
-                    Instance::informmw(
-                        rxplayer,
-                        txplayer,
-                        CommandRequest::InformTargetMissOpp(target)
-                    ),
-                    Instance::informmw(rxopp, txopp, CommandRequest::InformTargetMissYou(target)),
-                );
-                success1?;
-                success2?;
                 self.turn += 1;
-                Ok(true)
-            }
-            logic::AttackInfo::Hit(sunken) => {
-                let (success1, success2) = tokio::join!(
-                    Instance::informmw(
-                        rxplayer,
-                        txplayer,
-                        CommandRequest::InformTargetHitOpp(target, sunken)
-                    ),
-                    Instance::informmw(
-                        rxopp,
-                        txopp,
-                        CommandRequest::InformTargetHitYou(target, sunken)
-                    ),
-                );
-                success1?;
-                success2?;
-
+                Ok(Outcome::Continue)
+            }
+            logic::AttackInfo::Hit(_) => {
+                let (_, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
                 if boardopp.allsunken() {
-                    let (success1, success2) = tokio::join!(
-                        Instance::informmw(rxplayer, txplayer, CommandRequest::InformVictory),
-                        Instance::informmw(rxopp, txopp, CommandRequest::InformLoss),
-                    );
-                    success1?;
-                    success2?;
-
-                    let (success1, success2) = tokio::join!(
-                        Instance::informmw(rxplayer, txplayer, CommandRequest::TerminateConnection),
-                        Instance::informmw(rxopp, txopp, CommandRequest::TerminateConnection),
-                    );
-                    success1?;
-                    success2?;
-                    Ok(false)
+                    self.win().await?;
+                    Ok(Outcome::Decided)
                 } else {
-                    Ok(true)
+                    Ok(Outcome::Continue)
                 }
             }
         }
     }
 
+    /// fires one shot per ship the player has afloat, the turn passes
+    /// whether they hit or not
+    async fn playsalvo(&mut self) -> Result<Outcome, Error> {
+        if self.resignedwaiting().await? {
+            return Ok(Outcome::Decided);
+        }
+        self.seatspectators();
+        self.publish();
+        let (boardplayer, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
+        let (rxplayer, rxopp) = Instance::getplayeropppair(self.turn, &mut self.receivers);
+        let (txplayer, txopp) = Instance::getplayeropppair(self.turn, &mut self.senders);
+
+        let snapshot = logic::BoardSnapshot {
+            selfhits: boardplayer.hitgrid(),
+            opphits: boardopp.hitgrid(),
+            yourturn: true,
+        };
+        // never more shots than cells left to fire at
+        let shots = boardplayer
+            .remainingships()
+            .min(logic::legaltargets(&snapshot.opphits).len() as u8);
+        let mut left = self.turntimeout;
+        let pause = &self.pause;
+        let (targets, acknowledged) = tokio::join!(
+            Instance::requestsalvo(txplayer, rxplayer, &snapshot, shots, &mut left, pause),
+            Instance::informmw(rxopp, txopp, CommandRequest::InformTargetSelection)
+        );
+        acknowledged?;
+        let mut targets = targets?;
+        // one target already fired at or named twice rejects the whole salvo
+        let targets = loop {
+            let Some(salvo) = targets else {
+                if self.resignedwaiting().await? {
+                    return Ok(Outcome::Decided);
+                }
+                self.concede(self.turn % 2, prot::LeaveReason::Timeout)
+                    .await?;
+                return Ok(Outcome::Expired);
+            };
+            let mut named = logic::PositionSet::new();
+            if salvo.len() == shots as usize
+                && salvo
+                    .iter()
+                    .all(|&pos| named.insert(pos) && snapshot.opphits.get(pos).is_none())
+            {
+                break salvo;
+            }
+            let invalid = Instance::informmw(rxplayer, txplayer, CommandRequest::InformInvalid);
+            clocked(&mut left, pause, invalid).await?;
+            targets =
+                Instance::requestsalvo(txplayer, rxplayer, &snapshot, shots, &mut left, pause)
+                    .await?;
+        };
+
+        self.turns += 1;
+        for target in targets {
+            let (_, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
+            let Some(info) = boardopp.target(target) else {
+                continue;
+            };
+            self.report(target, info).await?;
+        }
+        let (_, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
+        if boardopp.allsunken() {
+            self.win().await?;
+            Ok(Outcome::Decided)
+        } else {
+            self.turn += 1;
+            Ok(Outcome::Continue)
+        }
+    }
+
+    /// records a resolved shot and tells both players where it landed
+    async fn report(
+        &mut self,
+        target: logic::Position,
+        info: logic::AttackInfo,
+    ) -> Result<(), Error> {
+        let (_, boardopp) = Instance::getplayeropppair(self.turn, &mut self.boards);
+        let sunken = boardopp.sunkenship(target).map(|(_, len)| len);
+        let seat = self.turn.is_multiple_of(2);
+        self.broadcast(match info {
+            logic::AttackInfo::Miss if seat => prot::ServerMessage::InformTargetMissOpp(target),
+            logic::AttackInfo::Miss => prot::ServerMessage::InformTargetMissYou(target),
+            logic::AttackInfo::Hit(_) if seat => {
+                prot::ServerMessage::InformTargetHitOpp(target, sunken)
+            }
+            logic::AttackInfo::Hit(_) => prot::ServerMessage::InformTargetHitYou(target, sunken),
+        });
+        self.publish();
+        let (rxplayer, rxopp) = Instance::getplayeropppair(self.turn, &mut self.receivers);
+        let (txplayer, txopp) = Instance::getplayeropppair(self.turn, &mut self.senders);
+
+        self.transcript.shot(self.turn % 2, target, info);
+        self.record.shots.push((self.turn % 2, target, info));
+        let (cmdplayer, cmdopp) = match info {
+            logic::AttackInfo::Miss => (
+                CommandRequest::InformTargetMissOpp(target),
+                CommandRequest::InformTargetMissYou(target),
+            ),
+            logic::AttackInfo::Hit(_) => (
+                CommandRequest::InformTargetHitOpp(target, sunken),
+                CommandRequest::InformTargetHitYou(target, sunken),
+            ),
+        };
+        Instance::informboth(rxplayer, txplayer, cmdplayer, rxopp, txopp, cmdopp).await
+    }
+
+    /// ends the game won by the player whose turn it is
+    async fn win(&mut self) -> Result<(), Error> {
+        Metrics::bump(&self.metrics.completed);
+        self.transcript.end(Some(self.turn % 2), None);
+        self.record.winner = Some(self.turn % 2);
+        let [board1, board2] = &self.boards;
+        let summary = logic::GameSummary {
+            winner: self.turn % 2,
+            turns: self.turns,
+            stats: [board2.stats(), board1.stats()],
+        };
+        self.broadcastend(self.turn % 2, summary);
+
+        let (rxplayer, rxopp) = Instance::getplayeropppair(self.turn, &mut self.receivers);
+        let (txplayer, txopp) = Instance::getplayeropppair(self.turn, &mut self.senders);
+        Instance::informboth(
+            rxplayer,
+            txplayer,
+            CommandRequest::InformVictory,
+            rxopp,
+            txopp,
+            CommandRequest::InformLoss,
+        )
+        .await?;
+        Instance::informboth(
+            rxplayer,
+            txplayer,
+            CommandRequest::InformGameSummary(summary),
+            rxopp,
+            txopp,
+            CommandRequest::InformGameSummary(summary),
+        )
+        .await
+    }
+
+    /// concedes for the player not to move if they resigned, which cuts the
+    /// mover's turn short; whether they did
+    async fn resignedwaiting(&mut self) -> Result<bool, Error> {
+        let waiting = (self.turn + 1) % 2;
+        if !*self.resigned[usize::from(waiting)].borrow() {
+            return Ok(false);
+        }
+        self.concede(waiting, prot::LeaveReason::Resigned).await?;
+        Ok(true)
+    }
+
+    /// ends the game for `loser`, 0 for the first player, because they ran
+    /// out of time or resigned; the opponent is told first and why, so an
+    /// idle player cannot hold up their victory, and an idle player is not
+    /// asked for a rematch but disconnected right away
+    async fn concede(&mut self, loser: u8, reason: prot::LeaveReason) -> Result<(), Error> {
+        Metrics::bump(&self.metrics.forfeited);
+        let winner = (loser + 1) % 2;
+        let note = match reason {
+            prot::LeaveReason::Timeout => String::from("turn timed out"),
+            reason => reason.to_string(),
+        };
+        self.transcript.end(Some(winner), Some(&note));
+        self.record.winner = Some(winner);
+        let [board1, board2] = &self.boards;
+        let summary = logic::GameSummary {
+            winner,
+            turns: self.turns,
+            stats: [board2.stats(), board1.stats()],
+        };
+        self.broadcastend(winner, summary);
+        let (rxplayer, rxopp) = Instance::getplayeropppair(loser, &mut self.receivers);
+        let (txplayer, txopp) = Instance::getplayeropppair(loser, &mut self.senders);
+        let mut cmdsopp = vec![
+            CommandRequest::InformOpponentLeft(reason),
+            CommandRequest::InformVictory,
+            CommandRequest::InformGameSummary(summary),
+        ];
+        let mut cmdsplayer = vec![
+            CommandRequest::InformLoss,
+            CommandRequest::InformGameSummary(summary),
+        ];
+        if reason != prot::LeaveReason::Resigned {
+            cmdsopp.push(CommandRequest::TerminateConnection);
+            cmdsplayer.push(CommandRequest::TerminateConnection);
+        }
+        for cmd in cmdsopp {
+            Instance::informmw(rxopp, txopp, cmd).await?;
+        }
+        for cmd in cmdsplayer {
+            if Instance::informmw(rxplayer, txplayer, cmd).await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// awards the game to every player whose middleware is still running,
+    /// telling them why the opponent is gone; a `loser` still connected is
+    /// told they lost instead
+    async fn forfeit(
+        senders: &mut [mpsc::Sender<CommandRequest>; 2],
+        receivers: &mut [mpsc::Receiver<Result<CommandResult, Error>>; 2],
+        reason: prot::LeaveReason,
+        loser: Option<usize>,
+        transcript: &mut Transcript,
+        metrics: &Metrics,
+    ) {
+        Metrics::bump(&metrics.forfeited);
+        let winner = match loser {
+            Some(loser) => Some(1 - loser),
+            None => senders.iter().position(|tx| !tx.is_closed()),
+        };
+        transcript.end(winner.map(|winner| winner as u8), Some(&reason.to_string()));
+        for (seat, (tx, rx)) in Iterator::zip(senders.iter_mut(), receivers.iter_mut()).enumerate()
+        {
+            if loser == Some(seat) {
+                let _ = Instance::informmw(rx, tx, CommandRequest::InformLoss).await;
+                let _ = Instance::informmw(rx, tx, CommandRequest::TerminateConnection).await;
+            } else if !tx.is_closed() {
+                let _ =
+                    Instance::informmw(rx, tx, CommandRequest::InformOpponentLeft(reason)).await;
+                let _ = Instance::informmw(rx, tx, CommandRequest::InformVictory).await;
+                let _ = Instance::informmw(rx, tx, CommandRequest::TerminateConnection).await;
+            }
+        }
+    }
+
+    async fn saverecord(&self) {
+        let Some(dir) = &self.recorddir else {
+            return;
+        };
+        let path = dir.join(format!("game-{}.zrec", unixmillis()));
+        if let Err(err) = tokio::fs::write(&path, self.record.encode()).await {
+            tracing::warn!("error writing record {}; {err}", path.display());
+        }
+    }
+
+    /// offers both players another game; a player who cannot be asked
+    /// declines
+    async fn rematch(&mut self) -> bool {
+        let [rx1, rx2] = &mut self.receivers;
+        let [tx1, tx2] = &mut self.senders;
+        let (answer1, answer2) = tokio::join!(
+            Instance::askrematch(tx1, rx1),
+            Instance::askrematch(tx2, rx2),
+        );
+        matches!((answer1, answer2), (Ok(true), Ok(true)))
+    }
+
+    async fn askrematch(
+        tx: &mut mpsc::Sender<CommandRequest>,
+        rx: &mut mpsc::Receiver<Result<CommandResult, Error>>,
+    ) -> Result<bool, Error> {
+        tx.send(CommandRequest::OfferRematch)
+            .await
+            .map_err(|_| Error::Disconnected)?;
+        match rx.recv().await.ok_or(Error::Disconnected)?? {
+            CommandResult::Rematch(accepted) => Ok(accepted),
+            other => Err(Error::Middleware(CommandRequest::OfferRematch, other)),
+        }
+    }
+
     async fn play(mut self) -> Result<(), Error> {
         loop {
-            match self.playturn().await {
-                Ok(true) => continue,
-                Ok(false) => break Ok(()),
-                Err(err) => break Err(err),
+            let res = loop {
+                let turn = if self.salvo {
+                    self.playsalvo().await
+                } else {
+                    self.playturn().await
+                };
+                match turn {
+                    Ok(Outcome::Continue) => continue,
+                    Ok(outcome) => break Ok(outcome),
+                    // the turn was not advanced, it is still the resigning player's
+                    Err(Error::Surrendered) => {
+                        break self
+                            .concede(self.turn % 2, prot::LeaveReason::Resigned)
+                            .await
+                            .map(|()| Outcome::Decided);
+                    }
+                    Err(err) => break Err(err),
+                }
+            };
+            self.saverecord().await;
+            if let Some(reason) = res.as_ref().err().and_then(|err| err.leavereason(true)) {
+                Instance::forfeit(
+                    &mut self.senders,
+                    &mut self.receivers,
+                    reason,
+                    None,
+                    self.transcript,
+                    &self.metrics,
+                )
+                .await;
+            }
+            if let Outcome::Expired = res? {
+                return Ok(());
+            }
+            if !self.rematch().await {
+                break;
             }
-        }?;
+
+            // fresh boards, the other player moves first
+            self.transcript.rematch();
+            self.boards = Instance::placeships(
+                &mut self.senders,
+                &mut self.receivers,
+                self.transcript,
+                &self.fleet,
+                &self.metrics,
+            )
+            .await?;
+            self.record = prot::GameRecord::new(self.boards.each_ref().map(|board| *board.ships()));
+            self.first = (self.first + 1) % 2;
+            self.turn = self.first;
+            self.transcript.firstmove(self.first);
+            let snapshot = self.spectatorsnapshot();
+            self.broadcast(prot::ServerMessage::Snapshot(snapshot));
+            self.reveal();
+            self.turns = 0;
+        }
 
         let [rx1, rx2] = &mut self.receivers;
         let [tx1, tx2] = &mut self.senders;
@@ -336,45 +1823,1786 @@ impl Instance {
     }
 }
 
-pub async fn listen(addr: impl net::ToSocketAddrs) -> io::Result<()> {
+pub async fn listen(endpoint: impl Into<Endpoint>, config: Config) -> io::Result<()> {
+    let endpoint = endpoint.into();
+    let listener = Listener::bind(&endpoint)
+        .await
+        .map_err(|err| binderror(err, &endpoint))?;
+    serve(listener, config).await
+}
+
+/// replaces the common bind failures with an actionable message
+fn binderror(err: io::Error, addr: &Endpoint) -> io::Error {
+    let message = match (err.kind(), addr) {
+        (io::ErrorKind::AddrInUse, _) => {
+            format!("address {addr} already in use — is another ziel server running?")
+        }
+        (io::ErrorKind::PermissionDenied, Endpoint::Tcp(_)) => {
+            format!("no permission to bind {addr} — ports below 1024 usually need root")
+        }
+        (io::ErrorKind::AddrNotAvailable, _) => {
+            format!("address {addr} is not available on this machine")
+        }
+        _ => return err,
+    };
+    io::Error::new(err.kind(), message)
+}
+
+/// tells a connection that sent its handshake there is no place for it
+async fn refuse(mut stream: Connection, writetimeout: time::Duration) {
+    let _ = time::timeout(
+        writetimeout,
+        prot::sendmessage(&mut stream, prot::ServerMessage::Refused),
+    )
+    .await;
+}
+
+/// reads the first message, which tells players and spectators apart
+async fn greet(
+    mut stream: Connection,
+    readtimeout: time::Duration,
+    greeted: mpsc::Sender<Greeted>,
+    metrics: sync::Arc<Metrics>,
+) {
+    match prot::readmessagewithin(&mut stream, readtimeout).await {
+        Ok(message) => {
+            let _ = greeted.send((stream, message)).await;
+        }
+        Err(err) => {
+            metrics.protocolerror(&err);
+            tracing::info!("dropping connection before its handshake; {err}");
+        }
+    }
+}
+
+/// whether `given` is the admin password, taking as long for any guess of
+/// the same length
+fn isadmin(given: &str, password: Option<&str>) -> bool {
+    password.is_some_and(|password| {
+        given.len() == password.len()
+            && Iterator::zip(given.bytes(), password.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    })
+}
+
+/// feeds a spectator the game it joined until the game ends; one who gave
+/// a password sees the fleets if it is the admin one and is refused if not
+async fn spectate(
+    mut stream: Connection,
+    version: u8,
+    password: Option<String>,
+    gallery: Option<mpsc::Sender<Spectator>>,
+    config: Config,
+) {
+    let (feed, mut rx) = mpsc::channel(SPECTATORBACKLOG);
+    let admin = password.is_some();
+    let spectator = Spectator { feed, admin };
+    let reply = if version != prot::PROTOCOLVERSION {
+        prot::ServerMessage::VersionMismatch(prot::PROTOCOLVERSION)
+    } else if password.is_some_and(|given| !isadmin(&given, config.adminpassword.as_deref())) {
+        tracing::info!("refusing spectator; wrong admin password");
+        prot::ServerMessage::Refused
+    } else if gallery.is_some_and(|gallery| gallery.try_send(spectator).is_ok()) {
+        prot::ServerMessage::Handshake(config.announced(), 0, None)
+    } else {
+        prot::ServerMessage::Refused
+    };
+    let joined = matches!(reply, prot::ServerMessage::Handshake(..));
+    let mut message = reply;
+    loop {
+        let last = !joined || matches!(message, prot::ServerMessage::TerminateConnection);
+        let sent =
+            time::timeout(config.writetimeout, prot::sendmessage(&mut stream, message)).await;
+        if last || !matches!(sent, Ok(Ok(()))) {
+            break;
+        }
+        // the game dropping the feed ends it
+        message = rx
+            .recv()
+            .await
+            .unwrap_or(prot::ServerMessage::TerminateConnection);
+    }
+}
+
+/// what became of a player who joined the lobby
+enum Joined {
+    /// queued until an opponent from the same queue shows up
+    Waiting(prot::QueueKey),
+    /// matched with the player from the same queue who waited longest, who
+    /// is listed first, along with the slot of their game
+    Paired(Greeted, Greeted, tokio::sync::OwnedSemaphorePermit),
+    /// every game is taken, the connection is handed back to be refused
+    Full(Connection),
+}
+
+/// player waiting for an opponent, holding the slot of the game they are
+/// going to play
+type Pending = (Greeted, tokio::sync::OwnedSemaphorePermit);
+
+/// players waiting for an opponent, each queue paired on its own in the
+/// order its players arrived; every slot is shared by all queues
+struct Lobby {
+    slots: sync::Arc<tokio::sync::Semaphore>,
+    queues: HashMap<prot::QueueKey, VecDeque<Pending>>,
+}
+
+impl Lobby {
+    fn new(maxgames: usize) -> Lobby {
+        Lobby {
+            slots: sync::Arc::new(tokio::sync::Semaphore::new(maxgames)),
+            queues: HashMap::new(),
+        }
+    }
+
+    fn join(&mut self, greeted: Greeted) -> Joined {
+        // anything but a handshake is refused by the game, wherever it waits
+        let queue = match &greeted.1 {
+            prot::ClientMessage::Handshake(_, queue, _) => queue.clone(),
+            _ => prot::QueueKey::default(),
+        };
+        let waiting = self.queues.entry(queue.clone()).or_default();
+        // whoever left since the last look is not paired with anyone
+        let mut cx = task::Context::from_waker(task::Waker::noop());
+        waiting.retain_mut(|((stream, _), _)| !Lobby::leaving(stream, &mut cx));
+        if let Some((waiting, slot)) = waiting.pop_front() {
+            self.queues.retain(|_, waiting| !waiting.is_empty());
+            return Joined::Paired(waiting, greeted, slot);
+        }
+        // every game holds a slot until it ends, anyone beyond is turned away
+        match sync::Arc::clone(&self.slots).try_acquire_owned() {
+            Ok(slot) => {
+                waiting.push_back((greeted, slot));
+                Joined::Waiting(queue)
+            }
+            Err(_) => {
+                self.queues.retain(|_, waiting| !waiting.is_empty());
+                Joined::Full(greeted.0)
+            }
+        }
+    }
+
+    /// players send nothing until their handshake is answered, so a waiting
+    /// connection with anything to read has hung up or misbehaves
+    fn leaving(stream: &mut Connection, cx: &mut task::Context) -> bool {
+        stream.poll_peek(cx).is_ready()
+    }
+
+    /// resolves once a waiting player left, after dropping them and freeing
+    /// their slot; never while nobody waits
+    async fn prune(&mut self) {
+        let (queue, left) = std::future::poll_fn(|cx| {
+            for (queue, waiting) in self.queues.iter_mut() {
+                if let Some(left) = waiting
+                    .iter_mut()
+                    .position(|((stream, _), _)| Lobby::leaving(stream, cx))
+                {
+                    return Poll::Ready((queue.clone(), left));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+        if let Some(waiting) = self.queues.get_mut(&queue) {
+            waiting.remove(left);
+            if waiting.is_empty() {
+                self.queues.remove(&queue);
+            }
+        }
+    }
+}
+
+pub async fn serve(listener: impl Into<Listener>, config: Config) -> io::Result<()> {
+    let listener = listener.into();
+    // nobody is accepted into games this build cannot host
+    prot::Capabilities::supported()
+        .require(config.capabilities())
+        .map_err(|err| io::Error::new(io::ErrorKind::Unsupported, err))?;
     tracing::info!("LISTENING");
+    let (greetedtx, mut greetedrx) = mpsc::channel(GREETEDBACKLOG);
+    let mut lobby = Lobby::new(config.maxgames);
+    // spectators join the game started last
+    let mut gallery: Option<mpsc::Sender<Spectator>> = None;
+    // seats of dropped players by token, kept until their game ends
+    let mut rejoins: HashMap<u64, mpsc::Sender<Rejoin>> = HashMap::new();
+    let mut summary = time::interval_at(time::Instant::now() + METRICSINTERVAL, METRICSINTERVAL);
+    let mut logged = MetricsSnapshot::default();
 
-    let listener = net::TcpListener::bind(addr).await?;
     loop {
-        let (stream1, _) = listener.accept().await?;
-        tracing::info!("player one connected");
-        let (stream2, _) = listener.accept().await?;
+        let (stream, message) = tokio::select! {
+            accepted = listener.accept() => {
+                tokio::spawn(greet(
+                    accepted?,
+                    config.readtimeout,
+                    greetedtx.clone(),
+                    sync::Arc::clone(&config.metrics),
+                ));
+                continue;
+            }
+            _ = summary.tick() => {
+                let snapshot = config.metrics.snapshot();
+                if snapshot != logged {
+                    tracing::info!("{snapshot}");
+                    logged = snapshot;
+                }
+                continue;
+            }
+            Some(greeted) = greetedrx.recv() => greeted,
+            () = lobby.prune() => {
+                tracing::info!("player left the lobby");
+                continue;
+            }
+        };
+        if let prot::ClientMessage::Spectate(version, password) = message {
+            tracing::info!("spectator connected");
+            let gallery = gallery.clone().filter(|gallery| !gallery.is_closed());
+            tokio::spawn(spectate(stream, version, password, gallery, config.clone()));
+            continue;
+        }
+        if let prot::ClientMessage::Reconnect(version, token) = message {
+            rejoins.retain(|_, seat| !seat.is_closed());
+            let handed = match rejoins.get(&token) {
+                Some(seat) => seat
+                    .try_send((stream, version))
+                    .map_err(|err| err.into_inner().0),
+                None => Err(stream),
+            };
+            match handed {
+                Ok(()) => tracing::info!("player reconnecting"),
+                Err(stream) => {
+                    tracing::info!("refusing reconnect; no game waiting for the token");
+                    tokio::spawn(refuse(stream, config.writetimeout));
+                }
+            }
+            continue;
+        }
+        let (first, second, slot) = match lobby.join((stream, message)) {
+            Joined::Waiting(queue) => {
+                tracing::info!("player one connected to the {queue} queue");
+                continue;
+            }
+            Joined::Full(stream) => {
+                tracing::info!("refusing player; all {} games in progress", config.maxgames);
+                tokio::spawn(refuse(stream, config.writetimeout));
+                continue;
+            }
+            Joined::Paired(first, second, slot) => (first, second, slot),
+        };
         tracing::info!("player two connected");
 
-        let (txcs1, rxcs1) = mpsc::channel(10);
-        let (txsc1, rxsc1) = mpsc::channel(10);
+        let (joiningtx, joiningrx) = mpsc::channel(SPECTATORBACKLOG);
+        gallery = Some(joiningtx);
+        let tokens = [(); 2].map(|()| {
+            let (tx, rx) = mpsc::channel(REJOINBACKLOG);
+            if config.reconnecttimeout.is_zero() {
+                return (0, rx);
+            }
+            rejoins.retain(|_, seat| !seat.is_closed());
+            let token = loop {
+                let token = rand::random();
+                if token != 0 && !rejoins.contains_key(&token) {
+                    break token;
+                }
+            };
+            rejoins.insert(token, tx);
+            (token, rx)
+        });
+        let config = config.clone();
+        let rng = rand::rngs::StdRng::from_os_rng();
+        tokio::spawn(async move {
+            host(first, second, joiningrx, tokens, config, rng).await;
+            drop(slot);
+        });
+    }
+}
+
+/// plays one game between two connections opened in this process, who can
+/// neither reconnect nor be watched
+#[cfg(test)]
+pub(crate) async fn hostpair(
+    [mut first, mut second]: [Connection; 2],
+    config: Config,
+    rng: rand::rngs::StdRng,
+) -> Result<(), prot::Error> {
+    let handshake1 = prot::readmessagewithin(&mut first, config.readtimeout).await?;
+    let handshake2 = prot::readmessagewithin(&mut second, config.readtimeout).await?;
+    let (_, joining) = mpsc::channel(1);
+    let tokens = [(); 2].map(|()| (0, mpsc::channel(1).1));
+    host(
+        (first, handshake1),
+        (second, handshake2),
+        joining,
+        tokens,
+        config,
+        rng,
+    )
+    .await;
+    Ok(())
+}
+
+/// plays one game between two greeted connections to the end, `rejoins`
+/// holds each player's token and the reconnections presenting it; `rng`
+/// flips the coin for who moves first
+async fn host(
+    (stream1, handshake1): Greeted,
+    (stream2, handshake2): Greeted,
+    joining: mpsc::Receiver<Spectator>,
+    [(token1, rejoin1), (token2, rejoin2)]: [(u64, mpsc::Receiver<Rejoin>); 2],
+    config: Config,
+    rng: rand::rngs::StdRng,
+) {
+    let (txcs1, rxcs1) = mpsc::channel(10);
+    let (txsc1, rxsc1) = mpsc::channel(10);
+
+    let capabilities = config.capabilities();
+    let (chattx1, chatrx2) = mpsc::channel(CHATBACKLOG);
+    let (chattx2, chatrx1) = mpsc::channel(CHATBACKLOG);
+    let empty = logic::BoardSnapshot {
+        selfhits: logic::HitGrid::new(),
+        opphits: logic::HitGrid::new(),
+        yourturn: false,
+    };
+    let (seattx1, seatrx1) = watch::channel(empty);
+    let (seattx2, seatrx2) = watch::channel(empty);
+    let (resignedtx1, resignedrx1) = watch::channel(false);
+    let (resignedtx2, resignedrx2) = watch::channel(false);
+    let (pause, _) = watch::channel(Pause::Running);
+    let pausing = pause.subscribe();
+    let [name1, name2] = [&handshake1, &handshake2].map(|handshake| match handshake {
+        prot::ClientMessage::Handshake(_, _, name) => name.clone(),
+        _ => None,
+    });
+    let mw1 = Middleware {
+        pending: Some(Ok(handshake1)),
+        info: config.announced(),
+        oppname: name2,
+        capabilities,
+        writetimeout: config.writetimeout,
+        readtimeout: config.readtimeout,
+        expired: false,
+        stream: stream1,
+        serverrx: rxsc1,
+        clienttx: txcs1,
+        chattx: chattx1,
+        chatrx: chatrx1,
+        chatlimit: ChatLimit::new(config.chatburst, config.chatinterval),
+        token: token1,
+        reconnecttimeout: config.reconnecttimeout,
+        rejoin: rejoin1,
+        rejoined: None,
+        seat: seatrx1,
+        resigned: resignedtx1,
+        oppresigned: resignedrx2.clone(),
+        targeting: false,
+        pause: pause.clone(),
+        player: 0,
+        maxpause: config.maxpause,
+    };
+
+    let (txcs2, rxcs2) = mpsc::channel(10);
+    let (txsc2, rxsc2) = mpsc::channel(10);
+
+    let mw2 = Middleware {
+        pending: Some(Ok(handshake2)),
+        info: config.announced(),
+        oppname: name1,
+        capabilities,
+        writetimeout: config.writetimeout,
+        readtimeout: config.readtimeout,
+        expired: false,
+        stream: stream2,
+        serverrx: rxsc2,
+        clienttx: txcs2,
+        chattx: chattx2,
+        chatrx: chatrx2,
+        chatlimit: ChatLimit::new(config.chatburst, config.chatinterval),
+        token: token2,
+        reconnecttimeout: config.reconnecttimeout,
+        rejoin: rejoin2,
+        rejoined: None,
+        seat: seatrx2,
+        resigned: resignedtx2,
+        oppresigned: resignedrx1.clone(),
+        targeting: false,
+        pause,
+        player: 1,
+        maxpause: config.maxpause,
+    };
+
+    tracing::info!("ready to play");
+    let metrics = sync::Arc::clone(&config.metrics);
+    Metrics::bump(&metrics.active);
+    let client1 = tokio::spawn(async move { Middleware::run(mw1).await });
+    let client2 = tokio::spawn(async move { Middleware::run(mw2).await });
+    let instance = tokio::spawn(async move {
+        Instance::run(
+            [txsc1, txsc2],
+            [rxcs1, rxcs2],
+            joining,
+            [seattx1, seattx2],
+            [resignedrx1, resignedrx2],
+            pausing,
+            config,
+            rng,
+        )
+        .await
+    });
+
+    let (_, _, instanceres) = tokio::join!(client1, client2, instance);
+    metrics.active.fetch_sub(1, atomic::Ordering::Relaxed);
+    match instanceres {
+        Ok(Ok(())) => tracing::info!("successful game"),
+        Ok(Err(err)) => {
+            match &err {
+                Error::Protocol(err) => metrics.protocolerror(err),
+                // a well-formed message that did not answer the request
+                Error::Middleware(_, CommandResult::Invalid) => {
+                    Metrics::bump(&metrics.protocolerrors)
+                }
+                _ => {}
+            }
+            tracing::warn!("error finishing game; {err}");
+        }
+        Err(err) => tracing::error!("error joining game; {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net;
+
+    /// a server on a free loopback port
+    async fn start(config: Config) -> std::net::SocketAddr {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, config));
+        addr
+    }
+
+    /// a player who sent their handshake, answered once they are paired
+    async fn connect(addr: std::net::SocketAddr) -> Connection {
+        queued(addr, "").await
+    }
+
+    /// `connect`, waiting in the queue called `queue`
+    async fn queued(addr: std::net::SocketAddr, queue: &str) -> Connection {
+        let mut stream = Connection::from(net::TcpStream::connect(addr).await.unwrap());
+        let queue = prot::QueueKey::new(queue).unwrap();
+        send(
+            &mut stream,
+            prot::ClientMessage::Handshake(prot::PROTOCOLVERSION, queue, None),
+        )
+        .await;
+        stream
+    }
 
-        let mw1 = Middleware {
-            stream: stream1,
-            serverrx: rxsc1,
-            clienttx: txcs1,
+    async fn send(stream: &mut Connection, message: prot::ClientMessage) {
+        prot::sendmessage(stream, message).await.unwrap();
+    }
+
+    async fn recv(stream: &mut Connection) -> prot::ServerMessage {
+        time::timeout(time::Duration::from_secs(10), prot::readmessage(stream))
+            .await
+            .expect("server went quiet")
+            .unwrap()
+    }
+
+    /// takes the handshake reply and queries the capabilities; the token to
+    /// reconnect with
+    async fn greet(stream: &mut Connection) -> u64 {
+        let prot::ServerMessage::Handshake(_, token, _) = recv(stream).await else {
+            panic!("no handshake");
         };
+        send(stream, prot::ClientMessage::QueryCapabilities).await;
+        assert!(matches!(
+            recv(stream).await,
+            prot::ServerMessage::Capabilities(_)
+        ));
+        token
+    }
 
-        let (txcs2, rxcs2) = mpsc::channel(10);
+    /// places random fleets for both greeted players and acknowledges the
+    /// opponent's turn to whoever waits; the index of the player to move
+    async fn placed(players: &mut [Connection; 2]) -> usize {
+        placedwith(players, [(); 2].map(|()| logic::randomplacement())).await
+    }
+
+    /// `placed` with the given fleets
+    async fn placedwith(players: &mut [Connection; 2], fleets: [logic::Ships; 2]) -> usize {
+        for (stream, ships) in players.iter_mut().zip(fleets) {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::RequestShipPositions
+            ));
+            send(stream, prot::ClientMessage::ShipPositions(ships)).await;
+        }
+        let [first, second] = players;
+        let (request, _) = tokio::join!(recv(first), recv(second));
+        let mover = match request {
+            prot::ServerMessage::RequestTarget(_) => 0,
+            _ => 1,
+        };
+        send(&mut players[1 - mover], prot::ClientMessage::Acknowledge).await;
+        mover
+    }
+
+    /// answers every request until the connection is terminated, firing at
+    /// the cells row by row and declining rematches; everything the server
+    /// sent, in order
+    async fn playout(stream: &mut Connection, ships: logic::Ships) -> Vec<prot::ServerMessage> {
+        let mut cells = (0..logic::BOARDSIZE)
+            .flat_map(|y| (0..logic::BOARDSIZE).map(move |x| (x, y)))
+            .filter_map(|(x, y)| logic::Position::fromcoords(x, y));
+        let mut seen = Vec::new();
+        loop {
+            let message = recv(stream).await;
+            let reply = match &message {
+                prot::ServerMessage::RequestShipPositions => {
+                    prot::ClientMessage::ShipPositions(ships)
+                }
+                prot::ServerMessage::RequestTarget(_) => {
+                    prot::ClientMessage::Target(cells.next().unwrap())
+                }
+                prot::ServerMessage::RequestSalvo(shots, _) => {
+                    prot::ClientMessage::Salvo(cells.by_ref().take((*shots).into()).collect())
+                }
+                prot::ServerMessage::OfferRematch => prot::ClientMessage::Rematch(false),
+                prot::ServerMessage::Chat(_) | prot::ServerMessage::OpponentAway(_) => {
+                    seen.push(message);
+                    continue;
+                }
+                _ => prot::ClientMessage::Acknowledge,
+            };
+            let last = matches!(message, prot::ServerMessage::TerminateConnection);
+            seen.push(message);
+            send(stream, reply).await;
+            if last {
+                return seen;
+            }
+        }
+    }
+
+    type Seat = (
+        mpsc::Receiver<CommandRequest>,
+        mpsc::Sender<Result<CommandResult, Error>>,
+    );
+
+    /// an instance without middlewares, driven through the returned seats
+    fn instance(
+        config: Config,
+        seed: u64,
+    ) -> (tokio::task::JoinHandle<Result<(), Error>>, [Seat; 2]) {
+        let (txsc1, rxsc1) = mpsc::channel(10);
         let (txsc2, rxsc2) = mpsc::channel(10);
+        let (txcs1, rxcs1) = mpsc::channel(10);
+        let (txcs2, rxcs2) = mpsc::channel(10);
+        let (_, joining) = mpsc::channel(1);
+        let empty = logic::BoardSnapshot {
+            selfhits: logic::HitGrid::new(),
+            opphits: logic::HitGrid::new(),
+            yourturn: false,
+        };
+        let seats = [watch::channel(empty).0, watch::channel(empty).0];
+        let resigned = [watch::channel(false).1, watch::channel(false).1];
+        let game = tokio::spawn(Instance::run(
+            [txsc1, txsc2],
+            [rxcs1, rxcs2],
+            joining,
+            seats,
+            resigned,
+            watch::channel(Pause::Running).1,
+            config,
+            rand::rngs::StdRng::seed_from_u64(seed),
+        ));
+        (game, [(rxsc1, txcs1), (rxsc2, txcs2)])
+    }
 
-        let mw2 = Middleware {
-            stream: stream2,
-            serverrx: rxsc2,
-            clienttx: txcs2,
+    /// how a fake player ends the game early
+    #[derive(Clone, Copy)]
+    enum Leave {
+        Never,
+        /// hangs up instead of answering the command with this index
+        HangUp(usize),
+        /// surrenders when asked for a target
+        Resign,
+        /// lets every target request run out of time
+        Idle,
+    }
+
+    /// stands in for a cooperative player's middleware, firing at the cells
+    /// row by row until it leaves; the commands it answered
+    async fn fakeplayer(
+        (mut rx, tx): Seat,
+        ships: logic::Ships,
+        leave: Leave,
+    ) -> Vec<CommandRequest> {
+        let mut cells = (0..logic::BOARDSIZE)
+            .flat_map(|y| (0..logic::BOARDSIZE).map(move |x| (x, y)))
+            .filter_map(|(x, y)| logic::Position::fromcoords(x, y));
+        let mut seen = Vec::new();
+        while let Some(cmd) = rx.recv().await {
+            let res = match (&cmd, leave) {
+                (_, Leave::HangUp(quit)) if quit == seen.len() => break,
+                (CommandRequest::RequestTarget(_), Leave::Resign) => CommandResult::Surrender,
+                (CommandRequest::RequestTarget(_), Leave::Idle) => CommandResult::Expired,
+                (CommandRequest::RequestShips, _) => CommandResult::GetShips(ships),
+                (CommandRequest::RequestTarget(_), _) => {
+                    CommandResult::GetTarget(cells.next().unwrap())
+                }
+                (CommandRequest::RequestSalvo(shots, _), _) => {
+                    CommandResult::GetSalvo(cells.by_ref().take((*shots).into()).collect())
+                }
+                (CommandRequest::OfferRematch, _) => CommandResult::Rematch(false),
+                _ => CommandResult::Success,
+            };
+            seen.push(cmd);
+            if tx.send(Ok(res)).await.is_err() {
+                break;
+            }
+        }
+        seen
+    }
+
+    #[tokio::test]
+    async fn shooterinformedfirst() {
+        let (game, [(rx1, tx1), (rx2, tx2)]) = instance(Config::default(), 3);
+        // both seats feed one queue in the order the commands were handed
+        // over, the single threaded runtime wakes the forwarders in that order
+        let (forward, mut merged) = mpsc::unbounded_channel();
+        for (seat, mut rx) in [rx1, rx2].into_iter().enumerate() {
+            let forward = forward.clone();
+            tokio::spawn(async move {
+                while let Some(cmd) = rx.recv().await {
+                    let _ = forward.send((seat, cmd));
+                }
+            });
+        }
+        drop(forward);
+        let txs = [tx1, tx2];
+        let mut cells = [(); 2].map(|()| {
+            (0..logic::BOARDSIZE)
+                .flat_map(|y| (0..logic::BOARDSIZE).map(move |x| (x, y)))
+                .filter_map(|(x, y)| logic::Position::fromcoords(x, y))
+        });
+        let mut log = Vec::new();
+        while let Some((seat, cmd)) = merged.recv().await {
+            let res = match cmd {
+                CommandRequest::RequestShips => CommandResult::GetShips(logic::randomplacement()),
+                CommandRequest::RequestTarget(_) => {
+                    CommandResult::GetTarget(cells[seat].next().unwrap())
+                }
+                CommandRequest::OfferRematch => CommandResult::Rematch(false),
+                _ => CommandResult::Success,
+            };
+            log.push((seat, cmd));
+            let _ = txs[seat].send(Ok(res)).await;
+        }
+        assert!(game.await.unwrap().is_ok());
+
+        let mut informs = 0;
+        for (i, (seat, cmd)) in log.iter().enumerate() {
+            if !matches!(
+                cmd,
+                CommandRequest::InformTargetHitYou(..)
+                    | CommandRequest::InformTargetMissYou(_)
+                    | CommandRequest::InformLoss
+            ) {
+                continue;
+            }
+            informs += 1;
+            let (shooter, before) = &log[i - 1];
+            assert_eq!(*shooter, 1 - seat);
+            assert!(match (cmd, before) {
+                (
+                    CommandRequest::InformTargetHitYou(pos, sunken),
+                    CommandRequest::InformTargetHitOpp(shot, sank),
+                ) => (pos, sunken) == (shot, sank),
+                (
+                    CommandRequest::InformTargetMissYou(pos),
+                    CommandRequest::InformTargetMissOpp(shot),
+                ) => {
+                    pos == shot
+                }
+                (CommandRequest::InformLoss, CommandRequest::InformVictory) => true,
+                _ => false,
+            });
+        }
+        assert!(informs > 17);
+    }
+
+    #[tokio::test]
+    async fn transcriptparses() {
+        let games = [
+            (Leave::Never, "fleet sunk"),
+            (Leave::HangUp(2), "disconnected"),
+        ];
+        for (i, (leave, reason)) in games.into_iter().enumerate() {
+            let dir =
+                std::env::temp_dir().join(format!("ziel-transcripts-{}-{i}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let config = Config {
+                info: prot::ServerInfo::new(r#"the "quoted" \ server"#).unwrap(),
+                transcriptdir: Some(dir.clone()),
+                ..Config::default()
+            };
+            let (game, [seat1, seat2]) = instance(config, 4);
+            let _ = tokio::join!(
+                fakeplayer(seat1, logic::randomplacement(), leave),
+                fakeplayer(seat2, logic::randomplacement(), Leave::Never),
+                game
+            );
+
+            let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+            assert_eq!(files.len(), 1);
+            let contents = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            let lines: Vec<serde_json::Value> = contents
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            let events: Vec<_> = lines
+                .iter()
+                .map(|line| {
+                    assert!(line["time"].is_u64());
+                    line["event"].as_str().unwrap()
+                })
+                .collect();
+
+            assert_eq!(lines[0]["server"], r#"the "quoted" \ server"#);
+            assert_eq!(events[..3], ["start", "fleet", "fleet"]);
+            assert_eq!(events.last(), Some(&"end"));
+            assert_eq!(lines.last().unwrap()["reason"], reason);
+            assert!(lines.last().unwrap()["winner"].is_u64());
+            let shots = events.iter().filter(|&&event| event == "shot").count();
+            if let Leave::Never = leave {
+                assert_eq!(events[3], "first");
+                assert!(shots >= 17);
+                assert!(lines.iter().any(|line| line["outcome"] == "sunk"));
+            } else {
+                assert_eq!(shots, 0);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn metricscountgames() {
+        let config = Config::default();
+        let metrics = sync::Arc::clone(&config.metrics);
+        let pair = || {
+            let [(mut first, one), (mut second, two)] = [(); 2].map(|()| Connection::pair(4096));
+            let host = tokio::spawn(hostpair(
+                [one, two],
+                config.clone(),
+                rand::rngs::StdRng::seed_from_u64(2),
+            ));
+            async move {
+                for stream in [&mut first, &mut second] {
+                    let handshake = prot::ClientMessage::Handshake(
+                        prot::PROTOCOLVERSION,
+                        prot::QueueKey::default(),
+                        None,
+                    );
+                    send(stream, handshake).await;
+                }
+                for stream in [&mut first, &mut second] {
+                    greet(stream).await;
+                }
+                (host, [first, second])
+            }
         };
 
-        tracing::info!("ready to play");
-        let client1 = tokio::spawn(async move { Middleware::run(mw1).await });
-        let client2 = tokio::spawn(async move { Middleware::run(mw2).await });
-        let instance =
-            tokio::spawn(async move { Instance::run([txsc1, txsc2], [rxcs1, rxcs2]).await });
+        let (host, [mut first, mut second]) = pair().await;
+        assert_eq!(metrics.snapshot().active, 1);
+        let fleets = [(); 2].map(|()| logic::randomplacement());
+        tokio::join!(
+            playout(&mut first, fleets[0]),
+            playout(&mut second, fleets[1])
+        );
+        host.await.unwrap().unwrap();
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                started: 1,
+                completed: 1,
+                ..MetricsSnapshot::default()
+            }
+        );
+
+        // a fleet request answered with something else ends the second game
+        let (host, [mut first, mut second]) = pair().await;
+        for stream in [&mut first, &mut second] {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::RequestShipPositions
+            ));
+        }
+        send(&mut first, prot::ClientMessage::Rematch(true)).await;
+        send(&mut second, prot::ClientMessage::ShipPositions(fleets[1])).await;
+        host.await.unwrap().unwrap();
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                started: 2,
+                completed: 1,
+                protocolerrors: 1,
+                ..MetricsSnapshot::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn addressinuseexplained() {
+        let taken = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = taken.local_addr().unwrap();
+        let err = listen(addr, Config::default()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(
+            err.to_string(),
+            format!("address {addr} already in use — is another ziel server running?")
+        );
+
+        // anything else is passed on as it was
+        let other = io::Error::other("disk on fire");
+        let err = binderror(other, &Endpoint::from(addr));
+        assert_eq!(err.to_string(), "disk on fire");
+    }
+
+    #[tokio::test]
+    async fn droppedmiddlewareforfeits() {
+        let (game, [seat1, seat2]) = instance(Config::default(), 1);
+        // handshake and ships, then the first player is gone
+        let (_, seen, res) = tokio::join!(
+            fakeplayer(seat1, logic::randomplacement(), Leave::HangUp(2)),
+            fakeplayer(seat2, logic::randomplacement(), Leave::Never),
+            game
+        );
+
+        assert!(matches!(res.unwrap(), Err(Error::Disconnected)));
+        assert!(matches!(
+            seen[2..],
+            [
+                CommandRequest::RequestTarget(_) | CommandRequest::InformTargetSelection,
+                CommandRequest::InformOpponentLeft(prot::LeaveReason::Disconnected),
+                CommandRequest::InformVictory,
+                CommandRequest::TerminateConnection,
+            ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn winnerhearswhyopponentleft() {
+        for (leave, reason) in [
+            (Leave::Resign, prot::LeaveReason::Resigned),
+            (Leave::Idle, prot::LeaveReason::Timeout),
+            (Leave::HangUp(2), prot::LeaveReason::Disconnected),
+            (Leave::HangUp(1), prot::LeaveReason::Abandoned),
+        ] {
+            let (game, [seat1, seat2]) = instance(Config::default(), 7);
+            // resigning and idling ends the game for whoever moves first
+            let other = match leave {
+                Leave::HangUp(_) => Leave::Never,
+                leave => leave,
+            };
+            let (seen1, seen2, _) = tokio::join!(
+                fakeplayer(seat1, logic::randomplacement(), leave),
+                fakeplayer(seat2, logic::randomplacement(), other),
+                game
+            );
+            let winner = [seen1, seen2]
+                .into_iter()
+                .find(|seen| {
+                    seen.iter()
+                        .any(|cmd| matches!(cmd, CommandRequest::InformVictory))
+                })
+                .expect("nobody won");
+            let left = winner
+                .iter()
+                .position(|cmd| matches!(cmd, CommandRequest::InformOpponentLeft(_)))
+                .expect("never told the opponent left");
+            assert!(
+                matches!(
+                    winner[left..],
+                    [CommandRequest::InformOpponentLeft(got), CommandRequest::InformVictory, ..]
+                        if got == reason
+                ),
+                "{reason}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn summarycountsturns() {
+        for salvo in [false, true] {
+            let (game, [seat1, seat2]) = instance(
+                Config {
+                    salvo,
+                    ..Config::default()
+                },
+                3,
+            );
+            let (seen1, seen2, res) = tokio::join!(
+                fakeplayer(seat1, logic::randomplacement(), Leave::Never),
+                fakeplayer(seat2, logic::randomplacement(), Leave::Never),
+                game
+            );
+            res.unwrap().unwrap();
+
+            let seen = [seen1, seen2].concat();
+            let requested = seen
+                .iter()
+                .filter(|cmd| {
+                    matches!(
+                        cmd,
+                        CommandRequest::RequestTarget(_) | CommandRequest::RequestSalvo(..)
+                    )
+                })
+                .count();
+            let summary = seen
+                .iter()
+                .find_map(|cmd| match cmd {
+                    CommandRequest::InformGameSummary(summary) => Some(*summary),
+                    _ => None,
+                })
+                .unwrap();
+            assert_eq!(usize::from(summary.turns), requested);
+            let fired: usize = summary
+                .stats
+                .iter()
+                .map(|stats| usize::from(stats.hits) + usize::from(stats.misses))
+                .sum();
+            // a salvo fires more than one shot per turn
+            assert_eq!(salvo, fired > requested);
+        }
+    }
+
+    #[tokio::test]
+    async fn presetfleetsplayout() {
+        for preset in logic::FleetPreset::ALL {
+            let fleet = logic::FleetSpec::from(preset);
+            let config = Config {
+                fleet: fleet.clone(),
+                ..Config::default()
+            };
+            let (game, [seat1, seat2]) = instance(config, 5);
+            let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+            let [ships1, ships2] = [(); 2].map(|()| fleet.random(&mut rng).unwrap());
+            let (seen1, seen2, res) = tokio::join!(
+                fakeplayer(seat1, ships1, Leave::Never),
+                fakeplayer(seat2, ships2, Leave::Never),
+                game
+            );
+            res.unwrap().unwrap();
+
+            let summary = [seen1, seen2]
+                .concat()
+                .into_iter()
+                .find_map(|cmd| match cmd {
+                    CommandRequest::InformGameSummary(summary) => Some(summary),
+                    _ => None,
+                })
+                .unwrap();
+            // the winner hit every cell of the fleet, however many ships
+            let cells: u8 = fleet.lengths().iter().sum();
+            assert_eq!(
+                summary.stats[summary.winner as usize].hits, cells,
+                "{preset}"
+            );
+        }
+    }
+
+    /// two players in a game hosted without `serve` or sockets, so paused
+    /// time runs every clock; `seed` flips the coin
+    async fn hosted(config: Config, seed: u64) -> [Connection; 2] {
+        let [(mut first, one), (mut second, two)] = [(); 2].map(|()| Connection::pair(4096));
+        for stream in [&mut first, &mut second] {
+            let handshake = prot::ClientMessage::Handshake(
+                prot::PROTOCOLVERSION,
+                prot::QueueKey::default(),
+                None,
+            );
+            send(stream, handshake).await;
+        }
+        let rng = rand::rngs::StdRng::seed_from_u64(seed);
+        tokio::spawn(hostpair([one, two], config, rng));
+        [first, second]
+    }
+
+    #[tokio::test]
+    async fn namespassedtoopponents() {
+        let [(mut first, one), (mut second, two)] = [(); 2].map(|()| Connection::pair(4096));
+        let ada = prot::PlayerName::new("ada").unwrap();
+        for (stream, name) in [(&mut first, Some(ada.clone())), (&mut second, None)] {
+            let handshake =
+                prot::ClientMessage::Handshake(prot::PROTOCOLVERSION, Default::default(), name);
+            send(stream, handshake).await;
+        }
+        let rng = rand::rngs::StdRng::seed_from_u64(0);
+        tokio::spawn(hostpair([one, two], Config::default(), rng));
+
+        // whoever gave no name is announced without one
+        let prot::ServerMessage::Handshake(_, _, oppname) = recv(&mut first).await else {
+            panic!("no handshake");
+        };
+        assert_eq!(oppname, None);
+        let prot::ServerMessage::Handshake(_, _, oppname) = recv(&mut second).await else {
+            panic!("no handshake");
+        };
+        assert_eq!(oppname, Some(ada));
+    }
+
+    #[tokio::test]
+    async fn refusedfleetsgivereason() {
+        let mut players = hosted(Config::default(), 0).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        // three bytes per ship; horizontal, position, length
+        let vertical = |lengths: [u8; 5]| {
+            Iterator::zip(0.., lengths)
+                .flat_map(|(x, len)| [0, x, len])
+                .collect::<Vec<u8>>()
+        };
+        let mut offboard = vertical([5, 4, 3, 3, 2]);
+        // the carrier sticks out past the right edge of the bottom row
+        offboard[..3].copy_from_slice(&[1, 0x98, 5]);
+        for (body, reason) in [
+            (
+                [5, 4, 3, 3, 2]
+                    .into_iter()
+                    .flat_map(|len| [0, 0, len])
+                    .collect(),
+                logic::Error::ShipOverlap,
+            ),
+            (vertical([5; 5]), logic::Error::InvalidShipLengths),
+            (offboard, logic::Error::OutOfBounds { x: 12, y: 9 }),
+        ] {
+            let stream = &mut players[0];
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::RequestShipPositions
+            ));
+            let positions = prot::RawMessage {
+                typemarker: prot::MessageType::ShipPositions,
+                body,
+            };
+            prot::sendmessage(stream, positions).await.unwrap();
+            match recv(stream).await {
+                prot::ServerMessage::InvalidShips(told) => assert_eq!(told, reason),
+                other => panic!("expected the reason for refusing the fleet, got {other:?}"),
+            }
+            send(stream, prot::ClientMessage::Acknowledge).await;
+        }
+
+        // asked again, a valid fleet gets the game going
+        let fleets = [(); 2].map(|()| logic::randomplacement());
+        let mover = placedwith(&mut players, fleets).await;
+        let target = logic::Position::fromcoords(0, 0).unwrap();
+        send(&mut players[mover], prot::ClientMessage::Target(target)).await;
+        assert!(matches!(
+            recv(&mut players[1 - mover]).await,
+            prot::ServerMessage::InformTargetHitYou(..)
+                | prot::ServerMessage::InformTargetMissYou(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn overlappingfleetsforfeit() {
+        let [mut first, mut second] = hosted(Config::default(), 0).await;
+        for stream in [&mut first, &mut second] {
+            greet(stream).await;
+        }
+        // every ship starts in the top left corner
+        let overlapping: Vec<u8> = [5, 4, 3, 3, 2]
+            .into_iter()
+            .flat_map(|len| [0, 0, len])
+            .collect();
+        let offender = async {
+            for _ in 0..MAXFLEETATTEMPTS {
+                assert!(matches!(
+                    recv(&mut first).await,
+                    prot::ServerMessage::RequestShipPositions
+                ));
+                let positions = prot::RawMessage {
+                    typemarker: prot::MessageType::ShipPositions,
+                    body: overlapping.clone(),
+                };
+                prot::sendmessage(&mut first, positions).await.unwrap();
+                assert!(matches!(
+                    recv(&mut first).await,
+                    prot::ServerMessage::InvalidShips(logic::Error::ShipOverlap)
+                ));
+                send(&mut first, prot::ClientMessage::Acknowledge).await;
+            }
+            playout(&mut first, logic::randomplacement()).await
+        };
+        let (offended, seen) =
+            tokio::join!(offender, playout(&mut second, logic::randomplacement()));
+
+        // no further request once the attempts are used up
+        assert!(matches!(
+            offended[..],
+            [
+                prot::ServerMessage::InformLoss,
+                prot::ServerMessage::TerminateConnection
+            ]
+        ));
+        assert!(matches!(
+            seen[..],
+            [
+                prot::ServerMessage::RequestShipPositions,
+                prot::ServerMessage::OpponentLeft(prot::LeaveReason::Abandoned),
+                prot::ServerMessage::InformVictory,
+                prot::ServerMessage::TerminateConnection
+            ]
+        ));
+    }
+
+    /// resigns for the player to move, or for the one waiting if not
+    /// `onturn`; what the resigner and their opponent were sent from then on
+    async fn resign(onturn: bool) -> (Vec<prot::ServerMessage>, Vec<prot::ServerMessage>) {
+        let mut players = hosted(Config::default(), 5).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let mover = placed(&mut players).await;
+        let resigner = if onturn { mover } else { 1 - mover };
+        let [first, second] = &mut players;
+        let (resigner, winner) = if resigner == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        // the mover's target request stays unanswered when the other resigns
+        send(resigner, prot::ClientMessage::Surrender).await;
+        tokio::join!(
+            playout(resigner, logic::randomplacement()),
+            playout(winner, logic::randomplacement()),
+        )
+    }
 
-        let (_, _, instanceres) = tokio::join!(client1, client2, instance);
-        match instanceres {
-            Ok(Ok(())) => tracing::info!("successful game"),
-            Ok(Err(err)) => tracing::warn!("error finishing game; {err}"),
-            Err(err) => tracing::error!("error joining game; {err}"),
+    #[tokio::test]
+    async fn resignconcedes() {
+        // off turn long before the mover's turn would have run out
+        for onturn in [true, false] {
+            let (resigner, winner) = resign(onturn).await;
+            assert!(matches!(
+                resigner[..],
+                [
+                    prot::ServerMessage::InformLoss,
+                    prot::ServerMessage::GameSummary(_),
+                    prot::ServerMessage::OfferRematch,
+                    prot::ServerMessage::TerminateConnection,
+                ]
+            ));
+            assert!(matches!(
+                winner[..],
+                [
+                    prot::ServerMessage::OpponentLeft(prot::LeaveReason::Resigned),
+                    prot::ServerMessage::InformVictory,
+                    prot::ServerMessage::GameSummary(_),
+                    prot::ServerMessage::OfferRematch,
+                    prot::ServerMessage::TerminateConnection,
+                ]
+            ));
         }
     }
+
+    /// whether the first player is asked for the first target
+    async fn firstmoves(seed: u64) -> bool {
+        let mut players = hosted(Config::default(), seed).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        for stream in &mut players {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::RequestShipPositions
+            ));
+            send(
+                stream,
+                prot::ClientMessage::ShipPositions(logic::randomplacement()),
+            )
+            .await;
+        }
+        matches!(
+            recv(&mut players[0]).await,
+            prot::ServerMessage::RequestTarget(_)
+        )
+    }
+
+    #[tokio::test]
+    async fn seededcoinflip() {
+        let mut outcomes = Vec::new();
+        for seed in 0..8 {
+            let first = firstmoves(seed).await;
+            assert_eq!(firstmoves(seed).await, first);
+            outcomes.push(first);
+        }
+        assert!(outcomes.contains(&true) && outcomes.contains(&false));
+    }
+
+    #[tokio::test]
+    async fn closedsocketforfeits() {
+        let addr = start(Config {
+            reconnecttimeout: time::Duration::ZERO,
+            ..Config::default()
+        })
+        .await;
+        let (mut stream1, mut stream2) = (connect(addr).await, connect(addr).await);
+        greet(&mut stream1).await;
+        greet(&mut stream2).await;
+        // the first player places their ships and hangs up once the game is on
+        assert!(matches!(
+            recv(&mut stream1).await,
+            prot::ServerMessage::RequestShipPositions
+        ));
+        send(
+            &mut stream1,
+            prot::ClientMessage::ShipPositions(logic::randomplacement()),
+        )
+        .await;
+        let (seen, ()) = tokio::join!(playout(&mut stream2, logic::randomplacement()), async {
+            recv(&mut stream1).await;
+            drop(stream1);
+        });
+
+        let left = seen
+            .iter()
+            .position(|message| {
+                matches!(
+                    message,
+                    prot::ServerMessage::OpponentLeft(prot::LeaveReason::Disconnected)
+                )
+            })
+            .expect("never told the opponent left");
+        assert!(matches!(
+            seen[left + 1..],
+            [
+                prot::ServerMessage::InformVictory,
+                prot::ServerMessage::TerminateConnection
+            ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn chatrelayedatonce() {
+        let mut players = hosted(Config::default(), 0).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let mover = placed(&mut players).await;
+        let [first, second] = &mut players;
+        let (mover, waiter) = if mover == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        // neither has an answer of the other pending
+        let chat = |text: &str| prot::ClientMessage::Chat(text.to_owned());
+        send(waiter, chat("hi")).await;
+        assert!(matches!(recv(mover).await, prot::ServerMessage::Chat(text) if text == "hi"));
+        send(mover, chat("yo")).await;
+        assert!(matches!(recv(waiter).await, prot::ServerMessage::Chat(text) if text == "yo"));
+    }
+
+    /// a game whose players may come back `grace` after dropping, with the
+    /// player to move gone; their token and the one waiting
+    async fn dropmover(grace: time::Duration) -> (std::net::SocketAddr, u64, Connection) {
+        let addr = start(Config {
+            reconnecttimeout: grace,
+            ..Config::default()
+        })
+        .await;
+        let mut players = [connect(addr).await, connect(addr).await];
+        let tokens = [greet(&mut players[0]).await, greet(&mut players[1]).await];
+        let mover = placed(&mut players).await;
+        let [first, second] = players;
+        let (gone, waiter) = if mover == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        drop(gone);
+        (addr, tokens[mover], waiter)
+    }
+
+    #[tokio::test]
+    async fn reconnectwithingrace() {
+        let (addr, token, mut waiter) = dropmover(time::Duration::from_secs(5)).await;
+        assert!(matches!(
+            recv(&mut waiter).await,
+            prot::ServerMessage::OpponentAway(5)
+        ));
+
+        let mut back = Connection::from(net::TcpStream::connect(addr).await.unwrap());
+        send(
+            &mut back,
+            prot::ClientMessage::Reconnect(prot::PROTOCOLVERSION, token),
+        )
+        .await;
+        assert!(matches!(
+            recv(&mut back).await,
+            prot::ServerMessage::Handshake(..)
+        ));
+        assert!(matches!(
+            recv(&mut back).await,
+            prot::ServerMessage::Snapshot(_)
+        ));
+        send(&mut back, prot::ClientMessage::Acknowledge).await;
+        assert!(matches!(
+            recv(&mut waiter).await,
+            prot::ServerMessage::OpponentAway(0)
+        ));
+        // the request lost with the old connection is asked again
+        assert!(matches!(
+            recv(&mut back).await,
+            prot::ServerMessage::RequestTarget(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn graceexpired() {
+        let (_, _, mut waiter) = dropmover(time::Duration::from_secs(1)).await;
+        let seen = playout(&mut waiter, logic::randomplacement()).await;
+        assert!(matches!(
+            seen[..],
+            [
+                prot::ServerMessage::OpponentAway(1),
+                prot::ServerMessage::OpponentLeft(prot::LeaveReason::Disconnected),
+                prot::ServerMessage::InformVictory,
+                prot::ServerMessage::TerminateConnection
+            ]
+        ));
+    }
+
+    /// a server taking connections in memory, so the clock can be paused
+    fn startlocal(config: Config) -> mpsc::Sender<Connection> {
+        let (connector, listener) = Listener::local(8);
+        tokio::spawn(serve(listener, config));
+        connector
+    }
+
+    /// `queued` on a server started by `startlocal`
+    async fn queuedlocal(server: &mpsc::Sender<Connection>, queue: &str) -> Connection {
+        let (mut stream, theirs) = Connection::pair(4096);
+        server.send(theirs).await.unwrap();
+        let queue = prot::QueueKey::new(queue).unwrap();
+        send(
+            &mut stream,
+            prot::ClientMessage::Handshake(prot::PROTOCOLVERSION, queue, None),
+        )
+        .await;
+        stream
+    }
+
+    /// whether nothing came in on `stream` while the paused clock ran on
+    async fn silent(stream: &mut Connection) -> bool {
+        time::advance(time::Duration::from_secs(5)).await;
+        // the server had every chance to answer, anything sent is buffered
+        std::future::poll_fn(|cx| Poll::Ready(stream.poll_peek(cx).is_pending())).await
+    }
+
+    /// a spectator of the game started last on a `startlocal` server, with
+    /// the reply to its handshake
+    async fn spectator(
+        server: &mpsc::Sender<Connection>,
+        password: Option<&str>,
+    ) -> (Connection, prot::ServerMessage) {
+        let (mut stream, theirs) = Connection::pair(4096);
+        server.send(theirs).await.unwrap();
+        let hello =
+            prot::ClientMessage::Spectate(prot::PROTOCOLVERSION, password.map(String::from));
+        send(&mut stream, hello).await;
+        let reply = recv(&mut stream).await;
+        (stream, reply)
+    }
+
+    /// everything a spectator is sent until the game ends
+    async fn watched(stream: &mut Connection) -> Vec<prot::ServerMessage> {
+        let mut seen = Vec::new();
+        loop {
+            let message = recv(stream).await;
+            let last = matches!(message, prot::ServerMessage::TerminateConnection);
+            seen.push(message);
+            if last {
+                return seen;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn adminspectatorseesfleets() {
+        let server = startlocal(Config {
+            adminpassword: Some(String::from("hunter2")),
+            ..Config::default()
+        });
+        let mut players = [
+            queuedlocal(&server, "").await,
+            queuedlocal(&server, "").await,
+        ];
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let (_, reply) = spectator(&server, Some("hunter3")).await;
+        assert!(matches!(reply, prot::ServerMessage::Refused));
+        let (mut admin, reply) = spectator(&server, Some("hunter2")).await;
+        assert!(matches!(reply, prot::ServerMessage::Handshake(..)));
+        let (mut regular, reply) = spectator(&server, None).await;
+        assert!(matches!(reply, prot::ServerMessage::Handshake(..)));
+
+        let fleets = [(); 2].map(|()| logic::randomplacement());
+        let [first, second] = &mut players;
+        let (first, second, admin, regular) = tokio::join!(
+            playout(first, fleets[0]),
+            playout(second, fleets[1]),
+            watched(&mut admin),
+            watched(&mut regular),
+        );
+        let revealed = |seen: &[prot::ServerMessage]| {
+            seen.iter()
+                .filter(|message| matches!(message, prot::ServerMessage::Fleets(_)))
+                .count()
+        };
+        assert_eq!(revealed(&first) + revealed(&second) + revealed(&regular), 0);
+        assert_eq!(revealed(&admin), 1);
+        let cells = |ships: &logic::Ships| -> Vec<logic::Position> {
+            ships.into_iter().flatten().collect()
+        };
+        let Some(prot::ServerMessage::Fleets(shown)) = admin.get(1) else {
+            panic!("no fleets right after the snapshot");
+        };
+        assert!(matches!(admin[0], prot::ServerMessage::Snapshot(_)));
+        for (shown, placed) in shown.iter().zip(&fleets) {
+            assert_eq!(cells(shown), cells(placed));
+        }
+        // apart from the fleets, both see the same game
+        assert_eq!(admin.len(), regular.len() + 1);
+    }
+
+    #[tokio::test]
+    async fn fullserverrefuses() {
+        let server = startlocal(Config {
+            maxgames: 1,
+            ..Config::default()
+        });
+        let mut players = [
+            queuedlocal(&server, "").await,
+            queuedlocal(&server, "").await,
+        ];
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        // the only slot is taken, whichever queue the third player picks
+        for queue in ["", "ranked"] {
+            let mut third = queuedlocal(&server, queue).await;
+            assert!(matches!(
+                recv(&mut third).await,
+                prot::ServerMessage::Refused
+            ));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queuespairapart() {
+        let server = startlocal(Config::default());
+        let mut ranked = queuedlocal(&server, "ranked").await;
+        let mut casual = queuedlocal(&server, "casual").await;
+        let mut second = queuedlocal(&server, "ranked").await;
+        for stream in [&mut ranked, &mut second] {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::Handshake(..)
+            ));
+        }
+        // nobody else asked for a casual game yet
+        assert!(silent(&mut casual).await);
+
+        let mut other = queuedlocal(&server, "casual").await;
+        for stream in [&mut casual, &mut other] {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::Handshake(..)
+            ));
+        }
+        // the default queue stays apart from the named ones
+        let mut unnamed = queuedlocal(&server, "").await;
+        let mut named = queuedlocal(&server, "ranked").await;
+        assert!(silent(&mut unnamed).await);
+        assert!(silent(&mut named).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejectedtargetskeepclock() {
+        let config = Config {
+            turntimeout: time::Duration::from_secs(1),
+            ..Config::default()
+        };
+        let mut players = hosted(config, 3).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let fleets = [(); 2].map(|()| logic::randomplacement());
+        let mover = placedwith(&mut players, fleets).await;
+        let start = time::Instant::now();
+        // a hit keeps the turn, the same cell again is rejected every time
+        let target = fleets[1 - mover].asslice()[0].into_iter().next().unwrap();
+        let [first, second] = &mut players;
+        let (mover, waiter) = if mover == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let (seen, fired) = tokio::join!(playout(waiter, logic::randomplacement()), async {
+            send(mover, prot::ClientMessage::Target(target)).await;
+            let mut fired = 0;
+            loop {
+                match recv(mover).await {
+                    prot::ServerMessage::RequestTarget(_) => {
+                        assert!(fired < 20, "never ran out of time");
+                        time::sleep(time::Duration::from_millis(300)).await;
+                        send(mover, prot::ClientMessage::Target(target)).await;
+                        fired += 1;
+                    }
+                    prot::ServerMessage::TerminateConnection => {
+                        send(mover, prot::ClientMessage::Acknowledge).await;
+                        return fired;
+                    }
+                    _ => send(mover, prot::ClientMessage::Acknowledge).await,
+                }
+            }
+        });
+
+        assert!(fired >= 2);
+        assert!(start.elapsed() < time::Duration::from_millis(1500));
+        assert!(seen.iter().any(|message| matches!(
+            message,
+            prot::ServerMessage::OpponentLeft(prot::LeaveReason::Timeout)
+        )));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pauseholdsturnclock() {
+        let config = Config {
+            turntimeout: time::Duration::from_secs(1),
+            maxpause: time::Duration::from_secs(10),
+            ..Config::default()
+        };
+        let mut players = hosted(config, 3).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let mover = placed(&mut players).await;
+        let [first, second] = &mut players;
+        let (mover, waiter) = if mover == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        send(waiter, prot::ClientMessage::Pause(true)).await;
+        assert!(matches!(
+            recv(mover).await,
+            prot::ServerMessage::Pause(prot::PauseNews::Offered)
+        ));
+        send(mover, prot::ClientMessage::Pause(false)).await;
+        assert!(matches!(
+            recv(waiter).await,
+            prot::ServerMessage::Pause(prot::PauseNews::Declined)
+        ));
+
+        send(waiter, prot::ClientMessage::Pause(true)).await;
+        assert!(matches!(
+            recv(mover).await,
+            prot::ServerMessage::Pause(prot::PauseNews::Offered)
+        ));
+        send(mover, prot::ClientMessage::Pause(true)).await;
+        for stream in [&mut *mover, &mut *waiter] {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::Pause(prot::PauseNews::Held(10))
+            ));
+        }
+        // well past the turn clock, which stands still
+        time::advance(time::Duration::from_millis(1500)).await;
+        send(waiter, prot::ClientMessage::Pause(false)).await;
+        for stream in [&mut *mover, &mut *waiter] {
+            assert!(matches!(
+                recv(stream).await,
+                prot::ServerMessage::Pause(prot::PauseNews::Resumed)
+            ));
+        }
+
+        let target = logic::Position::fromcoords(0, 0).unwrap();
+        send(mover, prot::ClientMessage::Target(target)).await;
+        assert!(matches!(
+            recv(waiter).await,
+            prot::ServerMessage::InformTargetHitYou(..)
+                | prot::ServerMessage::InformTargetMissYou(_)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stalledwriteforfeits() {
+        let config = Config {
+            writetimeout: time::Duration::from_secs(1),
+            ..Config::default()
+        };
+        // too small for a full chat message
+        let [(mut first, one), (mut second, two)] = [(); 2].map(|()| Connection::pair(32));
+        for stream in [&mut first, &mut second] {
+            let handshake = prot::ClientMessage::Handshake(
+                prot::PROTOCOLVERSION,
+                prot::QueueKey::default(),
+                None,
+            );
+            send(stream, handshake).await;
+        }
+        let rng = rand::rngs::StdRng::seed_from_u64(3);
+        tokio::spawn(hostpair([one, two], config, rng));
+        let mut players = [first, second];
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let mover = placed(&mut players).await;
+        let mover = &mut players[mover];
+
+        // the waiter never reads again, the chat relayed to them gets stuck
+        let text = "x".repeat(prot::MAXCHATLEN);
+        send(mover, prot::ClientMessage::Chat(text)).await;
+        let target = logic::Position::fromcoords(0, 0).unwrap();
+        send(mover, prot::ClientMessage::Target(target)).await;
+        let seen = playout(mover, logic::randomplacement()).await;
+        // a stuck write counts as the player running out of time
+        assert!(matches!(
+            seen[..],
+            [
+                prot::ServerMessage::InformTargetHitOpp(..)
+                    | prot::ServerMessage::InformTargetMissOpp(_),
+                prot::ServerMessage::OpponentLeft(prot::LeaveReason::Timeout),
+                prot::ServerMessage::InformVictory,
+                prot::ServerMessage::TerminateConnection
+            ]
+        ));
+    }
+
+    #[test]
+    fn capabilitiesfollowconfig() {
+        use prot::Capabilities;
+        let classic = Config::default().capabilities();
+        assert!(
+            classic.contains(Capabilities::COMPACTFLEET | Capabilities::CHAT | Capabilities::PAUSE)
+        );
+        assert!(!classic.intersects(Capabilities::SALVO | Capabilities::CUSTOMFLEETS));
+
+        let config = Config {
+            salvo: false,
+            maxpause: time::Duration::ZERO,
+            fleet: logic::FleetPreset::Mega.into(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.capabilities(),
+            Capabilities::CHAT | Capabilities::CUSTOMFLEETS
+        );
+        assert!(Capabilities::supported()
+            .require(config.capabilities())
+            .is_ok());
+
+        let salvo = Config {
+            salvo: true,
+            ..Config::default()
+        }
+        .capabilities();
+        assert!(salvo.contains(Capabilities::SALVO));
+    }
+
+    #[test]
+    fn chatlimitrefills() {
+        let mut limit = ChatLimit::new(2, time::Duration::from_secs(1));
+        let start = time::Instant::now();
+        assert!(limit.take(start));
+        assert!(limit.take(start));
+        assert!(!limit.take(start));
+        assert!(!limit.take(start + time::Duration::from_millis(900)));
+        assert!(limit.take(start + time::Duration::from_secs(1)));
+        // a long pause earns no more than a full bucket
+        let later = start + time::Duration::from_secs(60);
+        assert!(limit.take(later) && limit.take(later));
+        assert!(!limit.take(later));
+
+        let mut unlimited = ChatLimit::new(0, time::Duration::ZERO);
+        assert!((0..100).all(|_| unlimited.take(start)));
+    }
+
+    #[tokio::test]
+    async fn chatburstthrottled() {
+        let config = Config {
+            chatburst: 3,
+            chatinterval: time::Duration::from_secs(60),
+            ..Config::default()
+        };
+        let mut players = hosted(config, 0).await;
+        for stream in &mut players {
+            greet(stream).await;
+        }
+        let mover = placed(&mut players).await;
+        let [first, second] = &mut players;
+        let (mover, waiter) = if mover == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        for n in 0..6 {
+            send(waiter, prot::ClientMessage::Chat(n.to_string())).await;
+        }
+        for n in 0..3 {
+            assert!(
+                matches!(recv(mover).await, prot::ServerMessage::Chat(text) if text == n.to_string())
+            );
+        }
+
+        // the game goes on, and whatever passed the limit would show up
+        // before the mover is asked again
+        let target = logic::Position::fromcoords(0, 0).unwrap();
+        send(mover, prot::ClientMessage::Target(target)).await;
+        let mut seen = Vec::new();
+        let waited = async {
+            let informed = recv(waiter).await;
+            send(waiter, prot::ClientMessage::Acknowledge).await;
+            informed
+        };
+        let collect = async {
+            while seen
+                .iter()
+                .filter(|message| !matches!(message, prot::ServerMessage::Chat(_)))
+                .count()
+                < 2
+            {
+                let message = recv(mover).await;
+                if matches!(
+                    message,
+                    prot::ServerMessage::InformTargetHitOpp(..)
+                        | prot::ServerMessage::InformTargetMissOpp(_)
+                ) {
+                    send(mover, prot::ClientMessage::Acknowledge).await;
+                }
+                seen.push(message);
+            }
+        };
+        let (informed, ()) = tokio::join!(waited, collect);
+        assert!(matches!(
+            informed,
+            prot::ServerMessage::InformTargetHitYou(..)
+                | prot::ServerMessage::InformTargetMissYou(_)
+        ));
+        assert!(!seen
+            .iter()
+            .any(|message| matches!(message, prot::ServerMessage::Chat(_))));
+        assert!(matches!(
+            seen[1],
+            prot::ServerMessage::RequestTarget(_) | prot::ServerMessage::InformTargetSelection
+        ));
+    }
 }
@@ -0,0 +1,112 @@
+use rand::SeedableRng;
+
+use crate::{
+    client::{self, Client, UI},
+    conn::Connection,
+    server,
+};
+
+/// bytes buffered each way between a client and the server
+const PAIRCAPACITY: usize = 4096;
+
+/// how one seat's game ended, with the interface that played it
+pub struct GameResult<I: UI> {
+    pub outcome: Result<bool, client::Error<I>>,
+    pub interface: I,
+}
+
+/// plays a whole game between `a`, who joins first, and `b` against a server
+/// in this process, without sockets; `seed` decides who moves first
+pub async fn play_local<A: UI, B: UI>(a: A, b: B, seed: u64) -> (GameResult<A>, GameResult<B>) {
+    let (clienta, servera) = Connection::pair(PAIRCAPACITY);
+    let (clientb, serverb) = Connection::pair(PAIRCAPACITY);
+    let rng = rand::rngs::StdRng::seed_from_u64(seed);
+    let host = tokio::spawn(server::hostpair(
+        [servera, serverb],
+        server::Config::default(),
+        rng,
+    ));
+    let results = tokio::join!(play(clienta, a), play(clientb, b));
+    let _ = host.await;
+    results
+}
+
+async fn play<I: UI>(stream: Connection, mut interface: I) -> GameResult<I> {
+    let outcome = match Client::attach(stream, &mut interface).await {
+        Ok(mut client) => client.play(&mut interface).await,
+        Err(err) => Err(err),
+    };
+    GameResult { outcome, interface }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic;
+
+    /// the standard fleet stacked vertically in the five leftmost columns
+    fn fleet() -> logic::Ships {
+        const SHIPLEN: [u8; 5] = [5, 4, 3, 3, 2];
+        let ships: [logic::Ship; 5] = std::array::from_fn(|i| {
+            logic::Ship::try_from(logic::ShipPlan::Vertical {
+                pos: logic::Position::fromcoords(i as u8, 0).unwrap(),
+                len: SHIPLEN[i],
+            })
+            .unwrap()
+        });
+        logic::Ships::try_from(ships).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scriptedwinnerconsistent() {
+        for seed in 0..4 {
+            // one fires only at ships and the other only at open water, so
+            // the first wins whoever moves first
+            let winner = client::ScriptedUI::new(fleet(), fleet().into_iter().flatten());
+            let loser = client::ScriptedUI::new(
+                fleet(),
+                (5..10)
+                    .flat_map(|x| (0..10).map(move |y| (x, y)))
+                    .filter_map(|(x, y)| logic::Position::fromcoords(x, y)),
+            );
+            let (winner, loser) = play_local(winner, loser, seed).await;
+            assert!(matches!(winner.outcome, Ok(true)));
+            assert!(matches!(loser.outcome, Ok(false)));
+
+            let (winnerview, loserview) = (
+                winner.interface.end().unwrap(),
+                loser.interface.end().unwrap(),
+            );
+            assert!(winnerview.victory && !loserview.victory);
+            assert_eq!(winnerview.opphits, loserview.selfhits);
+            assert_eq!(winnerview.selfhits, loserview.opphits);
+            let summary = winnerview.summary.unwrap();
+            assert_eq!(Some(summary), loserview.summary);
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicatetargetrequestedagain() {
+        for seed in 0..2 {
+            let mut targets: Vec<_> = fleet().into_iter().flatten().collect();
+            let duplicate = targets[1];
+            targets.insert(2, duplicate);
+            let repeater = client::ScriptedUI::new(fleet(), targets);
+            let water = client::ScriptedUI::new(
+                fleet(),
+                (5..10)
+                    .flat_map(|x| (0..10).map(move |y| (x, y)))
+                    .filter_map(|(x, y)| logic::Position::fromcoords(x, y)),
+            );
+            let (repeater, _) = play_local(repeater, water, seed).await;
+            // every ship cell after the duplicate was still asked for and fired
+            assert!(matches!(repeater.outcome, Ok(true)));
+            assert_eq!(repeater.interface.rejected(), [duplicate]);
+            let opphits = repeater.interface.end().unwrap().opphits;
+            assert!(fleet()
+                .into_iter()
+                .flatten()
+                .all(|pos| opphits.get(pos).is_some()));
+        }
+    }
+}
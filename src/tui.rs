@@ -1,7 +1,7 @@
 use itertools::Itertools;
-use std::{array, io, iter, time};
+use std::{io, iter, thread, time};
 
-use crossterm::event::{self, KeyCode, KeyEventKind};
+use crossterm::event::{self, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
 use ratatui::{
     layout,
     style::{self, Stylize},
@@ -9,18 +9,67 @@ use ratatui::{
     widgets::{self, canvas},
 };
 
-use crate::{client, logic};
+use crate::{client, logic, prot};
+
+/// colors of the boards and their borders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// one per ship, in fleet order
+    pub ships: [style::Color; logic::MAXSHIPS],
+    pub hit: style::Color,
+    pub miss: style::Color,
+    /// border around an acceptable placement or target
+    pub valid: style::Color,
+    /// border around a placement or target that cannot be confirmed
+    pub invalid: style::Color,
+}
+
+impl Theme {
+    /// saturated colors that survive video compression
+    pub fn highcontrast() -> Theme {
+        Theme {
+            ships: [
+                style::Color::LightCyan,
+                style::Color::LightYellow,
+                style::Color::LightMagenta,
+                style::Color::LightBlue,
+                style::Color::White,
+            ],
+            hit: style::Color::Red,
+            miss: style::Color::Gray,
+            ..Theme::default()
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            ships: [
+                style::Color::from_u32(0xffcdb2),
+                style::Color::from_u32(0xffb4a2),
+                style::Color::from_u32(0xe5989b),
+                style::Color::from_u32(0xb5838d),
+                style::Color::from_u32(0x6d6875),
+            ],
+            hit: style::Color::LightRed,
+            miss: style::Color::White,
+            valid: style::Color::Green,
+            invalid: style::Color::Red,
+        }
+    }
+}
+
+const DEFAULTTICKRATE: time::Duration = time::Duration::from_millis(250);
 
-const SHIPCOLOR: [style::Color; 5] = [
-    style::Color::from_u32(0xffcdb2),
-    style::Color::from_u32(0xffb4a2),
-    style::Color::from_u32(0xe5989b),
-    style::Color::from_u32(0xb5838d),
-    style::Color::from_u32(0x6d6875),
-];
+/// columns left of the boards taken by row numbers
+const LABELWIDTH: u16 = 2;
 
-const ATTACKHITCOLOR: style::Color = style::Color::LightRed;
-const ATTACKMISSCOLOR: style::Color = style::Color::White;
+/// terminal cells are about twice as tall as they are wide
+pub const DEFAULTCELLASPECT: f32 = 2.0;
+
+const ANIMATIONTRAVELFRAME: time::Duration = time::Duration::from_millis(25);
+const ANIMATIONIMPACTFRAME: time::Duration = time::Duration::from_millis(80);
 
 impl<'s> TryFrom<client::Message> for text::Line<'s> {
     type Error = ();
@@ -30,12 +79,13 @@ impl<'s> TryFrom<client::Message> for text::Line<'s> {
             client::Message::SuccessfullyConnected => {
                 Ok(text::Line::from("successfully connected"))
             }
+            client::Message::Reconnected => Ok(text::Line::from("reconnected")),
             client::Message::ShipHit => Ok(text::Line::from(vec![
                 text::Span::raw("ship "),
                 text::Span::styled("hit", style::Style::new().light_red()),
             ])),
-            client::Message::ShipSunken => Ok(text::Line::from(vec![
-                text::Span::raw("ship "),
+            client::Message::ShipSunken(len) => Ok(text::Line::from(vec![
+                text::Span::raw(format!("{} ", logic::shipname(len))),
                 text::Span::styled("sunken", style::Style::new().light_red()),
             ])),
             client::Message::ShipMissed => Ok(text::Line::from(vec![
@@ -47,9 +97,9 @@ impl<'s> TryFrom<client::Message> for text::Line<'s> {
                 text::Span::raw("ship "),
                 text::Span::styled("hit", style::Style::new().yellow()),
             ])),
-            client::Message::OppShipSunken => Ok(text::Line::from(vec![
+            client::Message::OppShipSunken(len) => Ok(text::Line::from(vec![
                 text::Span::styled("opp.", style::Style::new().cyan()),
-                text::Span::raw(" ship "),
+                text::Span::raw(format!(" {} ", logic::shipname(len))),
                 text::Span::styled("sunken", style::Style::new().yellow()),
             ])),
             client::Message::OppShipMissed => Ok(text::Line::from(vec![
@@ -61,17 +111,667 @@ impl<'s> TryFrom<client::Message> for text::Line<'s> {
     }
 }
 
+/// where input comes from, so redraw loops can run without a terminal
+trait Input {
+    fn poll(&mut self, timeout: time::Duration) -> io::Result<bool>;
+    fn read(&mut self) -> io::Result<event::Event>;
+}
+
+/// the terminal's own input
+struct Crossterm;
+
+impl Input for Crossterm {
+    fn poll(&mut self, timeout: time::Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<event::Event> {
+        event::read()
+    }
+}
+
+/// waits up to one tick for input; `None` once the tick passed without any,
+/// so the caller redraws every tick even while nothing is typed
+fn nexttick(input: &mut impl Input, tickrate: time::Duration) -> io::Result<Option<event::Event>> {
+    Ok(if input.poll(tickrate)? {
+        Some(input.read()?)
+    } else {
+        None
+    })
+}
+
+/// how the boards map onto terminal cells, chosen so board cells come out
+/// roughly square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Geometry {
+    marker: symbols::Marker,
+    /// terminal rows spanned by the ten board rows, without borders
+    rows: u16,
+}
+
+impl Geometry {
+    /// `cellaspect` is the height of a terminal cell divided by its width
+    fn new(cellaspect: f32) -> Geometry {
+        if cellaspect >= 1.5 {
+            // two board rows per terminal row
+            Geometry {
+                marker: symbols::Marker::HalfBlock,
+                rows: 5,
+            }
+        } else {
+            Geometry {
+                marker: symbols::Marker::Block,
+                rows: 10,
+            }
+        }
+    }
+
+    /// both boards side by side, including borders and coordinate labels
+    fn size(self) -> layout::Size {
+        layout::Size::new(LABELWIDTH + 23, self.rows + 3)
+    }
+
+    /// a single board, including borders
+    fn boardsize(self) -> layout::Size {
+        layout::Size::new(12, self.rows + 2)
+    }
+
+    /// board cell under a terminal cell of a canvas drawn into `area`; with
+    /// two board rows per terminal row the cursor's is preferred, otherwise
+    /// the upper one
+    fn cellat(
+        self,
+        area: layout::Rect,
+        column: u16,
+        row: u16,
+        cursor: (u8, u8),
+    ) -> Option<(u8, u8)> {
+        if !area.contains(layout::Position::new(column, row)) {
+            return None;
+        }
+        let perrow = 10 / self.rows;
+        let (x, y) = (column - area.x, (row - area.y) * perrow);
+        if x >= 10 || y >= 10 {
+            return None;
+        }
+        let (x, y) = (x as u8, y as u8);
+        if cursor.0 == x && cursor.1 / perrow as u8 == y / perrow as u8 {
+            Some(cursor)
+        } else {
+            Some((x, y))
+        }
+    }
+
+    /// canvas y coordinate of a label printed into terminal row `row`;
+    /// labels snap to rows differently from points
+    fn labely(self, row: u16) -> f64 {
+        (9.0 - (row as f64 + 0.5) * 9.0 / (self.rows - 1) as f64).max(0.0)
+    }
+
+    /// glyph of every terminal cell showing a shot, by column and row; with
+    /// two board rows per terminal row a hit wins over a miss
+    fn glyphcells(self, hits: &logic::HitGrid) -> Vec<(u16, u16, logic::AttackInfo)> {
+        let perrow = (10 / self.rows) as usize;
+        (0..self.rows)
+            .flat_map(|row| (0..10).map(move |x| (x, row)))
+            .filter_map(|(x, row)| {
+                let start = row as usize * perrow;
+                (start..start + perrow)
+                    .filter_map(|y| hits[y][x as usize])
+                    .reduce(|kept, info| match kept {
+                        logic::AttackInfo::Hit(_) => kept,
+                        logic::AttackInfo::Miss => info,
+                    })
+                    .map(|info| (x, row, info))
+            })
+            .collect()
+    }
+
+    fn canvas<'a, F: Fn(&mut canvas::Context)>(self) -> canvas::Canvas<'a, F> {
+        canvas::Canvas::default()
+            .x_bounds([0.0, 9.0])
+            .y_bounds([0.0, 9.0])
+            .marker(self.marker)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Placement,
+    Turn,
+    Waiting,
+    Victory,
+    Loss,
+}
+
+impl Screen {
+    fn title(self) -> &'static str {
+        match self {
+            Screen::Placement => "ziel — placing ships",
+            Screen::Turn => "ziel — your turn",
+            Screen::Waiting => "ziel — waiting",
+            Screen::Victory => "ziel — victory",
+            Screen::Loss => "ziel — loss",
+        }
+    }
+
+    /// the title followed by who plays whom, once the opponent gave a name
+    fn windowtitle(self, [name, oppname]: [Option<&str>; 2]) -> String {
+        match oppname {
+            Some(oppname) => format!("{} — {} vs {oppname}", self.title(), name.unwrap_or("you")),
+            None => String::from(self.title()),
+        }
+    }
+}
+
+/// what one key press did to a ship being moved
+#[derive(Debug, PartialEq, Eq)]
+enum Carry {
+    Moving { valid: bool },
+    Placed,
+    Cancelled,
+}
+
+/// a ship picked up during placement, remembering where it came from
+struct Carried {
+    original: logic::Ship,
+    origin: (u8, u8),
+    shiplenoff: u8,
+    shiplen: u8,
+    horizontal: bool,
+}
+
+impl Carried {
+    /// picks up `ship` with the cursor at `x`, `y` on it
+    fn pickup(ship: logic::Ship, (x, y): (u8, u8)) -> Carried {
+        let (shiplenoff, shiplen, horizontal) = match ship.into() {
+            logic::ShipPlan::Horizontal { pos, len } => (x - pos.coords().0, len, true),
+            logic::ShipPlan::Vertical { pos, len } => (y - pos.coords().1, len, false),
+        };
+        Carried {
+            original: ship,
+            origin: (x, y),
+            shiplenoff,
+            shiplen,
+            horizontal,
+        }
+    }
+
+    /// applies one key press (or none, on other events) to `ships[idx]` and the cursor
+    fn press(
+        &mut self,
+        kevent: Option<event::KeyEvent>,
+        (x, y): (&mut u8, &mut u8),
+        ships: &mut [logic::Ship],
+        idx: usize,
+        notouch: bool,
+    ) -> io::Result<Carry> {
+        let (shiplenoff, shiplen) = (self.shiplenoff, self.shiplen);
+        let mut checkready = false;
+        if let Some(kevent) = kevent {
+            match kevent.code {
+                KeyCode::Char('a') | KeyCode::Left if *x > 0 => *x -= 1,
+                KeyCode::Char('w') | KeyCode::Up if *y > 0 => *y -= 1,
+                KeyCode::Char('d') | KeyCode::Right if *x < 9 => *x += 1,
+                KeyCode::Char('s') | KeyCode::Down if *y < 9 => *y += 1,
+                KeyCode::Char('r') => {
+                    self.horizontal ^= true;
+                }
+                KeyCode::Char(' ') => checkready = true,
+                KeyCode::Esc => {
+                    ships[idx] = self.original;
+                    (*x, *y) = self.origin;
+                    return Ok(Carry::Cancelled);
+                }
+                KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err(interrupted());
+                }
+                KeyCode::Char('q') => return Err(io::Error::other("player interrupted")),
+                _ => {}
+            }
+        }
+        let horizontal = self.horizontal;
+        *x = u8::clamp(
+            *x,
+            if horizontal { shiplenoff } else { 0 },
+            if horizontal {
+                10 - shiplen + shiplenoff
+            } else {
+                9
+            },
+        );
+        *y = u8::clamp(
+            *y,
+            if horizontal { 0 } else { shiplenoff },
+            if horizontal {
+                9
+            } else {
+                10 - shiplen + shiplenoff
+            },
+        );
+
+        ships[idx] = if horizontal {
+            logic::ShipPlan::Horizontal {
+                pos: logic::Position::new(*x - shiplenoff, *y).map_err(io::Error::other)?,
+                len: shiplen,
+            }
+        } else {
+            logic::ShipPlan::Vertical {
+                pos: logic::Position::new(*x, *y - shiplenoff).map_err(io::Error::other)?,
+                len: shiplen,
+            }
+        }
+        .try_into()
+        .map_err(|()| io::Error::other("ship out of bounds"))?;
+
+        let valid = if notouch {
+            logic::validshipposnotouch(ships)
+        } else {
+            logic::validshippos(ships)
+        };
+
+        Ok(if checkready && valid {
+            Carry::Placed
+        } else {
+            Carry::Moving { valid }
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Interface {
     term: ratatui::DefaultTerminal,
     cursorpos: (u8, u8),
+    animations: bool,
+    tickrate: time::Duration,
+    geometry: Geometry,
+    windowtitle: bool,
+    screen: Option<Screen>,
+    /// last window title set, it is not set again while it stays the same
+    title: String,
+    /// target refused by the server, marked until the cursor moves
+    rejected: Option<(u8, u8)>,
+    view: View,
+    /// why the server refused the last placement, shown until the next one
+    rejectedships: Option<logic::Error>,
+    /// outcome of the last screenshot, shown above the message log
+    notice: Option<String>,
+    /// layout code placement starts from instead of the standard seed
+    favourite: Option<String>,
+    theme: Theme,
+    /// rules placements are checked against before they are sent
+    fleet: logic::FleetSpec,
+    /// targets already picked for the salvo being selected
+    salvo: Vec<(u8, u8)>,
+    /// chat being typed in, opened with `t` during a game
+    draft: Option<String>,
+    /// chat finished but not yet handed to the client
+    outbox: Option<String>,
+    /// the last target selection was cut short, the next one picks up where
+    /// it left off
+    yielded: bool,
+    /// the player confirmed conceding the game
+    surrender: bool,
+    /// `r` was pressed once while the opponent moves, a second confirms
+    resigning: bool,
+    /// the player's word on a pause, not yet handed to the client
+    pause: Option<bool>,
+    /// a rematch offer waits for an answer on the end screen
+    rematchoffer: bool,
 }
 
 impl Interface {
     pub fn new() -> Interface {
+        let mut term = ratatui::init();
+        // best effort, clicks only add to the keyboard controls
+        let _ = crossterm::execute!(term.backend_mut(), event::EnableMouseCapture);
         Interface {
-            term: ratatui::init(),
+            term,
             cursorpos: (0, 0),
+            animations: true,
+            tickrate: DEFAULTTICKRATE,
+            geometry: Geometry::new(DEFAULTCELLASPECT),
+            windowtitle: true,
+            screen: None,
+            title: String::new(),
+            rejected: None,
+            view: View::default(),
+            rejectedships: None,
+            notice: None,
+            favourite: None,
+            theme: Theme::default(),
+            fleet: logic::FleetSpec::default(),
+            salvo: Vec::new(),
+            draft: None,
+            outbox: None,
+            yielded: false,
+            surrender: false,
+            resigning: false,
+            pause: None,
+            rematchoffer: false,
+        }
+    }
+
+    pub fn tickrate(mut self, tickrate: time::Duration) -> Interface {
+        self.tickrate = tickrate;
+        self
+    }
+
+    pub fn animations(mut self, enabled: bool) -> Interface {
+        self.animations = enabled;
+        self
+    }
+
+    /// height over width of a terminal cell, used to keep the boards square
+    pub fn cellaspect(mut self, cellaspect: f32) -> Interface {
+        self.geometry = Geometry::new(cellaspect);
+        self
+    }
+
+    /// starts with the message log split by origin, toggled with `m`
+    pub fn splitlog(mut self, enabled: bool) -> Interface {
+        self.view.splitlog = enabled;
+        self
+    }
+
+    /// starts with hits and misses marked by glyphs, toggled with `g`
+    pub fn glyphs(mut self, enabled: bool) -> Interface {
+        self.view.glyphs = enabled;
+        self
+    }
+
+    /// checks placements against these rules; a server refusing touching
+    /// ships turns the rule on by itself
+    pub fn fleet(mut self, fleet: logic::FleetSpec) -> Interface {
+        self.fleet = fleet;
+        self
+    }
+
+    /// starts every placement from this layout code; an invalid code falls
+    /// back to the standard seed and says so
+    pub fn favourite(mut self, code: impl Into<String>) -> Interface {
+        self.favourite = Some(code.into());
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Interface {
+        self.theme = theme;
+        self
+    }
+
+    pub fn windowtitle(mut self, enabled: bool) -> Interface {
+        self.windowtitle = enabled;
+        self
+    }
+
+    /// writes both boards with ANSI colors to a new file in the working directory
+    fn screenshot(&mut self, info: client::ClientInfo) {
+        let secs = time::SystemTime::now()
+            .duration_since(time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = format!("ziel-{secs}.ans");
+        self.notice = Some(
+            match std::fs::write(&path, ansiboards(&self.theme, &info)) {
+                Ok(()) => format!("saved {path}"),
+                Err(err) => format!("screenshot failed: {err}"),
+            },
+        );
+    }
+
+    fn noticeline(&self) -> Option<text::Line<'static>> {
+        self.notice
+            .as_ref()
+            .map(|notice| text::Line::from(notice.clone()).dark_gray())
+    }
+
+    /// `p` asks for or accepts a pause and ends one that holds, `n` declines
+    /// the opponent's; whether the key was taken
+    fn pausekey(&mut self, code: KeyCode, info: client::ClientInfo) -> bool {
+        self.pause = match (code, info.pause) {
+            (KeyCode::Char('p'), Some(client::PauseState::Held(_))) => Some(false),
+            (KeyCode::Char('p'), _) => Some(true),
+            (KeyCode::Char('n'), Some(client::PauseState::Offered(client::Origin::Opponent))) => {
+                Some(false)
+            }
+            _ => return false,
+        };
+        true
+    }
+
+    fn pauseline(info: client::ClientInfo) -> Option<text::Line<'static>> {
+        Some(match info.pause? {
+            client::PauseState::Offered(client::Origin::Opponent) => {
+                text::Line::from("opponent asks for a pause, p to accept, n to decline").yellow()
+            }
+            client::PauseState::Offered(client::Origin::Own) => {
+                text::Line::from("pause asked for, waiting for the opponent").dark_gray()
+            }
+            client::PauseState::Held(left) => {
+                text::Line::from(format!("paused for {}s, p to resume", left.as_secs())).yellow()
+            }
+        })
+    }
+
+    fn rematchline(&self) -> Option<text::Line<'static>> {
+        self.rematchoffer
+            .then(|| text::Line::from("rematch? y/n").yellow())
+    }
+
+    /// steps through a recorded game on keypresses until the viewer backs
+    /// out, the last step shows the end screen
+    pub fn playback(&mut self, replay: &client::Replay) -> io::Result<()> {
+        let mut step = 0;
+        loop {
+            let info = replay.info(step);
+            let shown = match replay.victory() {
+                Some(true) if step == replay.laststep() => client::UI::displayvictory(self, info),
+                Some(false) if step == replay.laststep() => client::UI::displayloss(self, info),
+                _ => client::UI::displayboard(self, info),
+            };
+            shown.map_err(|err| io::Error::new(err.inner().kind(), err.to_string()))?;
+
+            match event::read()? {
+                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
+                    match kevent.code {
+                        KeyCode::Char('d') | KeyCode::Right | KeyCode::Char(' ') => {
+                            step = usize::min(step + 1, replay.laststep())
+                        }
+                        KeyCode::Char('a') | KeyCode::Left => step = step.saturating_sub(1),
+                        KeyCode::Home => step = 0,
+                        KeyCode::End => step = replay.laststep(),
+                        KeyCode::Char('m') => self.view.splitlog ^= true,
+                        KeyCode::Char('g') => self.view.glyphs ^= true,
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted());
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// steps through the boards after each shot until the player backs out
+    fn replay(&mut self, info: client::ClientInfo) -> io::Result<()> {
+        let states = client::replaystates(info.shots).map_err(io::Error::other)?;
+        let laststep = states.len() - 1;
+        let mut step = 0;
+        loop {
+            let (selfhits, opphits) = &states[step];
+            let frame = client::ClientInfo {
+                selfhits,
+                opphits,
+                message: &[],
+                summary: None,
+                ..info
+            };
+            self.term.draw(|f| {
+                rendergame(
+                    f,
+                    self.geometry,
+                    self.view,
+                    &self.theme,
+                    &frame,
+                    Extras::default(),
+                );
+                if !fits(f, self.geometry.size()) {
+                    return;
+                }
+                let rect = centerrectinrect(f.area(), self.geometry.size());
+                let rectcounter = layout::Rect {
+                    x: rect.x,
+                    y: rect.y + rect.height,
+                    width: rect.width,
+                    height: 1,
+                }
+                .intersection(f.area());
+                f.render_widget(
+                    widgets::Paragraph::new(format!("replay {step}/{laststep}")).centered(),
+                    rectcounter,
+                );
+            })?;
+
+            match event::read()? {
+                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
+                    match kevent.code {
+                        KeyCode::Char('d') | KeyCode::Right | KeyCode::Char(' ')
+                            if step < laststep =>
+                        {
+                            step += 1
+                        }
+                        KeyCode::Char('a') | KeyCode::Left if step > 0 => step -= 1,
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted());
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `names` are the own and the opponent's, if they gave one
+    fn setscreen(&mut self, screen: Screen, names: [Option<&str>; 2]) {
+        let title = screen.windowtitle(names);
+        if self.windowtitle && self.title != title {
+            // best effort, not every terminal supports setting the title
+            let _ = crossterm::execute!(
+                self.term.backend_mut(),
+                crossterm::terminal::SetTitle(&title)
+            );
+            self.title = title;
+        }
+        self.screen = Some(screen);
+    }
+
+    /// edits the chat being typed in; `true` once it is finished
+    fn typechat(&mut self, kevent: event::KeyEvent) -> io::Result<bool> {
+        let Some(draft) = self.draft.as_mut() else {
+            return Ok(false);
+        };
+        match kevent.code {
+            KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Err(interrupted());
+            }
+            KeyCode::Char(c) if draft.len() + c.len_utf8() <= prot::MAXCHATLEN => draft.push(c),
+            KeyCode::Backspace => {
+                draft.pop();
+            }
+            KeyCode::Enter => {
+                self.outbox = self.draft.take().filter(|text| !text.is_empty());
+                return Ok(self.outbox.is_some());
+            }
+            KeyCode::Esc => self.draft = None,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// the chat being typed in, for the header
+    fn draftline(&self) -> Option<text::Line<'static>> {
+        let draft = self.draft.as_ref()?;
+        Some(text::Line::from(format!("say: {draft}_")))
+    }
+
+    /// lets the player move and rotate `ships[idx]` with the cursor at `x`, `y`
+    fn moveship(
+        &mut self,
+        x: &mut u8,
+        y: &mut u8,
+        ships: &mut [logic::Ship],
+        idx: usize,
+    ) -> io::Result<()> {
+        let (term, geometry, theme) = (&mut self.term, self.geometry, &self.theme);
+        let notouch = self.fleet.istouchforbidden();
+        let mut carried = Carried::pickup(ships[idx], (*x, *y));
+
+        loop {
+            let kevent = match event::read()? {
+                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => Some(kevent),
+                _ => None,
+            };
+            let valid = match carried.press(kevent, (x, y), ships, idx, notouch)? {
+                Carry::Moving { valid } => valid,
+                Carry::Placed | Carry::Cancelled => return Ok(()),
+            };
+
+            term.draw(|f| {
+                if !fits(f, geometry.boardsize()) {
+                    return;
+                }
+                let [boardx, boardy] = logic::Position::fromcoords(*x, *y).unwrap().toboard();
+                let canvas = geometry
+                    .canvas()
+                    .block(
+                        widgets::Block::bordered()
+                            .border_style(if valid {
+                                style::Style::new().fg(theme.valid)
+                            } else {
+                                style::Style::new().fg(theme.invalid)
+                            })
+                            .border_type(widgets::BorderType::Thick)
+                            .title_bottom(text::Line::raw(format!("{boardx}{boardy}"))),
+                    )
+                    .paint(|ctx| {
+                        for (ship, color) in Iterator::zip(ships.iter(), theme.ships)
+                            .chain(iter::once((&ships[idx], theme.ships[idx])))
+                        {
+                            let line = match ship.into() {
+                                logic::ShipPlan::Horizontal { pos, len } => {
+                                    let (x, y) = pos.coords();
+                                    canvas::Line {
+                                        x1: x as f64,
+                                        y1: (9 - y) as f64,
+                                        x2: (x + len - 1) as f64,
+                                        y2: (9 - y) as f64,
+                                        color,
+                                    }
+                                }
+                                logic::ShipPlan::Vertical { pos, len } => {
+                                    let (x, y) = pos.coords();
+                                    canvas::Line {
+                                        x1: x as f64,
+                                        y1: (9 - y) as f64,
+                                        x2: x as f64,
+                                        y2: (9 - (y + len - 1)) as f64,
+                                        color,
+                                    }
+                                }
+                            };
+                            ctx.draw(&line);
+                        }
+                        ctx.draw(&canvas::Points {
+                            coords: &[(*x as f64, (9 - *y) as f64)],
+                            color: style::Color::White,
+                        });
+                    });
+                f.render_widget(canvas, centerrectinrect(f.area(), geometry.boardsize()));
+            })?;
         }
     }
 }
@@ -84,7 +784,7 @@ impl Default for Interface {
 
 impl Drop for Interface {
     fn drop(&mut self) {
-        ratatui::restore();
+        let _ = restoreterminal(&mut io::stdout());
     }
 }
 
@@ -92,41 +792,74 @@ impl client::UI for Interface {
     type Error = io::Error;
 
     fn buildboard(&mut self) -> Result<logic::Ships, client::UIError<io::Error>> {
-        const SHIPLEN: [u8; 5] = [5, 4, 3, 3, 2];
-        let mut ships: [logic::Ship; 5] = array::from_fn(|i| {
-            logic::ShipPlan::Vertical {
-                pos: logic::Position::fromcoords(i as u8, 0).unwrap(),
-                len: SHIPLEN[i],
-            }
-            .try_into()
-            .unwrap()
-        });
+        self.setscreen(Screen::Placement, [None; 2]);
+        let (ships, invalidfavourite) = startships(self.favourite.as_deref(), &self.fleet);
+        let mut ships = ships.asslice().to_vec();
 
         let mut x = 0;
         let mut y = 0;
+        // layout code being typed in, if importing
+        let mut import: Option<String> = None;
         loop {
             match event::read()? {
+                event::Event::Key(kevent)
+                    if kevent.kind == KeyEventKind::Press && import.is_some() =>
+                {
+                    let Some(code) = import.as_mut() else {
+                        continue;
+                    };
+                    match kevent.code {
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted().into());
+                        }
+                        KeyCode::Char(c) if code.len() < 16 => code.push(c),
+                        KeyCode::Backspace => {
+                            code.pop();
+                        }
+                        KeyCode::Enter => {
+                            if let Ok(imported) = logic::Ships::fromcode(code) {
+                                ships = imported.asslice().to_vec();
+                                import = None;
+                            }
+                        }
+                        KeyCode::Esc => import = None,
+                        _ => {}
+                    }
+                }
                 event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
                     match kevent.code {
                         KeyCode::Char('a') | KeyCode::Left if x > 0 => x -= 1,
                         KeyCode::Char('w') | KeyCode::Up if y > 0 => y -= 1,
                         KeyCode::Char('d') | KeyCode::Right if x < 9 => x += 1,
                         KeyCode::Char('s') | KeyCode::Down if y < 9 => y += 1,
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted().into());
+                        }
                         KeyCode::Char('q') => {
-                            return Err(
-                                io::Error::new(io::ErrorKind::Other, "player interrupted").into()
-                            )
+                            return Err(io::Error::other("player interrupted").into())
+                        }
+                        KeyCode::Char('i') => import = Some(String::new()),
+                        KeyCode::Char('x') => {
+                            if let Some(random) = self.fleet.random(&mut rand::rng()) {
+                                ships = random.asslice().to_vec();
+                            }
                         }
                         KeyCode::Char(' ') => {
-                            let cpos = logic::Position::fromcoords(x, y).unwrap();
-                            for (i, ship) in ships.into_iter().enumerate() {
+                            let cpos = logic::Position::new(x, y).map_err(io::Error::other)?;
+                            for (i, ship) in ships.clone().into_iter().enumerate() {
                                 if ship.into_iter().any(|p| p == cpos) {
-                                    moveship(&mut self.term, &mut x, &mut y, &mut ships, i)?;
+                                    self.moveship(&mut x, &mut y, &mut ships, i)?;
                                     continue;
                                 }
                             }
                         }
-                        KeyCode::Enter => break,
+                        KeyCode::Enter => {
+                            if let Ok(ships) = logic::Ships::validate(&ships, &self.fleet) {
+                                self.cursorpos = (x, y);
+                                self.rejectedships = None;
+                                return Ok(ships);
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -134,459 +867,1101 @@ impl client::UI for Interface {
             }
 
             self.term.draw(|f| {
+                if !fits(f, self.geometry.boardsize()) {
+                    return;
+                }
                 let [boardx, boardy] = logic::Position::fromcoords(x, y).unwrap().toboard();
-                let canvas = canvas::Canvas::default()
+                let canvas = self
+                    .geometry
+                    .canvas()
                     .block(
                         widgets::Block::bordered()
                             .border_type(widgets::BorderType::Thick)
                             .title_bottom(text::Line::raw(format!("{boardx}{boardy}"))),
                     )
-                    .x_bounds([0.0, 9.0])
-                    .y_bounds([0.0, 9.0])
-                    .marker(symbols::Marker::HalfBlock)
                     .paint(|ctx| {
-                        drawships(ctx, &ships);
+                        drawships(ctx, &self.theme, &ships);
                         ctx.draw(&canvas::Points {
                             coords: &[(x as f64, (9 - y) as f64)],
                             color: style::Color::White,
                         });
                     });
 
-                f.render_widget(canvas, centerrectinrect(f.area(), layout::Size::new(12, 7)));
+                let rect = centerrectinrect(f.area(), self.geometry.boardsize());
+                f.render_widget(canvas, rect);
+
+                let mut lines = Vec::new();
+                if invalidfavourite {
+                    lines.push(
+                        text::Line::from("saved layout invalid, using the standard one").yellow(),
+                    );
+                }
+                if let Some(reason) = self.rejectedships {
+                    lines.push(text::Line::from(format!("refused by server: {reason}")).red());
+                }
+                match (&import, logic::Ships::validate(&ships, &self.fleet)) {
+                    (Some(code), _) => lines.push(text::Line::from(format!("import: {code}_"))),
+                    // layout codes imply the classic lengths
+                    (None, Ok(valid)) if self.fleet.lengths() == logic::SHIPLENGTHS => lines
+                        .push(text::Line::from(format!("code: {}", valid.tocode())).dark_gray()),
+                    (None, Ok(_)) => {}
+                    (None, Err(_)) => {}
+                }
+                let rectbelow = layout::Rect {
+                    x: f.area().x,
+                    y: rect.y + rect.height,
+                    width: f.area().width,
+                    height: lines.len() as u16,
+                }
+                .intersection(f.area());
+                f.render_widget(widgets::Paragraph::new(lines).centered(), rectbelow);
             })?;
         }
-
-        self.cursorpos = (x, y);
-        Ok(logic::Ships::try_from(ships).unwrap())
     }
 
     fn displayboard(&mut self, info: client::ClientInfo) -> Result<(), client::UIError<io::Error>> {
+        self.setscreen(Screen::Waiting, [info.name, info.oppname]);
         self.term.draw(|f| {
-            let rect = centerrectinrect(
-                f.area(),
-                layout::Size {
-                    width: 23,
-                    height: 7,
+            rendergame(
+                f,
+                self.geometry,
+                self.view,
+                &self.theme,
+                &info,
+                Extras {
+                    status: true,
+                    ..Extras::default()
                 },
             );
-            let rectleft = layout::Rect {
-                x: rect.x,
-                y: rect.y,
-                width: 11,
-                height: rect.height,
-            };
-            let rectright = layout::Rect {
-                x: rectleft.x + rectleft.width,
-                y: rect.y,
-                width: 12,
-                height: rect.height,
-            };
-            let rectbottom = layout::Rect {
-                x: rectleft.x,
-                y: rectleft.y + rectleft.height,
-                width: rect.width,
-                height: f.area().height - rectleft.y - rectleft.height,
-            };
+        })?;
+        Ok(())
+    }
 
-            let blockleft = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .borders(widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::BOTTOM);
+    fn animateincoming(
+        &mut self,
+        info: client::ClientInfo,
+        pos: logic::Position,
+    ) -> Result<(), client::UIError<io::Error>> {
+        if !self.animations {
+            return Ok(());
+        }
 
-            let blockrightsymbols = symbols::border::Set {
-                top_left: symbols::line::THICK_HORIZONTAL_DOWN,
-                bottom_left: symbols::line::THICK_HORIZONTAL_UP,
-                ..symbols::border::THICK
-            };
+        let (x, y) = pos.coords();
+        let impactcolor = match info.selfhits[y as usize][x as usize] {
+            Some(logic::AttackInfo::Hit(_)) => self.theme.hit,
+            _ => self.theme.miss,
+        };
 
-            let blockright = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .border_set(blockrightsymbols);
+        for cy in 0..y {
+            self.term.draw(|f| {
+                rendergame(
+                    f,
+                    self.geometry,
+                    self.view,
+                    &self.theme,
+                    &info,
+                    Extras {
+                        shot: Some(((x, cy), style::Color::Yellow)),
+                        ..Extras::default()
+                    },
+                );
+            })?;
+            thread::sleep(ANIMATIONTRAVELFRAME);
+        }
+        for color in [style::Color::Yellow, impactcolor, style::Color::Yellow] {
+            self.term.draw(|f| {
+                rendergame(
+                    f,
+                    self.geometry,
+                    self.view,
+                    &self.theme,
+                    &info,
+                    Extras {
+                        shot: Some(((x, y), color)),
+                        ..Extras::default()
+                    },
+                );
+            })?;
+            thread::sleep(ANIMATIONIMPACTFRAME);
+        }
+        Ok(())
+    }
 
-            let canvasleft = canvas::Canvas::default()
-                .block(blockleft)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawships(ctx, info.ships);
-                    drawhits(ctx, info.selfhits);
-                });
-
-            let canvasright = canvas::Canvas::default()
-                .block(blockright)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawhits(ctx, info.opphits);
-                });
+    fn invalidships(
+        &mut self,
+        _info: client::ClientInfo,
+        reason: logic::Error,
+    ) -> Result<(), client::UIError<io::Error>> {
+        self.rejectedships = Some(reason);
+        if reason == logic::Error::ShipsTouching {
+            self.fleet = self.fleet.clone().notouch(true);
+        }
+        Ok(())
+    }
 
-            f.render_widget(canvasleft, rectleft);
-            f.render_widget(canvasright, rectright);
-            let msg: Vec<_> = info
-                .message
-                .iter()
-                .rev()
-                .cloned()
-                .map(text::Line::try_from)
-                .filter_map(Result::ok)
-                .map(|line| line.style(style::Style::new().gray()))
-                .collect();
-            f.render_widget(
-                widgets::Paragraph::new(msg).wrap(widgets::Wrap { trim: true }),
-                rectbottom,
-            )
-        })?;
+    fn invalidtarget(
+        &mut self,
+        _info: client::ClientInfo,
+        pos: logic::Position,
+    ) -> Result<(), client::UIError<io::Error>> {
+        self.rejected = Some(pos.coords());
+        self.cursorpos = pos.coords();
         Ok(())
     }
 
+    fn rematch(&mut self, info: client::ClientInfo) -> Result<bool, client::UIError<io::Error>> {
+        let victory = self.screen == Some(Screen::Victory);
+        self.rematchoffer = true;
+        let answer = loop {
+            if victory {
+                client::UI::displayvictory(self, info)?;
+            } else {
+                client::UI::displayloss(self, info)?;
+            }
+
+            match event::read()? {
+                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
+                    match kevent.code {
+                        KeyCode::Char('y') => break true,
+                        KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => break false,
+                        KeyCode::Char('p') => self.replay(info)?,
+                        KeyCode::Char('o') => self.screenshot(info),
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted().into());
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        };
+        self.rematchoffer = false;
+        Ok(answer)
+    }
+
+    fn finish(
+        &mut self,
+        info: client::ClientInfo,
+        victory: bool,
+    ) -> Result<(), client::UIError<io::Error>> {
+        loop {
+            if victory {
+                client::UI::displayvictory(self, info)?;
+            } else {
+                client::UI::displayloss(self, info)?;
+            }
+
+            match event::read()? {
+                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
+                    match kevent.code {
+                        KeyCode::Char('p') => self.replay(info)?,
+                        KeyCode::Char('o') => self.screenshot(info),
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted().into());
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return Ok(()),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn selecttarget(
         &mut self,
         info: client::ClientInfo,
     ) -> Result<logic::Position, client::UIError<io::Error>> {
+        self.setscreen(Screen::Turn, [info.name, info.oppname]);
         let (mut x, mut y) = self.cursorpos;
-
-        while let Ok(true) = event::poll(time::Duration::from_secs(0)) {
-            event::read()?;
+        let deadline = info
+            .remaining
+            .map(|remaining| time::Instant::now() + remaining);
+
+        // keys typed ahead count once the turn was shown
+        if !std::mem::take(&mut self.yielded) {
+            while let Ok(true) = event::poll(time::Duration::from_secs(0)) {
+                event::read()?;
+            }
         }
 
+        // where the target board was last drawn, to map clicks onto it
+        let mut target = None;
+        // `r` asks to surrender, a second `r` confirms
+        let mut resigning = false;
         loop {
             let mut checkready = false;
-            match event::read()? {
-                event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => {
+            // redraw at least once per tick, even without input
+            let event = nexttick(&mut Crossterm, self.tickrate)?;
+            // the client gets a turn once a tick passes in peace or chat is
+            // finished, to send it and show what came in
+            let mut yielding = event.is_none() && !resigning;
+            match event {
+                Some(event::Event::Key(kevent))
+                    if kevent.kind == KeyEventKind::Press && self.draft.is_some() =>
+                {
+                    yielding = self.typechat(kevent)?;
+                }
+                Some(event::Event::Key(kevent))
+                    if kevent.kind == KeyEventKind::Press && self.pausekey(kevent.code, info) =>
+                {
+                    // handed to the client right away
+                    yielding = true;
+                }
+                Some(event::Event::Key(kevent)) if kevent.kind == KeyEventKind::Press => {
+                    let confirming = std::mem::take(&mut resigning);
                     match kevent.code {
+                        KeyCode::Char('r') if confirming => {
+                            self.surrender = true;
+                            return logic::Position::new(x, y)
+                                .map_err(|err| io::Error::other(err).into());
+                        }
+                        KeyCode::Char('r') => resigning = true,
                         KeyCode::Char('a') | KeyCode::Left if x > 0 => x -= 1,
                         KeyCode::Char('w') | KeyCode::Up if y > 0 => y -= 1,
                         KeyCode::Char('d') | KeyCode::Right if x < 9 => x += 1,
                         KeyCode::Char('s') | KeyCode::Down if y < 9 => y += 1,
+                        KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Err(interrupted().into());
+                        }
                         KeyCode::Char('q') => {
-                            return Err(
-                                io::Error::new(io::ErrorKind::Other, "player interrupted").into()
-                            )
+                            return Err(io::Error::other("player interrupted").into())
                         }
                         KeyCode::Char(' ') => checkready = true,
+                        KeyCode::Char('m') => self.view.splitlog ^= true,
+                        KeyCode::Char('l') => self.view.lastshots ^= true,
+                        KeyCode::Char('g') => self.view.glyphs ^= true,
+                        KeyCode::Char('o') => self.screenshot(info),
+                        KeyCode::Char('t') if self.outbox.is_none() => {
+                            self.draft = Some(String::new())
+                        }
                         _ => {}
                     }
                 }
+                // a click moves the cursor, a click on the cursor fires
+                Some(event::Event::Mouse(mevent))
+                    if mevent.kind == MouseEventKind::Down(MouseButton::Left) =>
+                {
+                    let cell = target.and_then(|area| {
+                        self.geometry
+                            .cellat(area, mevent.column, mevent.row, (x, y))
+                    });
+                    match cell {
+                        Some(cell) if cell == (x, y) => checkready = true,
+                        Some(cell) => (x, y) = cell,
+                        None => {}
+                    }
+                }
                 _ => {}
             }
 
-            let valid = info.opphits[y as usize][x as usize].is_none();
+            if self.rejected.is_some_and(|rejected| rejected != (x, y)) {
+                self.rejected = None;
+            }
+            let pos = logic::Position::new(x, y).map_err(io::Error::other)?;
+            let valid = logic::legaltargets(info.opphits).contains(&pos)
+                && self.rejected.is_none()
+                && !self.salvo.contains(&(x, y));
+            let mut header: Vec<_> = self.noticeline().into_iter().collect();
+            if let Some(deadline) = deadline {
+                let secs = deadline
+                    .saturating_duration_since(time::Instant::now())
+                    .as_secs();
+                let line = text::Line::from(format!("{secs}s left"));
+                header.insert(0, if secs <= 10 { line.light_red() } else { line });
+            }
+            if resigning {
+                header.push(text::Line::from("press r again to surrender").light_red());
+            }
+            header.extend(Interface::pauseline(info));
+            header.extend(self.draftline());
             if valid && checkready {
                 self.cursorpos = (x, y);
-                return Ok(logic::Position::fromcoords(x, y).unwrap());
+                return Ok(pos);
             }
 
             self.term.draw(|f| {
-                let rect = centerrectinrect(
-                    f.area(),
-                    layout::Size {
-                        width: 23,
-                        height: 7,
+                target = rendergame(
+                    f,
+                    self.geometry,
+                    self.view,
+                    &self.theme,
+                    &info,
+                    Extras {
+                        border: Some(if valid {
+                            self.theme.valid
+                        } else {
+                            self.theme.invalid
+                        }),
+                        title: Some("select"),
+                        status: true,
+                        header: header.clone(),
+                        marked: self.salvo.clone(),
+                        cursor: Some((
+                            (x, y),
+                            if self.rejected.is_some() {
+                                self.theme.invalid
+                            } else {
+                                style::Color::White
+                            },
+                        )),
+                        ..Extras::default()
                     },
                 );
-                let rectleft = layout::Rect {
-                    x: rect.x,
-                    y: rect.y,
-                    width: 11,
-                    height: rect.height,
-                };
-                let rectright = layout::Rect {
-                    x: rectleft.x + rectleft.width,
-                    y: rect.y,
-                    width: 12,
-                    height: rect.height,
-                };
-
-                let rectbottom = layout::Rect {
-                    x: rectleft.x,
-                    y: rectleft.y + rectleft.height,
-                    width: rect.width,
-                    height: f.area().height - rectleft.y - rectleft.height,
-                };
+            })?;
+            if yielding {
+                self.cursorpos = (x, y);
+                self.yielded = true;
+                return Ok(pos);
+            }
+        }
+    }
 
-                let blockleft = widgets::Block::bordered()
-                    .border_type(widgets::BorderType::Thick)
-                    .borders(
-                        widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::BOTTOM,
-                    )
-                    .border_style(if valid {
-                        style::Style::new().green()
-                    } else {
-                        style::Style::new().red()
-                    });
+    fn takechat(&mut self) -> Option<String> {
+        self.outbox.take()
+    }
 
-                let blockrightsymbols = symbols::border::Set {
-                    top_left: symbols::line::THICK_HORIZONTAL_DOWN,
-                    bottom_left: symbols::line::THICK_HORIZONTAL_UP,
-                    ..symbols::border::THICK
-                };
-
-                let blockright = widgets::Block::bordered()
-                    .title("select")
-                    .border_type(widgets::BorderType::Thick)
-                    .border_set(blockrightsymbols)
-                    .border_style(if valid {
-                        style::Style::new().green()
-                    } else {
-                        style::Style::new().red()
-                    });
+    fn pausing(&mut self) -> Option<bool> {
+        self.pause.take()
+    }
 
-                let canvasleft = canvas::Canvas::default()
-                    .block(blockleft)
-                    .x_bounds([0.0, 9.0])
-                    .y_bounds([0.0, 9.0])
-                    .marker(symbols::Marker::HalfBlock)
-                    .paint(|ctx| {
-                        drawships(ctx, info.ships);
-                        drawhits(ctx, info.selfhits);
-                    });
+    fn yielded(&mut self) -> bool {
+        self.yielded
+    }
 
-                let [boardx, boardy] = logic::Position::fromcoords(x, y).unwrap().toboard();
-                let canvasright = canvas::Canvas::default()
-                    .block(blockright.title_bottom(format! {"{boardx}{boardy}"}))
-                    .x_bounds([0.0, 9.0])
-                    .y_bounds([0.0, 9.0])
-                    .marker(symbols::Marker::HalfBlock)
-                    .paint(|ctx| {
-                        drawhits(ctx, info.opphits);
-                        ctx.draw(&canvas::Points {
-                            coords: &[(x as f64, (9 - y) as f64)],
-                            color: style::Color::White,
-                        });
-                    });
+    fn idle(&mut self, info: client::ClientInfo) -> Result<(), client::UIError<io::Error>> {
+        self.setscreen(Screen::Waiting, [info.name, info.oppname]);
+        let oppturn = info.turn == Some(client::Origin::Opponent);
+        while event::poll(time::Duration::ZERO)? {
+            let event::Event::Key(kevent) = event::read()? else {
+                continue;
+            };
+            if kevent.kind != KeyEventKind::Press {
+                continue;
+            }
+            if self.draft.is_some() {
+                self.typechat(kevent)?;
+                continue;
+            }
+            if self.pausekey(kevent.code, info) {
+                continue;
+            }
+            let confirming = std::mem::take(&mut self.resigning);
+            match kevent.code {
+                KeyCode::Char('r') if confirming && oppturn => self.surrender = true,
+                KeyCode::Char('r') => self.resigning = oppturn,
+                KeyCode::Char('c') if kevent.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err(interrupted().into());
+                }
+                KeyCode::Char('q') => return Err(io::Error::other("player interrupted").into()),
+                KeyCode::Char('m') => self.view.splitlog ^= true,
+                KeyCode::Char('l') => self.view.lastshots ^= true,
+                KeyCode::Char('g') => self.view.glyphs ^= true,
+                KeyCode::Char('o') => self.screenshot(info),
+                KeyCode::Char('t') if self.outbox.is_none() => self.draft = Some(String::new()),
+                _ => {}
+            }
+        }
+        self.resigning &= oppturn;
+        let mut header: Vec<_> = self.noticeline().into_iter().collect();
+        if self.resigning {
+            header.push(text::Line::from("press r again to surrender").light_red());
+        }
+        header.extend(Interface::pauseline(info));
+        header.extend(self.draftline());
+        self.term.draw(|f| {
+            rendergame(
+                f,
+                self.geometry,
+                self.view,
+                &self.theme,
+                &info,
+                Extras {
+                    status: true,
+                    header,
+                    ..Extras::default()
+                },
+            );
+        })?;
+        Ok(())
+    }
 
-                f.render_widget(canvasleft, rectleft);
-                f.render_widget(canvasright, rectright);
-                let msg: Vec<_> = info
-                    .message
-                    .iter()
-                    .rev()
-                    .cloned()
-                    .map(text::Line::try_from)
-                    .filter_map(Result::ok)
-                    .map(|line| line.style(style::Style::new().gray()))
-                    .collect();
-                f.render_widget(
-                    widgets::Paragraph::new(msg).wrap(widgets::Wrap { trim: true }),
-                    rectbottom,
-                )
-            })?;
+    fn surrendering(&mut self) -> bool {
+        std::mem::take(&mut self.surrender)
+    }
+
+    fn selecttargets(
+        &mut self,
+        info: client::ClientInfo,
+        count: u8,
+    ) -> Result<Vec<logic::Position>, client::UIError<io::Error>> {
+        // picks made before the selection was cut short still stand
+        if !self.yielded {
+            self.salvo.clear();
+        }
+        let mut targets: Vec<_> = self
+            .salvo
+            .iter()
+            .filter_map(|&(x, y)| logic::Position::fromcoords(x, y))
+            .collect();
+        while targets.len() < count.into() {
+            let target = self.selecttarget(info)?;
+            if self.surrender || self.yielded {
+                break;
+            }
+            self.salvo.push(target.coords());
+            targets.push(target);
+        }
+        if !self.yielded {
+            self.salvo.clear();
         }
+        Ok(targets)
     }
 
     fn displayvictory(
         &mut self,
         info: client::ClientInfo,
     ) -> Result<(), client::UIError<io::Error>> {
-        const MESSAGE: &str = "V I C T O R Y";
+        let message = if info.spectating {
+            "F I R S T   W I N S"
+        } else {
+            "V I C T O R Y"
+        };
+        self.setscreen(Screen::Victory, [info.name, info.oppname]);
 
         while let Ok(true) = event::poll(time::Duration::from_secs(0)) {
             event::read()?;
         }
 
+        let mut header = summarylines(info.summary, true);
+        if let Some(reason) = info.oppleft {
+            header.insert(0, text::Line::from(format!("opponent {reason}")).yellow());
+        }
+        header.extend(self.noticeline());
+        header.extend(self.rematchline());
+        header.push(text::Line::from("accuracy").dark_gray());
+
         self.term.draw(|f| {
-            let rect = centerrectinrect(
-                f.area(),
-                layout::Size {
-                    width: 23,
-                    height: 7,
+            rendergame(
+                f,
+                self.geometry,
+                self.view,
+                &self.theme,
+                &info,
+                Extras {
+                    header,
+                    trend: client::accuracytrend(info.shots),
+                    banner: Some((message, style::Color::Yellow)),
+                    ..Extras::default()
                 },
             );
-            let rectleft = layout::Rect {
-                x: rect.x,
-                y: rect.y,
-                width: 11,
-                height: rect.height,
-            };
-            let rectright = layout::Rect {
-                x: rectleft.x + rectleft.width,
-                y: rect.y,
-                width: 12,
-                height: rect.height,
-            };
-            let rectbottom = layout::Rect {
-                x: rectleft.x,
-                y: rectleft.y + rectleft.height,
-                width: rect.width,
-                height: f.area().height - rectleft.y - rectleft.height,
-            };
-            let rectmessage = centerrectinrect(
-                rect,
-                layout::Size {
-                    width: (MESSAGE.len() + 2) as u16,
-                    height: 3,
-                },
-            );
-
-            let blockleft = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .borders(widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::BOTTOM);
-
-            let blockrightsymbols = symbols::border::Set {
-                top_left: symbols::line::THICK_HORIZONTAL_DOWN,
-                bottom_left: symbols::line::THICK_HORIZONTAL_UP,
-                ..symbols::border::THICK
-            };
-
-            let blockright = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .border_set(blockrightsymbols);
-
-            let canvasleft = canvas::Canvas::default()
-                .block(blockleft)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawships(ctx, info.ships);
-                    drawhits(ctx, info.selfhits);
-                });
-
-            let canvasright = canvas::Canvas::default()
-                .block(blockright)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawhits(ctx, info.opphits);
-                });
-
-            f.render_widget(canvasleft, rectleft);
-            f.render_widget(canvasright, rectright);
-            let msg: Vec<_> = info
-                .message
-                .iter()
-                .rev()
-                .cloned()
-                .map(text::Line::try_from)
-                .filter_map(Result::ok)
-                .map(|line| line.style(style::Style::new().gray()))
-                .collect();
-            f.render_widget(
-                widgets::Paragraph::new(msg).wrap(widgets::Wrap { trim: true }),
-                rectbottom,
-            );
-            f.render_widget(widgets::Clear, rectmessage);
-            let rectmessage = layout::Rect {
-                x: rectmessage.x + 1,
-                y: rectmessage.y + 1,
-                width: rectmessage.width - 2,
-                height: 1,
-            };
-            f.render_widget(
-                widgets::Paragraph::new(MESSAGE).bold().centered().yellow(),
-                rectmessage,
-            );
         })?;
 
         Ok(())
     }
 
     fn displayloss(&mut self, info: client::ClientInfo) -> Result<(), client::UIError<io::Error>> {
-        const MESSAGE: &str = "L O S S";
+        let message = if info.spectating {
+            "S E C O N D   W I N S"
+        } else {
+            "L O S S"
+        };
+        self.setscreen(Screen::Loss, [info.name, info.oppname]);
 
         while let Ok(true) = event::poll(time::Duration::from_secs(0)) {
             event::read()?;
         }
 
+        let mut header = summarylines(info.summary, false);
+        header.extend(self.noticeline());
+        header.extend(self.rematchline());
+        header.push(text::Line::from("accuracy").dark_gray());
+
         self.term.draw(|f| {
-            let rect = centerrectinrect(
-                f.area(),
-                layout::Size {
-                    width: 23,
-                    height: 7,
-                },
-            );
-            let rectleft = layout::Rect {
-                x: rect.x,
-                y: rect.y,
-                width: 11,
-                height: rect.height,
-            };
-            let rectright = layout::Rect {
-                x: rectleft.x + rectleft.width,
-                y: rect.y,
-                width: 12,
-                height: rect.height,
-            };
-            let rectbottom = layout::Rect {
-                x: rectleft.x,
-                y: rectleft.y + rectleft.height,
-                width: rect.width,
-                height: f.area().height - rectleft.y - rectleft.height,
-            };
-            let rectmessage = centerrectinrect(
-                rect,
-                layout::Size {
-                    width: (MESSAGE.len() + 2) as u16,
-                    height: 3,
+            rendergame(
+                f,
+                self.geometry,
+                self.view,
+                &self.theme,
+                &info,
+                Extras {
+                    header,
+                    trend: client::accuracytrend(info.shots),
+                    banner: Some((message, style::Color::Cyan)),
+                    ..Extras::default()
                 },
             );
+        })?;
 
-            let blockleft = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .borders(widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::BOTTOM);
+        Ok(())
+    }
+}
 
-            let blockrightsymbols = symbols::border::Set {
-                top_left: symbols::line::THICK_HORIZONTAL_DOWN,
-                bottom_left: symbols::line::THICK_HORIZONTAL_UP,
-                ..symbols::border::THICK
-            };
+/// display toggles the player may flip on any game screen
+#[derive(Debug, Clone, Copy, Default)]
+struct View {
+    /// message log split by who acted, below the respective board
+    splitlog: bool,
+    /// most recent shots listed in the top right corner, toggled with `l`
+    lastshots: bool,
+    /// hits and misses marked with distinct glyphs on top of their color
+    glyphs: bool,
+}
 
-            let blockright = widgets::Block::bordered()
-                .border_type(widgets::BorderType::Thick)
-                .border_set(blockrightsymbols);
+/// shots listed by the `l` overlay
+const LASTSHOTS: usize = 5;
+
+/// chat lines shown above the message log
+const CHATLINES: usize = 3;
+
+/// columns inside the border of either board, the most a title can take up
+const BOARDTITLEWIDTH: usize = 10;
+
+/// screen specifics drawn on top of the shared game layout
+#[derive(Default)]
+struct Extras {
+    /// colors the borders of both boards
+    border: Option<style::Color>,
+    /// title of the target board, instead of the opponent's name
+    title: Option<&'static str>,
+    /// highlighted cell on the own board
+    shot: Option<((u8, u8), style::Color)>,
+    /// highlighted cell on the target board, also named in its border
+    cursor: Option<((u8, u8), style::Color)>,
+    /// cells marked on the target board besides the cursor
+    marked: Vec<(u8, u8)>,
+    /// whose turn it is and the ships afloat, in a row of its own above the
+    /// header lines
+    status: bool,
+    /// lines above the message log
+    header: Vec<text::Line<'static>>,
+    /// message boxed in the center of the boards
+    banner: Option<(&'static str, style::Color)>,
+    /// one row chart below the header lines
+    trend: Vec<u8>,
+}
 
-            let canvasleft = canvas::Canvas::default()
-                .block(blockleft)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawships(ctx, info.ships);
-                    drawhits(ctx, info.selfhits);
-                });
-
-            let canvasright = canvas::Canvas::default()
-                .block(blockright)
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    drawhits(ctx, info.opphits);
-                });
+/// both boards side by side with the message log below, returns the area
+/// the target board was drawn into
+fn rendergame(
+    f: &mut ratatui::Frame,
+    geometry: Geometry,
+    view: View,
+    theme: &Theme,
+    info: &client::ClientInfo,
+    extras: Extras,
+) -> Option<layout::Rect> {
+    if !fits(f, geometry.size()) {
+        return None;
+    }
+    let outer = centerrectinrect(f.area(), geometry.size());
+    // column letters above, row numbers to the left of the boards
+    let rect = layout::Rect {
+        x: outer.x + LABELWIDTH,
+        y: outer.y + 1,
+        width: outer.width - LABELWIDTH,
+        height: outer.height - 1,
+    };
+    drawlabels(f, geometry, rect);
+    let rectleft = layout::Rect {
+        x: rect.x,
+        y: rect.y,
+        width: 11,
+        height: rect.height,
+    };
+    let rectright = layout::Rect {
+        x: rectleft.x + rectleft.width,
+        y: rect.y,
+        width: 12,
+        height: rect.height,
+    };
+    let rectbottom = layout::Rect {
+        x: rectleft.x,
+        y: rectleft.y + rectleft.height,
+        width: rect.width,
+        height: f.area().height - rectleft.y - rectleft.height,
+    };
 
-            f.render_widget(canvasleft, rectleft);
-            f.render_widget(canvasright, rectright);
-            let msg: Vec<_> = info
-                .message
-                .iter()
-                .rev()
-                .cloned()
-                .map(text::Line::try_from)
-                .filter_map(Result::ok)
-                .map(|line| line.style(style::Style::new().gray()))
-                .collect();
-            f.render_widget(
-                widgets::Paragraph::new(msg).wrap(widgets::Wrap { trim: true }),
-                rectbottom,
+    let borderstyle = extras
+        .border
+        .map_or(style::Style::new(), |color| style::Style::new().fg(color));
+
+    let bottomleft = if info.spectating {
+        String::from("first player")
+    } else {
+        format!("{} cells", logic::survivingcells(info.ships, info.selfhits))
+    };
+    // spectators are told whose board is which at the bottom instead
+    let [titleleft, titleright] = if info.spectating {
+        [String::new(), String::new()]
+    } else {
+        boardtitles([info.name, info.oppname], BOARDTITLEWIDTH)
+    };
+    let blockleft = widgets::Block::bordered()
+        .title(titleleft)
+        .title_bottom(text::Line::from(bottomleft).dark_gray())
+        .border_type(widgets::BorderType::Thick)
+        .borders(widgets::Borders::TOP | widgets::Borders::LEFT | widgets::Borders::BOTTOM)
+        .border_style(borderstyle);
+
+    let blockrightsymbols = symbols::border::Set {
+        top_left: symbols::line::THICK_HORIZONTAL_DOWN,
+        bottom_left: symbols::line::THICK_HORIZONTAL_UP,
+        ..symbols::border::THICK
+    };
+
+    let mut blockright = widgets::Block::bordered()
+        .title(extras.title.map_or(titleright, String::from))
+        .title_bottom(text::Line::from(format!("{} left", info.oppremaining)).right_aligned())
+        .border_type(widgets::BorderType::Thick)
+        .border_set(blockrightsymbols)
+        .border_style(borderstyle);
+    if let Some(((x, y), _)) = extras.cursor {
+        let [boardx, boardy] = logic::Position::fromcoords(x, y).unwrap().toboard();
+        blockright = blockright.title_bottom(format!("{boardx}{boardy}"));
+    }
+
+    let canvasleft = geometry.canvas().block(blockleft).paint(|ctx| {
+        if let Some([first, _]) = info.fleets {
+            drawships(ctx, theme, first.asslice());
+        } else if !info.spectating {
+            drawships(ctx, theme, info.ships);
+        }
+        drawhits(ctx, theme, info.selfhits);
+        if view.glyphs {
+            drawglyphs(ctx, theme, geometry, info.selfhits);
+        }
+        if let Some(((x, y), color)) = extras.shot {
+            ctx.draw(&canvas::Points {
+                coords: &[(x as f64, (9 - y) as f64)],
+                color,
+            });
+        }
+    });
+
+    let targetcanvas = blockright.inner(rectright);
+    let canvasright = geometry.canvas().block(blockright).paint(|ctx| {
+        if let Some([_, second]) = info.fleets {
+            drawships(ctx, theme, second.asslice());
+        }
+        drawhits(ctx, theme, info.opphits);
+        if view.glyphs {
+            drawglyphs(ctx, theme, geometry, info.opphits);
+        }
+        let marked: Vec<_> = extras
+            .marked
+            .iter()
+            .map(|&(x, y)| (x as f64, (9 - y) as f64))
+            .collect();
+        ctx.draw(&canvas::Points {
+            coords: &marked,
+            color: style::Color::Yellow,
+        });
+        if let Some(((x, y), color)) = extras.cursor {
+            ctx.draw(&canvas::Points {
+                coords: &[(x as f64, (9 - y) as f64)],
+                color,
+            });
+        }
+    });
+
+    f.render_widget(canvasleft, rectleft);
+    f.render_widget(canvasright, rectright);
+    let rectstatus = layout::Rect {
+        height: u16::min(u16::from(extras.status), rectbottom.height),
+        ..rectbottom
+    };
+    let rectbottom = layout::Rect {
+        y: rectstatus.y + rectstatus.height,
+        height: rectbottom.height - rectstatus.height,
+        ..rectbottom
+    };
+    if rectstatus.height > 0 {
+        for line in statuslines(info) {
+            f.render_widget(widgets::Paragraph::new(line), rectstatus);
+        }
+    }
+    let mut header = extras.header;
+    if let Some(left) = info.oppaway {
+        let lines = [
+            "opponent disconnected,".to_owned(),
+            format!("waiting {}s…", left.as_secs()),
+        ];
+        header.splice(0..0, lines.map(|line| text::Line::from(line).light_red()));
+    }
+    let trendheight = u16::from(!extras.trend.is_empty());
+    let rectheader = layout::Rect {
+        height: u16::min(header.len() as u16 + trendheight, rectbottom.height),
+        ..rectbottom
+    };
+    let chat = chatlines(info.chat, CHATLINES);
+    let rectchat = layout::Rect {
+        y: rectheader.y + rectheader.height,
+        height: u16::min(chat.len() as u16, rectbottom.height - rectheader.height),
+        ..rectbottom
+    };
+    let rectlog = layout::Rect {
+        y: rectchat.y + rectchat.height,
+        height: rectbottom.height - rectheader.height - rectchat.height,
+        ..rectbottom
+    };
+    f.render_widget(widgets::Paragraph::new(header), rectheader);
+    f.render_widget(widgets::Paragraph::new(chat), rectchat);
+    if trendheight > 0 && rectheader.height > 0 {
+        let recttrend = layout::Rect {
+            y: rectheader.y + rectheader.height - 1,
+            height: 1,
+            ..rectheader
+        };
+        // squeeze long games into the available width
+        let len = extras.trend.len();
+        let width = usize::min(len, recttrend.width as usize);
+        let data: Vec<u64> = (0..width)
+            .map(|i| extras.trend[i * len / width] as u64)
+            .collect();
+        f.render_widget(
+            widgets::Sparkline::default()
+                .data(&data)
+                .max(100)
+                .style(style::Style::new().yellow()),
+            recttrend,
+        );
+    }
+    if view.lastshots {
+        let lines = shotlines(info.shots, LASTSHOTS);
+        let width = 6;
+        let area = f.area();
+        let rectshots = layout::Rect {
+            x: area.x + area.width.saturating_sub(width),
+            y: area.y,
+            width: u16::min(width, area.width),
+            height: u16::min(lines.len() as u16, area.height),
+        };
+        f.render_widget(widgets::Paragraph::new(lines), rectshots);
+    }
+    if view.splitlog {
+        // shots at the own board below it, own shots below the target board
+        let rectlogleft = layout::Rect {
+            width: rectleft.width,
+            ..rectlog
+        };
+        let rectlogright = layout::Rect {
+            x: rectright.x,
+            width: rectright.width,
+            ..rectlog
+        };
+        let msgleft = messagelines(*info, rectlogleft.height, |message| {
+            message.origin() != Some(client::Origin::Own)
+        });
+        let msgright = messagelines(*info, rectlogright.height, |message| {
+            message.origin() == Some(client::Origin::Own)
+        });
+        f.render_widget(
+            widgets::Paragraph::new(msgleft).wrap(widgets::Wrap { trim: true }),
+            rectlogleft,
+        );
+        f.render_widget(
+            widgets::Paragraph::new(msgright).wrap(widgets::Wrap { trim: true }),
+            rectlogright,
+        );
+    } else {
+        let msg = messagelines(*info, rectlog.height, |_| true);
+        f.render_widget(
+            widgets::Paragraph::new(msg).wrap(widgets::Wrap { trim: true }),
+            rectlog,
+        );
+    }
+
+    if let Some((message, color)) = extras.banner {
+        let rectmessage = centerrectinrect(
+            rect,
+            layout::Size {
+                width: (message.chars().count() + 2) as u16,
+                height: 3,
+            },
+        );
+        f.render_widget(widgets::Clear, rectmessage);
+        let rectmessage = layout::Rect {
+            x: rectmessage.x + 1,
+            y: rectmessage.y + 1,
+            width: rectmessage.width - 2,
+            height: 1,
+        };
+        f.render_widget(
+            widgets::Paragraph::new(message).bold().centered().fg(color),
+            rectmessage,
+        );
+    }
+
+    Some(targetcanvas)
+}
+
+/// whose turn it is, if anyone's, to the left of the ships each side has
+/// afloat; both fit below the boards
+fn statuslines(info: &client::ClientInfo) -> [text::Line<'static>; 2] {
+    let turn = match info.turn {
+        Some(client::Origin::Own) => text::Line::from("your turn").cyan(),
+        Some(client::Origin::Opponent) => text::Line::from("their turn").yellow(),
+        None => text::Line::default(),
+    };
+    let afloat = text::Line::from(vec![
+        text::Span::from("afloat "),
+        text::Span::from(info.selfremaining.to_string()).cyan(),
+        text::Span::from(" v "),
+        text::Span::from(info.oppremaining.to_string()).yellow(),
+    ])
+    .right_aligned();
+    [turn, afloat]
+}
+
+/// the last `count` chat lines, oldest first
+fn chatlines(chat: &[(client::Origin, String)], count: usize) -> Vec<text::Line<'static>> {
+    chat[chat.len().saturating_sub(count)..]
+        .iter()
+        .map(|(origin, text)| match origin {
+            client::Origin::Own => text::Line::from(format!("you: {text}")).cyan(),
+            client::Origin::Opponent => text::Line::from(format!("opp: {text}")).yellow(),
+        })
+        .collect()
+}
+
+/// newest messages passing `filter` first, as many as fit into `height`
+/// lines; if older ones are cut off, the top line says how many
+fn messagelines(
+    info: client::ClientInfo,
+    height: u16,
+    filter: impl Fn(client::Message) -> bool,
+) -> Vec<text::Line<'static>> {
+    let height = height as usize;
+    let mut lines: Vec<_> = info
+        .message
+        .iter()
+        .rev()
+        .filter(|&&message| filter(message))
+        .filter_map(|&message| match message {
+            client::Message::SuccessfullyConnected => Some(text::Line::from(format!(
+                "connected to {} {}",
+                info.server.name(),
+                info.server.version()
+            ))),
+            message => text::Line::try_from(message).ok(),
+        })
+        .map(|line| line.style(style::Style::new().gray()))
+        .collect();
+    if lines.len() > height {
+        let total = lines.len();
+        lines.truncate(height.saturating_sub(1));
+        let hidden = total - lines.len();
+        if height > 0 {
+            lines.insert(
+                0,
+                text::Line::from(format!("({hidden} earlier messages)"))
+                    .style(style::Style::new().dark_gray().italic()),
             );
-            f.render_widget(widgets::Clear, rectmessage);
-            let rectmessage = layout::Rect {
-                x: rectmessage.x + 1,
-                y: rectmessage.y + 1,
-                width: rectmessage.width - 2,
-                height: 1,
+        }
+    }
+    lines
+}
+
+/// the last `count` shots, newest first, as board notation and outcome;
+/// incoming shots are cyan like the opponent in the message log
+fn shotlines(shots: &[client::Shot], count: usize) -> Vec<text::Line<'static>> {
+    shots
+        .iter()
+        .rev()
+        .take(count)
+        .map(|shot| {
+            let [x, y] = shot.pos.toboard();
+            let mark = match shot.info {
+                logic::AttackInfo::Hit(_) => "✓",
+                logic::AttackInfo::Miss => "✗",
             };
-            f.render_widget(
-                widgets::Paragraph::new(MESSAGE).bold().centered().cyan(),
-                rectmessage,
-            );
-        })?;
+            let line = text::Line::from(format!("{x}{y} {mark}"));
+            if shot.incoming {
+                line.cyan()
+            } else {
+                line
+            }
+        })
+        .collect()
+}
 
-        Ok(())
+fn summarylines(summary: Option<&logic::GameSummary>, victory: bool) -> Vec<text::Line<'static>> {
+    let Some(summary) = summary else {
+        return Vec::new();
+    };
+    let [own, opp] = summary.perspective(victory);
+    vec![
+        text::Line::from(format!(
+            "you  {:>2} hits {:>2} misses",
+            own.hits, own.misses
+        )),
+        text::Line::from(format!(
+            "opp. {:>2} hits {:>2} misses",
+            opp.hits, opp.misses
+        )),
+        text::Line::from(format!("{} turns", summary.turns)),
+    ]
+}
+
+/// both boards as plain text, colored with ANSI escapes the way they are drawn
+fn ansiboards(theme: &Theme, info: &client::ClientInfo) -> String {
+    use crossterm::style::{ResetColor, SetForegroundColor};
+
+    let grid = |fleet: &[logic::Ship]| {
+        let mut ships = [[None; 10]; 10];
+        for (ship, color) in Iterator::zip(fleet.iter(), theme.ships) {
+            for pos in *ship {
+                let (x, y) = pos.coords();
+                ships[y as usize][x as usize] = Some(color);
+            }
+        }
+        ships
+    };
+    // an admin spectator sees both fleets, anyone else their own at most
+    let (ships, oppships) = match info.fleets {
+        Some([first, second]) => (grid(first.asslice()), grid(second.asslice())),
+        None if info.spectating => (grid(&[]), grid(&[])),
+        None => (grid(info.ships), grid(&[])),
+    };
+    let cell = |hit: Option<logic::AttackInfo>, ship: Option<style::Color>| match (hit, ship) {
+        (Some(logic::AttackInfo::Hit(_)), _) => (theme.hit, 'X'),
+        (Some(logic::AttackInfo::Miss), _) => (theme.miss, 'o'),
+        (None, Some(color)) => (color, '#'),
+        (None, None) => (style::Color::DarkGray, '.'),
+    };
+
+    let mut out = String::from("   A B C D E F G H I J     A B C D E F G H I J\n");
+    for (y, (shiprow, oppshiprow)) in Iterator::zip(ships.iter(), oppships.iter()).enumerate() {
+        let own = (0..10).map(|x| cell(info.selfhits[y][x], shiprow[x]));
+        let opp = (0..10).map(|x| cell(info.opphits[y][x], oppshiprow[x]));
+        out += &format!("{:>2} ", y + 1);
+        for (i, (color, symbol)) in Iterator::chain(own, opp).enumerate() {
+            if i == 10 {
+                out += &format!("{ResetColor}  {:>2} ", y + 1);
+            }
+            out += &format!("{}{symbol} ", SetForegroundColor(color.into()));
+        }
+        out += &format!("{ResetColor}\n");
     }
+    out
+}
+
+/// undoes `Interface::new` on `out`, the terminal's output, however the
+/// client is left, Ctrl-C included
+fn restoreterminal(out: &mut impl io::Write) -> io::Result<()> {
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(
+        out,
+        event::DisableMouseCapture,
+        crossterm::terminal::LeaveAlternateScreen,
+        crossterm::cursor::Show,
+    )
+}
+
+/// raised on Ctrl-C, which raw mode delivers as a key press instead of SIGINT
+fn interrupted() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "interrupted")
+}
+
+/// ships placement starts from: the favourite layout code if it suits
+/// `fleet`, else the standard seed; also whether a favourite was passed over
+fn startships(favourite: Option<&str>, fleet: &logic::FleetSpec) -> (logic::Ships, bool) {
+    let chosen = favourite
+        .and_then(|code| logic::Ships::fromcode(code).ok())
+        .filter(|ships| logic::Ships::validate(ships.asslice(), fleet).is_ok());
+    let passedover = favourite.is_some() && chosen.is_none();
+    let ships = seededorrandom(chosen.or_else(|| seedships(fleet)), fleet);
+    (ships, passedover)
+}
+
+/// `seeded` if seeding worked out, else a random placement of `fleet`, so a
+/// bad seed never leaves placement without ships
+fn seededorrandom(seeded: Option<logic::Ships>, fleet: &logic::FleetSpec) -> logic::Ships {
+    seeded
+        .or_else(|| fleet.random(&mut rand::rng()))
+        .unwrap_or_else(logic::randomplacement)
+}
+
+/// lines the fleet up in the top left columns, longest ship first, with a
+/// gap between ships that may not touch
+fn seedships(fleet: &logic::FleetSpec) -> Option<logic::Ships> {
+    let spacing = if fleet.istouchforbidden() { 2 } else { 1 };
+    let mut ships = Vec::with_capacity(fleet.lengths().len());
+    for (i, &len) in fleet.lengths().iter().rev().enumerate() {
+        let pos = logic::Position::fromcoords(i as u8 * spacing, 0)?;
+        ships.push(logic::Ship::try_from(logic::ShipPlan::Vertical { pos, len }).ok()?);
+    }
+    logic::Ships::validate(&ships, fleet).ok()
+}
+
+/// board coordinates around the two boards filling `rect`; with two board
+/// rows per terminal row, each row is labeled with the upper one
+fn drawlabels(f: &mut ratatui::Frame, geometry: Geometry, rect: layout::Rect) {
+    let columns: String = (0..10)
+        .filter_map(|x| logic::Position::fromcoords(x, 0))
+        .map(|pos| pos.toboard()[0])
+        .collect();
+    // the left board has one border column, the right one shares it
+    for x in [rect.x + 1, rect.x + 12] {
+        f.render_widget(
+            text::Line::from(columns.clone()).dark_gray(),
+            layout::Rect::new(x, rect.y - 1, 10, 1),
+        );
+    }
+    for row in 0..geometry.rows {
+        let y = (row * 10 / geometry.rows) as u8;
+        let Some(pos) = logic::Position::fromcoords(0, y) else {
+            continue;
+        };
+        f.render_widget(
+            text::Line::from(pos.toboard()[1])
+                .dark_gray()
+                .right_aligned(),
+            layout::Rect::new(rect.x - LABELWIDTH, rect.y + 1 + row, LABELWIDTH, 1),
+        );
+    }
+}
+
+/// titles of the own and the target board from the own and the opponent's
+/// name, "you" and "opponent" for whoever gave none
+fn boardtitles([name, oppname]: [Option<&str>; 2], width: usize) -> [String; 2] {
+    [name.unwrap_or("you"), oppname.unwrap_or("opponent")].map(|name| truncate(name, width))
+}
+
+/// `text` cut down to `width` characters, ending in an ellipsis if it had to
+/// be cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return String::from(text);
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+/// asks for a larger terminal instead, if the frame cannot hold `need`;
+/// the caller skips its own layout then
+fn fits(f: &mut ratatui::Frame, need: layout::Size) -> bool {
+    let area = f.area();
+    if area.width >= need.width && area.height >= need.height {
+        return true;
+    }
+    let prompt = widgets::Paragraph::new(format!(
+        "resize your terminal (need {}x{})",
+        need.width, need.height
+    ))
+    .centered()
+    .wrap(widgets::Wrap { trim: true });
+    let rect = layout::Rect {
+        y: area.y + area.height / 2,
+        height: u16::min(area.height - area.height / 2, 2),
+        ..area
+    };
+    f.render_widget(prompt, rect);
+    false
 }
 
 fn centerrectinrect(rect: layout::Rect, size: layout::Size) -> layout::Rect {
@@ -598,8 +1973,8 @@ fn centerrectinrect(rect: layout::Rect, size: layout::Size) -> layout::Rect {
     }
 }
 
-fn drawships(ctx: &mut canvas::Context, ships: &[logic::Ship; 5]) {
-    for (ship, color) in Iterator::zip(ships.iter(), SHIPCOLOR.into_iter()) {
+fn drawships(ctx: &mut canvas::Context, theme: &Theme, ships: &[logic::Ship]) {
+    for (ship, color) in Iterator::zip(ships.iter(), theme.ships) {
         let line = match ship.into() {
             logic::ShipPlan::Horizontal { pos, len } => {
                 let (x, y) = pos.coords();
@@ -626,7 +2001,11 @@ fn drawships(ctx: &mut canvas::Context, ships: &[logic::Ship; 5]) {
     }
 }
 
-fn drawhits(ctx: &mut canvas::Context, hits: &[[Option<logic::AttackInfo>; 10]; 10]) {
+/// glyphs printed over hits and misses when colors alone are not enough
+const HITGLYPH: &str = "X";
+const MISSGLYPH: &str = "·";
+
+fn drawhits(ctx: &mut canvas::Context, theme: &Theme, hits: &logic::HitGrid) {
     let (hit, missed): (Vec<_>, Vec<_>) = (0..10)
         .flat_map(|x| (0..10).map(move |y| (x, y)))
         .filter_map(|(x, y)| hits[y][x].map(|attackinfo| (attackinfo, x as f64, (9 - y) as f64)))
@@ -636,135 +2015,588 @@ fn drawhits(ctx: &mut canvas::Context, hits: &[[Option<logic::AttackInfo>; 10];
         });
     ctx.draw(&canvas::Points {
         coords: &hit,
-        color: ATTACKHITCOLOR,
+        color: theme.hit,
     });
     ctx.draw(&canvas::Points {
         coords: &missed,
-        color: ATTACKMISSCOLOR,
+        color: theme.miss,
     });
 }
 
-fn moveship(
-    term: &mut ratatui::DefaultTerminal,
-    x: &mut u8,
-    y: &mut u8,
-    ships: &mut [logic::Ship; 5],
-    idx: usize,
-) -> io::Result<()> {
-    let (shiplenoff, shiplen, mut horizontal) = match ships[idx].into() {
-        logic::ShipPlan::Horizontal { pos, len } => (*x - pos.coords().0, len, true),
-        logic::ShipPlan::Vertical { pos, len } => (*y - pos.coords().1, len, false),
-    };
+/// one glyph per terminal cell, printed over what `drawhits` painted
+fn drawglyphs(ctx: &mut canvas::Context, theme: &Theme, geometry: Geometry, hits: &logic::HitGrid) {
+    for (x, row, info) in geometry.glyphcells(hits) {
+        let glyph = match info {
+            logic::AttackInfo::Hit(_) => HITGLYPH.black().bold().bg(theme.hit),
+            logic::AttackInfo::Miss => MISSGLYPH.black().bg(theme.miss),
+        };
+        ctx.print(x as f64, geometry.labely(row), glyph);
+    }
+}
 
-    loop {
-        let mut checkready = false;
-        match event::read()? {
-            event::Event::Key(kevent) if kevent.kind == KeyEventKind::Press => match kevent.code {
-                KeyCode::Char('a') | KeyCode::Left if *x > 0 => *x -= 1,
-                KeyCode::Char('w') | KeyCode::Up if *y > 0 => *y -= 1,
-                KeyCode::Char('d') | KeyCode::Right if *x < 9 => *x += 1,
-                KeyCode::Char('s') | KeyCode::Down if *y < 9 => *y += 1,
-                KeyCode::Char('r') => {
-                    horizontal ^= true;
-                }
-                KeyCode::Char(' ') => checkready = true,
-                KeyCode::Char('q') => {
-                    return Err(io::Error::new(io::ErrorKind::Other, "player interrupted"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{buffer, widgets::Widget};
+
+    fn grid(shots: &[(u8, u8, logic::AttackInfo)]) -> logic::HitGrid {
+        let mut hits = logic::HitGrid::new();
+        for &(x, y, info) in shots {
+            hits.apply(logic::Position::fromcoords(x, y).unwrap(), info)
+                .unwrap();
+        }
+        hits
+    }
+
+    #[test]
+    fn shutdownrestoresterminal() {
+        let mut out = Vec::new();
+        restoreterminal(&mut out).unwrap();
+        assert!(!crossterm::terminal::is_raw_mode_enabled().unwrap());
+        let out = String::from_utf8(out).unwrap();
+        // mouse reports off, back on the main screen, cursor shown, in order
+        let sequences = ["\x1b[?1000l", "\x1b[?1049l", "\x1b[?25h"];
+        let found: Vec<_> = sequences.iter().map(|seq| out.find(seq)).collect();
+        assert!(found.iter().all(Option::is_some), "{out:?}");
+        assert!(found.is_sorted());
+    }
+
+    /// everything a `ClientInfo` borrows, to draw without a client
+    struct Seat {
+        ships: logic::Ships,
+        selfhits: logic::HitGrid,
+        opphits: logic::HitGrid,
+        server: prot::ServerInfo,
+        message: Vec<client::Message>,
+        shots: Vec<client::Shot>,
+    }
+
+    impl Seat {
+        fn new() -> Seat {
+            Seat {
+                ships: seedships(&logic::FleetSpec::default()).unwrap(),
+                selfhits: logic::HitGrid::new(),
+                opphits: logic::HitGrid::new(),
+                server: prot::ServerInfo::default(),
+                message: Vec::new(),
+                shots: Vec::new(),
+            }
+        }
+
+        fn info(&self) -> client::ClientInfo<'_> {
+            client::ClientInfo {
+                ships: self.ships.asslice(),
+                selfhits: &self.selfhits,
+                opphits: &self.opphits,
+                server: &self.server,
+                name: None,
+                oppname: None,
+                message: &self.message,
+                shots: &self.shots,
+                summary: None,
+                oppremaining: 5,
+                selfremaining: 5,
+                turn: None,
+                remaining: None,
+                oppleft: None,
+                oppaway: None,
+                pause: None,
+                chat: &[],
+                spectating: false,
+                fleets: None,
+                score: client::MatchScore::default(),
+            }
+        }
+    }
+
+    /// the text of `lines`, without styles
+    fn plain(lines: &[text::Line]) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn earliermessagesindicated() {
+        let mut seat = Seat::new();
+        seat.message = (0..5)
+            .map(|i| {
+                if i % 2 == 0 {
+                    client::Message::ShipHit
+                } else {
+                    client::Message::OppShipMissed
                 }
+            })
+            .collect();
+        let lines = |height| plain(&messagelines(seat.info(), height, |_| true));
+
+        // all fit, newest first
+        assert_eq!(
+            lines(5),
+            [
+                "ship hit",
+                "you missed",
+                "ship hit",
+                "you missed",
+                "ship hit"
+            ]
+        );
+        assert_eq!(lines(9).len(), 5);
+        // the indicator takes a line of its own
+        assert_eq!(
+            lines(4),
+            ["(2 earlier messages)", "ship hit", "you missed", "ship hit"]
+        );
+        assert_eq!(lines(2), ["(4 earlier messages)", "ship hit"]);
+        assert_eq!(lines(1), ["(5 earlier messages)"]);
+        assert!(lines(0).is_empty());
+
+        // filtered out messages are not counted as hidden
+        let own = plain(&messagelines(seat.info(), 2, |message| {
+            message == client::Message::ShipHit
+        }));
+        assert_eq!(own, ["(2 earlier messages)", "ship hit"]);
+    }
+
+    #[test]
+    fn ansiboardsdrawn() {
+        use crossterm::style::SetForegroundColor;
+
+        let mut seat = Seat::new();
+        seat.selfhits = grid(&[
+            (0, 0, logic::AttackInfo::Hit(false)),
+            (9, 9, logic::AttackInfo::Miss),
+        ]);
+        seat.opphits = grid(&[
+            (2, 3, logic::AttackInfo::Miss),
+            (5, 5, logic::AttackInfo::Hit(false)),
+        ]);
+        let theme = Theme::default();
+        let out = ansiboards(&theme, &seat.info());
+
+        // without escapes, the longest ship is hit in the corner
+        let mut plain = String::new();
+        let mut escaped = false;
+        for c in out.chars() {
+            match c {
+                '\x1b' => escaped = true,
+                'm' if escaped => escaped = false,
+                c if !escaped => plain.push(c),
                 _ => {}
-            },
-            _ => {}
+            }
         }
+        let lines: Vec<_> = plain.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(lines[0], "   A B C D E F G H I J     A B C D E F G H I J");
+        assert_eq!(lines[1], " 1 X # # # # . . . . .    1 . . . . . . . . . . ");
+        assert_eq!(lines[4], " 4 # # . . . . . . . .    4 . . o . . . . . . . ");
+        assert_eq!(lines[6], " 6 . . . . . . . . . .    6 . . . . . X . . . . ");
+        assert_eq!(
+            lines[10],
+            "10 . . . . . . . . . o   10 . . . . . . . . . . "
+        );
 
-        *x = u8::clamp(
-            *x,
-            if horizontal { shiplenoff } else { 0 },
-            if horizontal {
-                10 - shiplen + shiplenoff
-            } else {
-                9
+        // each cell is preceded by its color
+        let hit = SetForegroundColor(theme.hit.into()).to_string();
+        let ship = SetForegroundColor(theme.ships[1].into()).to_string();
+        assert!(out.contains(&format!("{hit}X {ship}# ")));
+    }
+
+    /// `rendergame` on a terminal of `width` by `height`, with the target
+    /// board's area and the text of every row
+    fn drawn(
+        (width, height): (u16, u16),
+        view: View,
+        info: &client::ClientInfo,
+        extras: Extras,
+    ) -> (Option<layout::Rect>, Vec<String>) {
+        let backend = ratatui::backend::TestBackend::new(width, height);
+        let mut term = ratatui::Terminal::new(backend).unwrap();
+        let geometry = Geometry::new(DEFAULTCELLASPECT);
+        let mut target = None;
+        let frame = term
+            .draw(|f| target = rendergame(f, geometry, view, &Theme::default(), info, extras))
+            .unwrap();
+        let rows = (0..height)
+            .map(|y| (0..width).map(|x| frame.buffer[(x, y)].symbol()).collect())
+            .collect();
+        (target, rows)
+    }
+
+    #[test]
+    fn splitlogbyorigin() {
+        let mut seat = Seat::new();
+        seat.message = vec![
+            client::Message::SuccessfullyConnected,
+            client::Message::ShipHit,
+            client::Message::OppShipMissed,
+        ];
+        // two lines below the boards, the newest message goes first
+        let (_, rows) = drawn((25, 12), View::default(), &seat.info(), Extras::default());
+        assert_eq!(rows[10], "  (2 earlier messages)   ");
+        assert_eq!(rows[11], "  you missed             ");
+
+        // shots at the own board and the rest on the left, own shots right
+        let view = View {
+            splitlog: true,
+            ..View::default()
+        };
+        let (_, rows) = drawn((25, 12), view, &seat.info(), Extras::default());
+        assert_eq!(rows[10], "  ship hit   you missed  ");
+        assert!(rows[11].starts_with("  connected"));
+        assert_eq!(rows[11][13..].trim(), "");
+    }
+
+    #[test]
+    fn lastshotsnewestfirst() {
+        let shot = |incoming, x, y, info| client::Shot {
+            incoming,
+            pos: logic::Position::fromcoords(x, y).unwrap(),
+            info,
+        };
+        let mut seat = Seat::new();
+        seat.shots = vec![
+            shot(false, 0, 0, logic::AttackInfo::Miss),
+            shot(true, 1, 1, logic::AttackInfo::Miss),
+            shot(false, 2, 2, logic::AttackInfo::Hit(false)),
+            shot(true, 0, 0, logic::AttackInfo::Hit(false)),
+            shot(false, 2, 3, logic::AttackInfo::Hit(true)),
+            shot(true, 9, 9, logic::AttackInfo::Miss),
+        ];
+        let lines = shotlines(&seat.shots, LASTSHOTS);
+        assert_eq!(plain(&lines), ["J10 ✗", "C4 ✓", "A1 ✓", "C3 ✓", "B2 ✗"]);
+        // the opponent's shots in the opponent's color
+        let cyan: Vec<_> = lines
+            .iter()
+            .map(|line| line.style.fg == Some(style::Color::Cyan))
+            .collect();
+        assert_eq!(cyan, [true, false, true, false, true]);
+        assert!(shotlines(&seat.shots, 0).is_empty());
+
+        // drawn over the top right corner, only when asked for
+        let view = View {
+            lastshots: true,
+            ..View::default()
+        };
+        let (_, rows) = drawn((25, 12), view, &seat.info(), Extras::default());
+        assert!(rows[0].ends_with("J10 ✗ "));
+        assert!(rows[1].ends_with("C4 ✓  "));
+        assert!(rows[4].ends_with("B2 ✗ ┃"));
+        let (_, rows) = drawn((25, 12), View::default(), &seat.info(), Extras::default());
+        assert!(!rows.iter().any(|row| row.contains('✗')));
+    }
+
+    #[test]
+    fn smallframeasksresize() {
+        let seat = Seat::new();
+        // the prompt wraps below the middle of whatever room there is
+        let (target, rows) = drawn((24, 8), View::default(), &seat.info(), Extras::default());
+        assert_eq!(target, None);
+        assert_eq!(rows[4].trim(), "resize your terminal");
+        assert_eq!(rows[5].trim(), "(need 25x8)");
+        let (_, rows) = drawn((40, 7), View::default(), &seat.info(), Extras::default());
+        assert_eq!(rows[3].trim(), "resize your terminal (need 25x8)");
+
+        // down to nothing at all, nothing panics
+        for size in [(25, 7), (1, 1), (0, 0), (3, 40), (200, 2)] {
+            let (target, _) = drawn(size, View::default(), &seat.info(), Extras::default());
+            assert_eq!(target, None);
+        }
+        let (target, _) = drawn((25, 8), View::default(), &seat.info(), Extras::default());
+        assert!(target.is_some());
+    }
+
+    #[test]
+    fn rendergamesharedlayout() {
+        let seat = Seat::new();
+        let geometry = Geometry::new(DEFAULTCELLASPECT);
+        let area = layout::Rect::new(0, 0, 80, 24);
+        // the layout every board screen computed for itself before, with
+        // the coordinate labels in front
+        let outer = centerrectinrect(area, geometry.size());
+        let rectright = layout::Rect {
+            x: outer.x + LABELWIDTH + 11,
+            y: outer.y + 1,
+            width: 12,
+            height: outer.height - 1,
+        };
+        let expected = widgets::Block::bordered().inner(rectright);
+
+        let screens = [
+            Extras::default(),
+            // taking a turn
+            Extras {
+                border: Some(style::Color::Green),
+                cursor: Some(((4, 4), style::Color::Yellow)),
+                status: true,
+                header: vec![text::Line::from("select a target")],
+                ..Extras::default()
             },
-        );
-        *y = u8::clamp(
-            *y,
-            if horizontal { 0 } else { shiplenoff },
-            if horizontal {
-                9
-            } else {
-                10 - shiplen + shiplenoff
+            // the end of the game, but for the banner
+            Extras {
+                header: vec![text::Line::from("a summary"); 2],
+                ..Extras::default()
             },
+        ];
+        let drawings: Vec<_> = screens
+            .into_iter()
+            .map(|extras| drawn((80, 24), View::default(), &seat.info(), extras))
+            .collect();
+        // the own board and everything above it look the same on every screen
+        let ownboard = |rows: &[String]| -> Vec<String> {
+            rows[..(outer.y + outer.height - 1) as usize]
+                .iter()
+                .map(|row| row.chars().take(rectright.x as usize).collect())
+                .collect()
+        };
+        for (target, rows) in &drawings {
+            assert_eq!(*target, Some(expected));
+            assert_eq!(ownboard(rows), ownboard(&drawings[0].1));
+        }
+
+        // which goes on top, centered on both boards
+        let extras = Extras {
+            banner: Some(("victory", style::Color::Green)),
+            ..Extras::default()
+        };
+        let (target, rows) = drawn((80, 24), View::default(), &seat.info(), extras);
+        assert_eq!(target, Some(expected));
+        let middle = (outer.y + 1 + (outer.height - 1) / 2) as usize;
+        assert!(rows[middle].contains(" victory "));
+    }
+
+    #[test]
+    fn cellaspectgeometry() {
+        let cases = [
+            (2.0, symbols::Marker::HalfBlock, 5),
+            (1.5, symbols::Marker::HalfBlock, 5),
+            (1.4, symbols::Marker::Block, 10),
+            (1.0, symbols::Marker::Block, 10),
+        ];
+        for (cellaspect, marker, rows) in cases {
+            let geometry = Geometry::new(cellaspect);
+            assert_eq!((geometry.marker, geometry.rows), (marker, rows));
+            assert_eq!(geometry.boardsize(), layout::Size::new(12, rows + 2));
+            assert_eq!(
+                geometry.size(),
+                layout::Size::new(LABELWIDTH + 23, rows + 3)
+            );
+
+            // a board cell is one column wide and rows / 10 terminal rows tall
+            let aspect = cellaspect * rows as f32 / 10.0;
+            assert!((0.7..=1.4).contains(&aspect), "{cellaspect}: {aspect}");
+
+            // the canvas corners fill the bordered board exactly
+            let area = layout::Rect::new(0, 0, 12, rows + 2);
+            let mut buf = buffer::Buffer::empty(area);
+            geometry
+                .canvas()
+                .block(widgets::Block::bordered())
+                .paint(|ctx| {
+                    ctx.draw(&canvas::Points {
+                        coords: &[(0.0, 9.0), (9.0, 0.0)],
+                        color: style::Color::White,
+                    })
+                })
+                .render(area, &mut buf);
+            let filled: Vec<_> = (1..=rows)
+                .flat_map(|y| (1..=10).map(move |x| (x, y)))
+                .filter(|&pos| buf[pos].symbol() != " ")
+                .collect();
+            assert_eq!(filled, [(1, 1), (10, rows)], "{cellaspect}");
+        }
+    }
+
+    #[test]
+    fn glyphsshareterminalrows() {
+        let hits = grid(&[
+            (3, 0, logic::AttackInfo::Miss),
+            (3, 1, logic::AttackInfo::Hit(false)),
+            (5, 2, logic::AttackInfo::Miss),
+            (9, 9, logic::AttackInfo::Hit(true)),
+        ]);
+        let halfblock = Geometry::new(2.0);
+        assert_eq!(
+            halfblock.glyphcells(&hits),
+            [
+                (3, 0, logic::AttackInfo::Hit(false)),
+                (5, 1, logic::AttackInfo::Miss),
+                (9, 4, logic::AttackInfo::Hit(true)),
+            ]
         );
+        assert_eq!(Geometry::new(1.0).glyphcells(&hits).len(), 4);
+
+        // every glyph lands on the terminal row of its board rows
+        let area = layout::Rect::new(0, 0, 10, halfblock.rows);
+        let mut buf = buffer::Buffer::empty(area);
+        halfblock
+            .canvas()
+            .paint(|ctx| drawglyphs(ctx, &Theme::default(), halfblock, &hits))
+            .render(area, &mut buf);
+        assert_eq!(buf[(3, 0)].symbol(), HITGLYPH);
+        assert_eq!(buf[(5, 1)].symbol(), MISSGLYPH);
+        assert_eq!(buf[(9, 4)].symbol(), HITGLYPH);
+        let glyphs = buf
+            .content()
+            .iter()
+            .filter(|cell| cell.symbol() != " ")
+            .count();
+        assert_eq!(glyphs, 3);
+    }
 
-        ships[idx] = if horizontal {
-            logic::ShipPlan::Horizontal {
-                pos: logic::Position::fromcoords(*x - shiplenoff, *y).unwrap(),
-                len: shiplen,
+    #[test]
+    fn titlesnameboth() {
+        assert_eq!(boardtitles([None, None], 10), ["you", "opponent"]);
+        assert_eq!(
+            boardtitles([Some("ada"), Some("grace hopper")], 10),
+            ["ada", "grace hop…"]
+        );
+        assert_eq!(boardtitles([Some("ten chars!"), None], 10)[0], "ten chars!");
+        assert_eq!(boardtitles([None, None], 3), ["you", "op…"]);
+        assert_eq!(truncate("anyone", 1), "…");
+        assert_eq!(truncate("anyone", 0), "");
+
+        assert_eq!(Screen::Turn.windowtitle([None, None]), "ziel — your turn");
+        assert_eq!(
+            Screen::Turn.windowtitle([None, Some("grace")]),
+            "ziel — your turn — you vs grace"
+        );
+        assert_eq!(
+            Screen::Loss.windowtitle([Some("ada"), Some("grace")]),
+            "ziel — loss — ada vs grace"
+        );
+    }
+
+    #[test]
+    fn windowtitleperscreen() {
+        let screens = [
+            (Screen::Placement, "ziel — placing ships"),
+            (Screen::Turn, "ziel — your turn"),
+            (Screen::Waiting, "ziel — waiting"),
+            (Screen::Victory, "ziel — victory"),
+            (Screen::Loss, "ziel — loss"),
+        ];
+        for (screen, title) in screens {
+            assert_eq!(screen.windowtitle([None, None]), title);
+            // a name of our own alone does not make a matchup
+            assert_eq!(screen.windowtitle([Some("ada"), None]), title);
+            assert_eq!(
+                screen.windowtitle([Some("ada"), Some("grace")]),
+                format!("{title} — ada vs grace")
+            );
+        }
+    }
+
+    #[test]
+    fn escrestoresplacement() {
+        let mut ships = seedships(&logic::FleetSpec::default())
+            .unwrap()
+            .asslice()
+            .to_vec();
+        let before = format!("{ships:?}");
+        let (mut x, mut y) = match ships[0].into() {
+            logic::ShipPlan::Horizontal { pos, .. } | logic::ShipPlan::Vertical { pos, .. } => {
+                pos.coords()
             }
-        } else {
-            logic::ShipPlan::Vertical {
-                pos: logic::Position::fromcoords(*x, *y - shiplenoff).unwrap(),
-                len: shiplen,
+        };
+        let origin = (x, y);
+        let mut carried = Carried::pickup(ships[0], origin);
+        let key = |code| Some(event::KeyEvent::from(code));
+
+        for code in [
+            KeyCode::Right,
+            KeyCode::Down,
+            KeyCode::Char('r'),
+            KeyCode::Down,
+        ] {
+            let step = carried.press(key(code), (&mut x, &mut y), &mut ships, 0, false);
+            assert!(matches!(step, Ok(Carry::Moving { .. })));
+        }
+        assert_ne!(format!("{ships:?}"), before);
+
+        let step = carried.press(key(KeyCode::Esc), (&mut x, &mut y), &mut ships, 0, false);
+        assert_eq!(step.unwrap(), Carry::Cancelled);
+        assert_eq!(format!("{ships:?}"), before);
+        assert_eq!((x, y), origin);
+    }
+
+    #[test]
+    fn favouriteseedsplacement() {
+        use rand::SeedableRng;
+
+        let classic = logic::FleetSpec::default();
+        let seed = seedships(&classic).unwrap().tocode();
+        let saved = logic::randomfleet(&mut rand::rngs::StdRng::seed_from_u64(7)).tocode();
+        assert_ne!(saved, seed);
+        let start = |favourite, fleet| {
+            let (ships, passedover) = startships(favourite, fleet);
+            (ships.tocode(), passedover)
+        };
+
+        assert_eq!(start(None, &classic), (seed.clone(), false));
+        assert_eq!(start(Some(&saved), &classic), (saved.clone(), false));
+        assert_eq!(start(Some("not a code"), &classic), (seed.clone(), true));
+        assert_eq!(start(Some(""), &classic), (seed.clone(), true));
+
+        // a layout that breaks the game's rules is passed over too
+        let notouch = classic.clone().notouch(true);
+        let spaced = seedships(&notouch).unwrap().tocode();
+        assert_eq!(start(Some(&seed), &notouch), (spaced, true));
+    }
+
+    #[test]
+    fn badseedfallsback() {
+        let fleet = logic::FleetSpec::new([2, 3, 4]).unwrap().notouch(true);
+        for _ in 0..20 {
+            let ships = seededorrandom(None, &fleet);
+            assert!(logic::Ships::validate(ships.asslice(), &fleet).is_ok());
+        }
+
+        // a fleet that never fits still leaves something to place
+        let cramped = logic::FleetSpec::new([10; 5]).unwrap().notouch(true);
+        assert_eq!(seededorrandom(None, &cramped).asslice().len(), 5);
+    }
+
+    #[test]
+    fn redrawneachtick() {
+        /// input that never comes, counting how long it was waited for
+        struct Idle(Vec<time::Duration>);
+
+        impl Input for Idle {
+            fn poll(&mut self, timeout: time::Duration) -> io::Result<bool> {
+                self.0.push(timeout);
+                Ok(false)
+            }
+
+            fn read(&mut self) -> io::Result<event::Event> {
+                unreachable!("nothing was polled")
             }
         }
-        .try_into()
-        .unwrap();
 
-        let valid = logic::validshippos(ships);
+        let tickrate = time::Duration::from_millis(40);
+        let mut idle = Idle(Vec::new());
+        let mut draws = 0;
+        while idle.0.len() < 5 {
+            assert!(nexttick(&mut idle, tickrate).unwrap().is_none());
+            draws += 1;
+        }
+        assert_eq!(draws, 5);
+        assert_eq!(idle.0, [tickrate; 5]);
+
+        /// input that is already waiting
+        struct Typed(Vec<event::Event>);
 
-        if checkready && valid {
-            return Ok(());
+        impl Input for Typed {
+            fn poll(&mut self, _: time::Duration) -> io::Result<bool> {
+                Ok(!self.0.is_empty())
+            }
+
+            fn read(&mut self) -> io::Result<event::Event> {
+                Ok(self.0.remove(0))
+            }
         }
 
-        term.draw(|f| {
-            let [boardx, boardy] = logic::Position::fromcoords(*x, *y).unwrap().toboard();
-            let canvas = canvas::Canvas::default()
-                .block(
-                    widgets::Block::bordered()
-                        .border_style(if valid {
-                            style::Style::new().green()
-                        } else {
-                            style::Style::new().red()
-                        })
-                        .border_type(widgets::BorderType::Thick)
-                        .title_bottom(text::Line::raw(format!("{boardx}{boardy}"))),
-                )
-                .x_bounds([0.0, 9.0])
-                .y_bounds([0.0, 9.0])
-                .marker(symbols::Marker::HalfBlock)
-                .paint(|ctx| {
-                    for (ship, color) in Iterator::zip(ships.iter(), SHIPCOLOR.into_iter())
-                        .chain(iter::once((&ships[idx], SHIPCOLOR[idx])))
-                    {
-                        let line = match ship.into() {
-                            logic::ShipPlan::Horizontal { pos, len } => {
-                                let (x, y) = pos.coords();
-                                canvas::Line {
-                                    x1: x as f64,
-                                    y1: (9 - y) as f64,
-                                    x2: (x + len - 1) as f64,
-                                    y2: (9 - y) as f64,
-                                    color,
-                                }
-                            }
-                            logic::ShipPlan::Vertical { pos, len } => {
-                                let (x, y) = pos.coords();
-                                canvas::Line {
-                                    x1: x as f64,
-                                    y1: (9 - y) as f64,
-                                    x2: x as f64,
-                                    y2: (9 - (y + len - 1)) as f64,
-                                    color,
-                                }
-                            }
-                        };
-                        ctx.draw(&line);
-                    }
-                    ctx.draw(&canvas::Points {
-                        coords: &[(*x as f64, (9 - *y) as f64)],
-                        color: style::Color::White,
-                    });
-                });
-            f.render_widget(canvas, centerrectinrect(f.area(), layout::Size::new(12, 7)));
-        })?;
+        let key = event::Event::Key(KeyCode::Char('a').into());
+        let mut typed = Typed(vec![key.clone()]);
+        assert_eq!(nexttick(&mut typed, tickrate).unwrap(), Some(key));
+        assert_eq!(nexttick(&mut typed, tickrate).unwrap(), None);
     }
 }